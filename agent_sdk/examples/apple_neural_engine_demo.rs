@@ -89,21 +89,16 @@ async fn main() -> Result<()> {
     {
         println!("\n🧠 Testing Apple Neural Engine inference...");
 
-        use nebula_agent_sdk::npu::{DataType, InferenceInput, InferenceRequest};
+        use nebula_agent_sdk::npu::{InferenceRequest, Tensor};
         use std::collections::HashMap;
         use std::time::Duration;
 
         // Create test input (4 float32 values)
         let test_data = vec![1.0f32, 2.0f32, 3.0f32, 4.0f32];
-        let input_bytes: Vec<u8> = test_data.iter().flat_map(|&f| f.to_le_bytes()).collect();
 
         let request = InferenceRequest {
             model_path: "apple_neural_test.mlmodel".to_string(),
-            inputs: vec![InferenceInput {
-                data: input_bytes,
-                shape: vec![1, 4],
-                data_type: DataType::Float32,
-            }],
+            inputs: vec![Tensor::from_f32(&test_data, vec![1, 4])?],
             timeout: Duration::from_secs(5),
             priority: nebula_agent_sdk::npu::TaskPriority::High,
             agent_id: Some(1),
@@ -116,15 +111,11 @@ async fn main() -> Result<()> {
                 println!("  - Execution time: {:?}", response.execution_time);
                 println!(
                     "  - Output data size: {} bytes",
-                    response.outputs[0].data.len()
+                    response.outputs[0].data().len()
                 );
 
                 // Convert output back to float32 for display
-                let output_floats: Vec<f32> = response.outputs[0]
-                    .data
-                    .chunks_exact(4)
-                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                    .collect();
+                let output_floats: Vec<f32> = response.outputs[0].view()?;
                 println!("  - Output values: {:?}", output_floats);
             }
             Err(e) => {
@@ -67,11 +67,10 @@ async fn main() -> Result<()> {
 
         let request = InferenceRequest {
             model_path: "mock_model.onnx".to_string(),
-            inputs: vec![nebula_agent_sdk::npu::InferenceInput {
-                data: vec![1, 2, 3, 4],
-                shape: vec![1, 4],
-                data_type: nebula_agent_sdk::npu::DataType::Float32,
-            }],
+            inputs: vec![nebula_agent_sdk::npu::Tensor::from_f32(
+                &[1.0, 2.0, 3.0, 4.0],
+                vec![1, 4],
+            )?],
             timeout: Duration::from_secs(30),
             priority: nebula_agent_sdk::npu::TaskPriority::Normal,
             agent_id: Some(1),
@@ -22,7 +22,7 @@ impl AgentHandler for EchoAgent {
         {
             let mut ctx = context.lock().unwrap();
             ctx.update_activity();
-            ctx.resource_usage.message_count += 1;
+            ctx.resource_usage.message_count.increment();
         }
 
         // Echo the message back (in a real scenario, you'd send to another agent)
@@ -89,6 +89,7 @@ async fn main() -> AnyResult<()> {
             message_type: MessageType::Data,
             payload: b"Hello from NebulaOS!".to_vec(),
             timestamp: chrono::Utc::now().timestamp() as u64,
+            id: None,
         },
         Message {
             from: 0,
@@ -96,6 +97,7 @@ async fn main() -> AnyResult<()> {
             message_type: MessageType::InferenceRequest,
             payload: b"inference_data".to_vec(),
             timestamp: chrono::Utc::now().timestamp() as u64,
+            id: None,
         },
     ];
 
@@ -117,7 +119,7 @@ async fn main() -> AnyResult<()> {
         let ctx = context.lock().unwrap();
         println!(
             "📈 Messages processed: {}",
-            ctx.resource_usage.message_count
+            ctx.resource_usage.message_count.get()
         );
         println!(
             "⏰ Agent uptime: {:?}",
@@ -127,8 +129,7 @@ async fn main() -> AnyResult<()> {
 
     // Test ML handler
     let ml_handler = runtime.get_ml_handler();
-    let ml = ml_handler.lock().await;
-    println!("🧠 ML models loaded: {:?}", ml.get_loaded_models());
+    println!("🧠 ML models loaded: {:?}", ml_handler.get_loaded_models());
 
     // Graceful shutdown
     println!("🔄 Shutting down...");
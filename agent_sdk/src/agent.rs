@@ -3,31 +3,190 @@
 //! Defines the core agent structure, lifecycle management, and
 //! capabilities description for agents running on NebulaOS.
 
+use crate::backend::InferenceBackend;
+use crate::logging;
 use crate::message::{Message, MessageHandler};
+use crate::middleware::MessageMiddleware;
 use crate::types::*;
 use anyhow::Result;
-use log::{debug, error, info};
-use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc;
+use log::{debug, error, info, warn};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 /// Configuration options for an agent
 #[derive(Clone, Debug)]
 pub struct AgentConfig {
     pub name: String,
-    pub capabilities: AgentCapabilities,
+    /// `None` lets the runtime apply its default capabilities
+    pub capabilities: Option<AgentCapabilities>,
     pub max_message_queue_size: usize,
+    pub inbox_fairness: InboxFairness,
+    /// Free-form labels used to find the agent through `AgentQuery`
+    pub tags: Vec<String>,
+    /// How often a started agent publishes a [`Heartbeat`] (None = never)
+    pub heartbeat_interval: Option<std::time::Duration>,
+    /// How many recent message ids are remembered to drop duplicate
+    /// deliveries (None = every message is handled)
+    pub dedup_capacity: Option<usize>,
 }
 
 impl Default for AgentConfig {
     fn default() -> Self {
         Self {
             name: "unnamed_agent".to_string(),
-            capabilities: AgentCapabilities::default(),
+            capabilities: None,
             max_message_queue_size: 1000,
+            inbox_fairness: InboxFairness::default(),
+            tags: Vec::new(),
+            heartbeat_interval: None,
+            dedup_capacity: None,
         }
     }
 }
 
+/// Balance between control and normal messages in an agent's inbox
+///
+/// Control messages are handled before normal ones. Once `control_burst`
+/// control messages have been handled in a row, up to `normal_per_burst`
+/// waiting normal messages are handled before control messages resume, so
+/// a steady stream of control traffic cannot starve normal messages.
+///
+/// Messages of the same class are always handled in the order they were
+/// sent; only control and normal messages are reordered relative to each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InboxFairness {
+    pub control_burst: usize,
+    pub normal_per_burst: usize,
+}
+
+impl Default for InboxFairness {
+    fn default() -> Self {
+        Self {
+            control_burst: 16,
+            normal_per_burst: 1,
+        }
+    }
+}
+
+/// Per-class FIFO channels drained according to an [`InboxFairness`]
+struct Inbox {
+    control_rx: mpsc::UnboundedReceiver<Message>,
+    normal_rx: mpsc::UnboundedReceiver<Message>,
+    fairness: InboxFairness,
+    control_streak: usize,
+    normal_owed: usize,
+}
+
+impl Inbox {
+    /// Wait for the next message, or `None` once both channels are closed
+    async fn recv(&mut self) -> Option<Message> {
+        let (message, from_control) = if self.normal_owed > 0 {
+            tokio::select! {
+                biased;
+                Some(message) = self.normal_rx.recv() => (message, false),
+                Some(message) = self.control_rx.recv() => (message, true),
+                else => return None,
+            }
+        } else {
+            tokio::select! {
+                biased;
+                Some(message) = self.control_rx.recv() => (message, true),
+                Some(message) = self.normal_rx.recv() => (message, false),
+                else => return None,
+            }
+        };
+
+        if from_control {
+            self.control_streak += 1;
+            if self.control_streak >= self.fairness.control_burst.max(1) {
+                self.control_streak = 0;
+                self.normal_owed = self.fairness.normal_per_burst;
+            }
+        } else {
+            self.control_streak = 0;
+            self.normal_owed = self.normal_owed.saturating_sub(1);
+        }
+        Some(message)
+    }
+}
+
+/// Bounded set of the most recently seen message ids
+struct SeenIds {
+    ids: HashSet<u64>,
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl SeenIds {
+    fn new(capacity: usize) -> Self {
+        Self {
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Remember `id`, returning false if it was already seen
+    fn insert(&mut self, id: u64) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Lock one of an agent's mutexes, recovering it if a handler panicked
+/// while holding the lock so the agent stays usable
+pub(crate) fn lock_or_recover<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        warn!("Recovering agent state from a panicked handler");
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+/// Why a message could not be queued for an agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendError {
+    /// `max_message_queue_size` messages are already waiting
+    QueueFull,
+    /// The agent's message loop has shut down
+    Closed,
+    /// The agent is draining and accepts no new messages
+    Draining,
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SendError::QueueFull => write!(f, "agent message queue is full"),
+            SendError::Closed => write!(f, "agent message channel is closed"),
+            SendError::Draining => write!(f, "agent is draining"),
+        }
+    }
+}
+
+impl std::error::Error for SendError {}
+
+/// Outcome of [`Agent::drain`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrainReport {
+    /// Queued messages handled before the agent stopped
+    pub drained: usize,
+    /// Queued messages left unhandled when the timeout ran out
+    pub dropped: usize,
+}
+
 /// The main interface for an agent
 #[derive(Clone)]
 pub struct Agent {
@@ -35,20 +194,57 @@ pub struct Agent {
     pub config: AgentConfig,
     context: Arc<Mutex<AgentContext>>,
     message_tx: mpsc::UnboundedSender<Message>,
-    message_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<Message>>>>,
+    control_tx: mpsc::UnboundedSender<Message>,
+    inbox: Arc<Mutex<Option<Inbox>>>,
+    /// Messages sent but not yet picked up by the message loop
+    pending_messages: Arc<AtomicUsize>,
+    /// Cleared by `drain` to refuse new messages
+    accepting: Arc<AtomicBool>,
+    /// Senders whose messages are still taken once `accepting` is cleared
+    admitted: Arc<Mutex<HashSet<AgentId>>>,
+    /// Handler of a started agent, kept so shutdown can run its hook
+    handler: Arc<Mutex<Option<Arc<dyn AgentHandler + Send + Sync>>>>,
+    /// Message loop of a started agent, awaited on shutdown
+    loop_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    /// Asks the message loop to stop once its queue is empty
+    drain: CancellationToken,
+    /// Latest heartbeat, published while the agent runs
+    heartbeat: Arc<watch::Sender<Option<Heartbeat>>>,
+    /// Backend the agent runs inference delegated to it on
+    inference_backend: Arc<Mutex<Option<Arc<dyn InferenceBackend>>>>,
 }
 
 impl Agent {
     /// Create a new agent with the given configuration
     pub fn new(id: AgentId, config: AgentConfig) -> Self {
-        let (message_tx, message_rx) = mpsc::unbounded_channel();
+        let (message_tx, normal_rx) = mpsc::unbounded_channel();
+        let (control_tx, control_rx) = mpsc::unbounded_channel();
+        let inbox = Inbox {
+            control_rx,
+            normal_rx,
+            fairness: config.inbox_fairness,
+            control_streak: 0,
+            normal_owed: 0,
+        };
+
+        let mut context = AgentContext::new(id, config.name.clone());
+        context.capabilities = config.capabilities.clone().unwrap_or_default();
 
         Self {
             id,
             config: config.clone(),
-            context: Arc::new(Mutex::new(AgentContext::new(id, config.name.clone()))),
+            context: Arc::new(Mutex::new(context)),
             message_tx,
-            message_rx: Arc::new(Mutex::new(Some(message_rx))),
+            control_tx,
+            inbox: Arc::new(Mutex::new(Some(inbox))),
+            pending_messages: Arc::new(AtomicUsize::new(0)),
+            accepting: Arc::new(AtomicBool::new(true)),
+            admitted: Arc::default(),
+            handler: Arc::new(Mutex::new(None)),
+            loop_task: Arc::new(Mutex::new(None)),
+            drain: CancellationToken::new(),
+            heartbeat: Arc::new(watch::channel(None).0),
+            inference_backend: Arc::default(),
         }
     }
 
@@ -57,42 +253,213 @@ impl Agent {
         self.context.clone()
     }
 
+    /// Snapshot the agent's context for reading
+    ///
+    /// The lock is only held while the fields are copied, so monitors can
+    /// inspect the snapshot at leisure without contending with handlers.
+    pub fn snapshot_context(&self) -> AgentContextView {
+        lock_or_recover(&self.context).view()
+    }
+
+    /// Get the agent's usage counters
+    ///
+    /// The returned counters stay live, so metric readers can keep them and
+    /// poll without locking the context again.
+    pub fn resource_usage(&self) -> ResourceUsage {
+        lock_or_recover(&self.context).resource_usage.share()
+    }
+
+    /// Set the backend inference delegated to this agent runs on
+    pub fn set_inference_backend(&self, backend: Arc<dyn InferenceBackend>) {
+        *lock_or_recover(&self.inference_backend) = Some(backend);
+    }
+
+    /// Backend inference delegated to this agent runs on, if it has one
+    pub fn inference_backend(&self) -> Option<Arc<dyn InferenceBackend>> {
+        lock_or_recover(&self.inference_backend).clone()
+    }
+
     /// Send a message to this agent
+    ///
+    /// Control messages are handled ahead of other messages; see
+    /// [`InboxFairness`] for the ordering guarantee.
     pub fn send_message(&self, message: Message) -> Result<()> {
-        self.message_tx.send(message)?;
+        self.try_send(message).map_err(|(_, e)| e)?;
         debug!("Message sent to agent {}", self.id);
         Ok(())
     }
 
+    /// Send several messages in one pass. Messages that could not be
+    /// queued are returned with the reason, in their original order.
+    pub fn send_messages(
+        &self,
+        messages: impl IntoIterator<Item = Message>,
+    ) -> std::result::Result<(), Vec<(Message, SendError)>> {
+        let mut sent = 0;
+        let failures: Vec<_> = messages
+            .into_iter()
+            .filter_map(|message| match self.try_send(message) {
+                Ok(()) => {
+                    sent += 1;
+                    None
+                }
+                Err(failure) => Some(failure),
+            })
+            .collect();
+
+        debug!("Batch sent {} message(s) to agent {}", sent, self.id);
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    /// Queue one message, respecting `max_message_queue_size`
+    fn try_send(&self, message: Message) -> std::result::Result<(), (Message, SendError)> {
+        if !self.accepting.load(Ordering::SeqCst)
+            && !lock_or_recover(&self.admitted).contains(&message.from)
+        {
+            return Err((message, SendError::Draining));
+        }
+
+        // Count before sending so the message loop never decrements first
+        let max = self.config.max_message_queue_size;
+        if self
+            .pending_messages
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .is_err()
+        {
+            return Err((message, SendError::QueueFull));
+        }
+
+        let tx = if message.message_type.is_priority() {
+            &self.control_tx
+        } else {
+            &self.message_tx
+        };
+        if let Err(mpsc::error::SendError(message)) = tx.send(message) {
+            self.pending_messages.fetch_sub(1, Ordering::SeqCst);
+            return Err((message, SendError::Closed));
+        }
+        Ok(())
+    }
+
+    /// Refuse new messages except those sent by `peers`
+    ///
+    /// Other sends fail with [`SendError::Draining`], including after the
+    /// agent drains, so peers still draining can pass their work along.
+    pub fn stop_accepting(&self, peers: impl IntoIterator<Item = AgentId>) {
+        lock_or_recover(&self.admitted).extend(peers);
+        self.accepting.store(false, Ordering::SeqCst);
+    }
+
+    /// Whether the agent still takes messages from any sender
+    pub fn is_accepting(&self) -> bool {
+        self.accepting.load(Ordering::SeqCst)
+    }
+
+    /// Number of messages queued in the agent's channel and not yet handled
+    pub fn pending_messages(&self) -> usize {
+        self.pending_messages.load(Ordering::SeqCst)
+    }
+
+    /// Messages buffered for this agent; compare with [`Agent::queue_capacity`]
+    /// to detect buildup
+    pub fn queue_depth(&self) -> usize {
+        self.pending_messages()
+    }
+
+    /// Most messages that can be buffered before sends fail with
+    /// [`SendError::QueueFull`]
+    pub fn queue_capacity(&self) -> usize {
+        self.config.max_message_queue_size
+    }
+
     /// Initialize the agent and prepare for execution
     pub async fn initialize(&self) -> Result<()> {
         info!("Initializing agent: {} (ID: {})", self.config.name, self.id);
         {
-            let mut ctx = self.context.lock().unwrap();
+            let mut ctx = lock_or_recover(&self.context);
             ctx.status = AgentStatus::Ready;
-            ctx.capabilities = self.config.capabilities.clone();
+            // Without explicit capabilities, keep the ones in the shared
+            // context, which a runtime may have filled in at registration
+            if let Some(capabilities) = &self.config.capabilities {
+                ctx.capabilities = capabilities.clone();
+            }
         }
         Ok(())
     }
 
     /// Start the agent's main execution loop
+    ///
+    /// Runs the handler's `on_initialize` hook first; if it fails the agent
+    /// is left unstarted and the error is returned.
     pub async fn start<H>(&self, handler: H) -> Result<()>
     where
-        H: AgentHandler + Send + 'static,
+        H: AgentHandler + Send + Sync + 'static,
+    {
+        self.start_with_middleware(handler, Vec::new()).await
+    }
+
+    /// Start the agent's main execution loop with a middleware chain
+    /// wrapped around the handler
+    pub async fn start_with_middleware<H>(
+        &self,
+        handler: H,
+        middleware: Vec<Box<dyn MessageMiddleware>>,
+    ) -> Result<()>
+    where
+        H: AgentHandler + Send + Sync + 'static,
     {
         let context = self.context.clone();
-        let message_rx = {
-            let mut rx_guard = self.message_rx.lock().unwrap();
-            rx_guard
+        let inbox = {
+            let mut inbox_guard = lock_or_recover(&self.inbox);
+            inbox_guard
                 .take()
                 .ok_or_else(|| anyhow::anyhow!("Agent already started"))?
         };
 
+        if let Err(e) = handler.on_initialize(context.clone()).await {
+            error!("Agent {} failed to initialize: {}", self.config.name, e);
+            *lock_or_recover(&self.inbox) = Some(inbox);
+            lock_or_recover(&context).status = AgentStatus::Error(e.to_string());
+            return Err(e);
+        }
+
+        let handler = Arc::new(handler);
+        *lock_or_recover(&self.handler) = Some(handler.clone());
+
         // Spawn the message processing task
         let handler_context = context.clone();
-        tokio::spawn(async move {
-            Self::message_loop(handler_context, message_rx, handler).await;
-        });
+        let pending_messages = self.pending_messages.clone();
+        let drain = self.drain.clone();
+        let seen = self.config.dedup_capacity.map(SeenIds::new);
+        // Middleware logs from the loop are tagged with the agent too
+        let loop_task = tokio::spawn(logging::scope(self.config.name.clone(), async move {
+            Self::message_loop(
+                handler_context,
+                inbox,
+                pending_messages,
+                drain,
+                seen,
+                handler,
+                middleware,
+            )
+            .await;
+        }));
+        *lock_or_recover(&self.loop_task) = Some(loop_task);
+
+        if let Some(interval) = self.config.heartbeat_interval {
+            tokio::spawn(Self::heartbeat_loop(
+                self.id,
+                context,
+                self.heartbeat.clone(),
+                interval,
+            ));
+        }
 
         info!("Agent {} started successfully", self.config.name);
         Ok(())
@@ -101,54 +468,224 @@ impl Agent {
     /// Internal message processing loop
     async fn message_loop<H>(
         context: Arc<Mutex<AgentContext>>,
-        mut message_rx: mpsc::UnboundedReceiver<Message>,
-        handler: H,
+        mut inbox: Inbox,
+        pending_messages: Arc<AtomicUsize>,
+        drain: CancellationToken,
+        mut seen: Option<SeenIds>,
+        handler: Arc<H>,
+        middleware: Vec<Box<dyn MessageMiddleware>>,
     ) where
-        H: AgentHandler,
+        H: AgentHandler + Send + Sync + 'static,
     {
-        while let Some(message) = message_rx.recv().await {
-            debug!("Processing message: {:?}", message.message_type);
+        let (agent, cancellation) = {
+            let ctx = lock_or_recover(&context);
+            (ctx.name.clone(), ctx.cancellation.clone())
+        };
+        'messages: loop {
+            let message = tokio::select! {
+                biased;
+                _ = cancellation.cancelled() => break 'messages,
+                Some(message) = inbox.recv() => message,
+                // Messages are counted before they are sent, so an empty
+                // count means nothing is left to drain
+                _ = drain.cancelled(), if pending_messages.load(Ordering::SeqCst) == 0 => {
+                    break 'messages
+                }
+                else => break 'messages,
+            };
+            pending_messages.fetch_sub(1, Ordering::SeqCst);
+            debug!(agent = agent.as_str(); "Processing message: {:?}", message.message_type);
+
+            // Run the middleware chain; any step may drop the message
+            let mut message = message;
+            for step in &middleware {
+                match step.before(context.clone(), message).await {
+                    Ok(Some(next)) => message = next,
+                    Ok(None) => {
+                        debug!(agent = agent.as_str(); "Message dropped by middleware");
+                        continue 'messages;
+                    }
+                    Err(e) => {
+                        error!(agent = agent.as_str(); "Middleware rejected message: {}", e);
+                        continue 'messages;
+                    }
+                }
+            }
+            // Middleware may have rejected an earlier delivery, so only
+            // messages about to reach the handler count as seen
+            if let (Some(seen), Some(id)) = (seen.as_mut(), message.id) {
+                if !seen.insert(id) {
+                    debug!(agent = agent.as_str(); "Dropping duplicate message {}", id);
+                    continue 'messages;
+                }
+            }
+            let message_type = message.message_type.clone();
 
             // Update agent activity
             {
-                let mut ctx = context.lock().unwrap();
+                let mut ctx = lock_or_recover(&context);
                 ctx.update_activity();
                 ctx.status = AgentStatus::Busy;
             }
 
-            // Handle the message
-            if let Err(e) = handler.handle_message(context.clone(), message).await {
-                error!("Error handling message: {}", e);
-                let mut ctx = context.lock().unwrap();
+            // Handle the message on its own task, so a panicking handler
+            // fails this message instead of ending the loop
+            let task = tokio::spawn({
+                let handler = handler.clone();
+                let context = context.clone();
+                logging::scope(agent.clone(), async move {
+                    handler.handle_message(context, message).await
+                })
+            });
+            let result = match task.await {
+                Ok(result) => result,
+                Err(e) => Err(anyhow::anyhow!("Handler panicked: {}", e)),
+            };
+            if let Err(e) = &result {
+                error!(agent = agent.as_str(); "Error handling message: {}", e);
+                let mut ctx = lock_or_recover(&context);
                 ctx.status = AgentStatus::Error(e.to_string());
             } else {
-                let mut ctx = context.lock().unwrap();
+                let mut ctx = lock_or_recover(&context);
                 ctx.status = AgentStatus::Ready;
             }
+
+            for step in middleware.iter().rev() {
+                step.after(context.clone(), &message_type, &result).await;
+            }
         }
 
-        info!("Agent message loop ended");
-        let mut ctx = context.lock().unwrap();
+        info!(agent = agent.as_str(); "Agent message loop ended");
+        let mut ctx = lock_or_recover(&context);
         ctx.status = AgentStatus::Shutdown;
     }
 
+    /// Publish a heartbeat every `interval` until the agent shuts down
+    async fn heartbeat_loop(
+        id: AgentId,
+        context: Arc<Mutex<AgentContext>>,
+        heartbeat: Arc<watch::Sender<Option<Heartbeat>>>,
+        interval: std::time::Duration,
+    ) {
+        let cancellation = lock_or_recover(&context).cancellation.clone();
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = cancellation.cancelled() => break,
+                _ = ticker.tick() => {}
+            }
+            let beat = {
+                let ctx = lock_or_recover(&context);
+                Heartbeat {
+                    agent_id: id,
+                    status: ctx.status.clone(),
+                    last_activity: ctx.last_activity,
+                    sent_at: std::time::Instant::now(),
+                }
+            };
+            heartbeat.send_replace(Some(beat));
+        }
+    }
+
+    /// Watch the agent's heartbeats
+    ///
+    /// Holds `None` until the first heartbeat; stops changing once the
+    /// agent shuts down. Agents only publish heartbeats when built with
+    /// [`AgentBuilder::with_heartbeat`].
+    pub fn heartbeats(&self) -> watch::Receiver<Option<Heartbeat>> {
+        self.heartbeat.subscribe()
+    }
+
+    /// Most recent heartbeat, if any was published
+    pub fn last_heartbeat(&self) -> Option<Heartbeat> {
+        self.heartbeat.borrow().clone()
+    }
+
     /// Get the current status of the agent
     pub fn status(&self) -> AgentStatus {
-        let ctx = self.context.lock().unwrap();
+        let ctx = lock_or_recover(&self.context);
         ctx.status.clone()
     }
 
     /// Shutdown the agent gracefully
+    ///
+    /// Cancels the context's `cancellation` token and waits for the message
+    /// being handled, if any, to finish; messages still queued are not
+    /// handled. Handlers that don't check the token still block shutdown
+    /// until they return.
+    ///
+    /// Runs the handler's `on_shutdown` hook once, the first time a started
+    /// agent is shut down.
     pub async fn shutdown(&self) -> Result<()> {
         info!("Shutting down agent: {}", self.config.name);
+        lock_or_recover(&self.context).cancellation.cancel();
+        self.join_message_loop().await;
+
+        let handler = lock_or_recover(&self.handler).take();
+        if let Some(handler) = handler {
+            handler.on_shutdown(self.context.clone()).await?;
+        }
         {
-            let mut ctx = self.context.lock().unwrap();
+            let mut ctx = lock_or_recover(&self.context);
             ctx.status = AgentStatus::Shutdown;
         }
-        // Close the message channel to stop the message loop
-        drop(self.message_tx.clone());
         Ok(())
     }
+
+    /// Stop accepting messages, handle the ones already queued and shut
+    /// down
+    ///
+    /// New sends fail with [`SendError::Draining`]. With a `timeout`,
+    /// messages still queued when it runs out are dropped, as with
+    /// [`Agent::shutdown`]; without one the agent waits for its whole queue.
+    pub async fn drain(&self, timeout: Option<std::time::Duration>) -> Result<DrainReport> {
+        info!(
+            "Draining agent {} (timeout {:?})",
+            self.config.name, timeout
+        );
+        self.accepting.store(false, Ordering::SeqCst);
+        let queued = self.pending_messages();
+        self.drain.cancel();
+
+        match timeout {
+            Some(timeout) => {
+                let loop_task = lock_or_recover(&self.loop_task).take();
+                if let Some(mut loop_task) = loop_task {
+                    match tokio::time::timeout(timeout, &mut loop_task).await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => {
+                            error!("Message loop of agent {} failed: {}", self.config.name, e)
+                        }
+                        Err(_) => {
+                            warn!(
+                                "Agent {} did not drain within {:?}",
+                                self.config.name, timeout
+                            );
+                            *lock_or_recover(&self.loop_task) = Some(loop_task);
+                        }
+                    }
+                }
+            }
+            None => self.join_message_loop().await,
+        }
+        self.shutdown().await?;
+
+        let dropped = self.pending_messages();
+        Ok(DrainReport {
+            drained: queued.saturating_sub(dropped),
+            dropped,
+        })
+    }
+
+    /// Wait for the message loop of a started agent to end
+    async fn join_message_loop(&self) {
+        let loop_task = lock_or_recover(&self.loop_task).take();
+        if let Some(loop_task) = loop_task {
+            if let Err(e) = loop_task.await {
+                error!("Message loop of agent {} failed: {}", self.config.name, e);
+            }
+        }
+    }
 }
 
 /// Builder pattern for creating agents with fluent API
@@ -169,7 +706,7 @@ impl AgentBuilder {
 
     /// Set the agent's capabilities
     pub fn with_capabilities(mut self, capabilities: AgentCapabilities) -> Self {
-        self.config.capabilities = capabilities;
+        self.config.capabilities = Some(capabilities);
         self
     }
 
@@ -179,21 +716,54 @@ impl AgentBuilder {
         self
     }
 
+    /// Set how control messages are balanced against normal messages
+    pub fn with_inbox_fairness(mut self, fairness: InboxFairness) -> Self {
+        self.config.inbox_fairness = fairness;
+        self
+    }
+
     /// Enable AI inference capability
     pub fn with_ai_inference(mut self) -> Self {
-        self.config.capabilities.can_inference = true;
+        self.config
+            .capabilities
+            .get_or_insert_with(Default::default)
+            .can_inference = true;
         self
     }
 
     /// Enable training capability  
     pub fn with_training(mut self) -> Self {
-        self.config.capabilities.can_training = true;
+        self.config
+            .capabilities
+            .get_or_insert_with(Default::default)
+            .can_training = true;
         self
     }
 
     /// Add supported model types
     pub fn with_models(mut self, models: Vec<String>) -> Self {
-        self.config.capabilities.supported_models = models;
+        self.config
+            .capabilities
+            .get_or_insert_with(Default::default)
+            .supported_models = models;
+        self
+    }
+
+    /// Add a tag used to find the agent through `AgentQuery`
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.config.tags.push(tag.to_string());
+        self
+    }
+
+    /// Publish a heartbeat every `interval` while the agent runs
+    pub fn with_heartbeat(mut self, interval: std::time::Duration) -> Self {
+        self.config.heartbeat_interval = Some(interval);
+        self
+    }
+
+    /// Drop messages whose id matches one of the last `capacity` handled
+    pub fn with_message_dedup(mut self, capacity: usize) -> Self {
+        self.config.dedup_capacity = Some(capacity);
         self
     }
 
@@ -207,6 +777,9 @@ impl AgentBuilder {
 #[async_trait::async_trait]
 pub trait AgentHandler {
     /// Handle an incoming message
+    ///
+    /// Long-running handlers should watch `context`'s `cancellation` token
+    /// and return early once it is cancelled, as shutdown waits for them.
     async fn handle_message(
         &self,
         context: Arc<Mutex<AgentContext>>,
@@ -224,10 +797,24 @@ pub trait AgentHandler {
     }
 }
 
+/// Whether a runtime lets several agents share a name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NamePolicy {
+    /// Names may repeat; lookups by name find the earliest registration
+    #[default]
+    Lenient,
+    /// Registering a name that is already taken fails
+    Strict,
+}
+
 /// Runtime for managing multiple agents
 pub struct AgentRuntime {
     agents: Vec<Agent>,
     next_agent_id: AgentId,
+    default_capabilities: AgentCapabilities,
+    name_policy: NamePolicy,
+    /// Parent of each agent registered as a child
+    parents: std::collections::HashMap<AgentId, AgentId>,
 }
 
 impl AgentRuntime {
@@ -236,21 +823,111 @@ impl AgentRuntime {
         Self {
             agents: Vec::new(),
             next_agent_id: 1,
+            default_capabilities: AgentCapabilities::default(),
+            name_policy: NamePolicy::default(),
+            parents: std::collections::HashMap::new(),
         }
     }
 
+    /// Set the capabilities given to agents registered without their own
+    pub fn with_default_capabilities(mut self, capabilities: AgentCapabilities) -> Self {
+        self.default_capabilities = capabilities;
+        self
+    }
+
+    /// Set whether registered agents must have unique names
+    pub fn with_name_policy(mut self, name_policy: NamePolicy) -> Self {
+        self.name_policy = name_policy;
+        self
+    }
+
     /// Register a new agent with the runtime
-    pub fn register_agent(&mut self, config: AgentConfig) -> Agent {
+    ///
+    /// Fails under `NamePolicy::Strict` if an agent with the same name is
+    /// already registered.
+    pub fn register_agent(&mut self, mut config: AgentConfig) -> Result<Agent> {
+        if self.name_policy == NamePolicy::Strict && self.get_agent_by_name(&config.name).is_some()
+        {
+            anyhow::bail!("An agent named {} is already registered", config.name);
+        }
+        config
+            .capabilities
+            .get_or_insert_with(|| self.default_capabilities.clone());
         let agent = Agent::new(self.next_agent_id, config);
         self.next_agent_id += 1;
         self.agents.push(agent.clone());
-        agent
+        Ok(agent)
+    }
+
+    /// Register a new agent supervised by `parent_id`
+    ///
+    /// The child is shut down whenever its parent is deregistered.
+    pub fn register_child(&mut self, parent_id: AgentId, config: AgentConfig) -> Result<Agent> {
+        if self.get_agent(parent_id).is_none() {
+            anyhow::bail!("Parent agent {} is not registered", parent_id);
+        }
+        let agent = self.register_agent(config)?;
+        self.parents.insert(agent.id, parent_id);
+        Ok(agent)
+    }
+
+    /// Parent of an agent registered with `register_child`
+    pub fn parent_of(&self, id: AgentId) -> Option<AgentId> {
+        self.parents.get(&id).copied()
+    }
+
+    /// Direct children of an agent, in registration order
+    pub fn children_of(&self, id: AgentId) -> Vec<AgentId> {
+        self.agents
+            .iter()
+            .map(|agent| agent.id)
+            .filter(|child| self.parent_of(*child) == Some(id))
+            .collect()
+    }
+
+    /// Shut down an agent and all its descendants, children first, and
+    /// remove them from the runtime
+    pub async fn deregister_agent(&mut self, id: AgentId) -> Result<()> {
+        if self.get_agent(id).is_none() {
+            anyhow::bail!("Agent {} is not registered", id);
+        }
+
+        let subtree = self.descendants_first(id);
+        for agent_id in &subtree {
+            if let Some(agent) = self.get_agent(*agent_id) {
+                agent.shutdown().await?;
+            }
+        }
+        self.agents.retain(|agent| !subtree.contains(&agent.id));
+        self.parents
+            .retain(|child, parent| !subtree.contains(child) && !subtree.contains(parent));
+        info!(
+            "Deregistered agent {} and {} descendants",
+            id,
+            subtree.len() - 1
+        );
+        Ok(())
+    }
+
+    /// `id` and its descendants, each after all of its own descendants
+    fn descendants_first(&self, id: AgentId) -> Vec<AgentId> {
+        let mut order = Vec::new();
+        for child in self.children_of(id) {
+            order.extend(self.descendants_first(child));
+        }
+        order.push(id);
+        order
+    }
+
+    /// Number of ancestors above an agent
+    fn depth(&self, id: AgentId) -> usize {
+        std::iter::successors(self.parent_of(id), |parent| self.parent_of(*parent)).count()
     }
 
     /// Start all registered agents
     pub async fn start_all<H>(&self, handler: H) -> Result<()>
     where
-        H: AgentHandler + Clone + Send + 'static,
+        H: AgentHandler + Clone + Send + Sync + 'static,
     {
         for agent in &self.agents {
             agent.initialize().await?;
@@ -265,14 +942,31 @@ impl AgentRuntime {
         self.agents.iter().find(|agent| agent.id == id)
     }
 
+    /// Get the earliest registered agent with the given name
+    pub fn get_agent_by_name(&self, name: &str) -> Option<&Agent> {
+        self.agents.iter().find(|agent| agent.config.name == name)
+    }
+
     /// Get all agents
     pub fn agents(&self) -> &[Agent] {
         &self.agents
     }
 
-    /// Shutdown all agents
+    /// Messages buffered across all registered agents
+    pub fn queue_depth(&self) -> usize {
+        self.agents.iter().map(Agent::queue_depth).sum()
+    }
+
+    /// Combined queue capacity of all registered agents
+    pub fn queue_capacity(&self) -> usize {
+        self.agents.iter().map(Agent::queue_capacity).sum()
+    }
+
+    /// Shutdown all agents, children before their parents
     pub async fn shutdown_all(&self) -> Result<()> {
-        for agent in &self.agents {
+        let mut agents: Vec<&Agent> = self.agents.iter().collect();
+        agents.sort_by_key(|agent| std::cmp::Reverse(self.depth(agent.id)));
+        for agent in agents {
             agent.shutdown().await?;
         }
         info!("All agents shut down");
@@ -301,7 +995,7 @@ mod tests {
             message: Message,
         ) -> Result<()> {
             let mut ctx = context.lock().unwrap();
-            ctx.resource_usage.message_count += 1;
+            ctx.resource_usage.message_count.increment();
             debug!("Test handler processed message: {:?}", message.message_type);
             Ok(())
         }
@@ -332,6 +1026,7 @@ mod tests {
             message_type: MessageType::Data,
             payload: b"test".to_vec(),
             timestamp: chrono::Utc::now().timestamp() as u64,
+            id: None,
         };
 
         agent.send_message(message).unwrap();
@@ -340,6 +1035,728 @@ mod tests {
         tokio::time::sleep(std::time::Duration::from_millis(100)).await;
 
         let ctx = agent.context.lock().unwrap();
-        assert_eq!(ctx.resource_usage.message_count, 1);
+        assert_eq!(ctx.resource_usage.message_count.get(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_message_count() {
+        let context = Arc::new(Mutex::new(AgentContext::new(1, "test_agent".to_string())));
+        let usage = context.lock().unwrap().resource_usage.share();
+
+        // Each task takes a handle once and then counts without the lock
+        let mut tasks = Vec::new();
+        for _ in 0..16 {
+            let counter = context.lock().unwrap().resource_usage.message_count.share();
+            tasks.push(tokio::spawn(async move {
+                for _ in 0..100 {
+                    counter.increment();
+                    tokio::task::yield_now().await;
+                }
+            }));
+        }
+
+        // The context stays free while the tasks count
+        assert!(context.try_lock().is_ok());
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(usage.message_count.get(), 1600);
+        assert_eq!(
+            context.lock().unwrap().resource_usage.message_count.get(),
+            1600
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_context_does_not_hold_lock() {
+        let agent = AgentBuilder::new("test_agent").with_ai_inference().build(1);
+        agent.initialize().await.unwrap();
+
+        let snapshot = agent.snapshot_context();
+        assert_eq!(snapshot.id, 1);
+        assert_eq!(snapshot.name, "test_agent");
+        assert_eq!(snapshot.status, AgentStatus::Ready);
+        assert!(snapshot.capabilities.can_inference);
+
+        // A writer on another thread gets the lock while the snapshot is alive
+        let context = agent.context();
+        std::thread::spawn(move || {
+            let mut ctx = context.try_lock().expect("snapshot held the context lock");
+            ctx.status = AgentStatus::Busy;
+            ctx.resource_usage.message_count.increment();
+        })
+        .join()
+        .unwrap();
+
+        // The snapshot keeps the values from when it was taken
+        assert_eq!(snapshot.status, AgentStatus::Ready);
+        assert_eq!(snapshot.resource_usage.message_count, 0);
+        let later = agent.snapshot_context();
+        assert_eq!(later.status, AgentStatus::Busy);
+        assert_eq!(later.resource_usage.message_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats_follow_interval_and_stop_on_shutdown() {
+        let interval = std::time::Duration::from_millis(50);
+        let agent = AgentBuilder::new("test_agent")
+            .with_heartbeat(interval)
+            .build(1);
+        let mut heartbeats = agent.heartbeats();
+        assert!(agent.last_heartbeat().is_none());
+
+        agent.initialize().await.unwrap();
+        agent.start(TestHandler).await.unwrap();
+
+        let mut sent = Vec::new();
+        for _ in 0..4 {
+            tokio::time::timeout(interval * 4, heartbeats.changed())
+                .await
+                .expect("heartbeat late")
+                .unwrap();
+            let beat = heartbeats.borrow_and_update().clone().unwrap();
+            assert_eq!(beat.agent_id, 1);
+            assert_eq!(beat.status, AgentStatus::Ready);
+            sent.push(beat.sent_at);
+        }
+        for pair in sent.windows(2) {
+            let gap = pair[1] - pair[0];
+            assert!(gap >= interval / 2 && gap <= interval * 3, "gap {:?}", gap);
+        }
+
+        agent.shutdown().await.unwrap();
+        heartbeats.borrow_and_update();
+        let after_shutdown = tokio::time::timeout(interval * 3, heartbeats.changed()).await;
+        assert!(after_shutdown.is_err(), "heartbeat after shutdown");
+    }
+
+    #[tokio::test]
+    async fn test_pending_messages_observable_until_handled() {
+        let agent = AgentBuilder::new("test_agent").build(1);
+
+        for i in 0..3u8 {
+            agent
+                .send_message(Message {
+                    from: 0,
+                    to: 1,
+                    message_type: MessageType::Data,
+                    payload: vec![i],
+                    timestamp: 0,
+                    id: None,
+                })
+                .unwrap();
+        }
+
+        // Nothing consumes the channel until the agent is started
+        assert_eq!(agent.pending_messages(), 3);
+
+        agent.initialize().await.unwrap();
+        agent.start(TestHandler).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        assert_eq!(agent.pending_messages(), 0);
+        assert_eq!(agent.resource_usage().message_count.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_queue_depth_reports_buffered_messages() {
+        let mut runtime = AgentRuntime::new();
+        let busy = runtime
+            .register_agent(AgentConfig {
+                name: "busy".to_string(),
+                max_message_queue_size: 8,
+                ..AgentConfig::default()
+            })
+            .unwrap();
+        let idle = runtime
+            .register_agent(AgentConfig {
+                name: "idle".to_string(),
+                max_message_queue_size: 4,
+                ..AgentConfig::default()
+            })
+            .unwrap();
+
+        // Neither agent is started, so everything sent stays buffered
+        for i in 0..5u8 {
+            busy.send_message(Message {
+                from: 0,
+                to: busy.id,
+                message_type: MessageType::Data,
+                payload: vec![i],
+                timestamp: 0,
+                id: None,
+            })
+            .unwrap();
+        }
+
+        assert_eq!(busy.queue_depth(), 5);
+        assert_eq!(busy.queue_capacity(), 8);
+        assert_eq!(idle.queue_depth(), 0);
+        assert_eq!(runtime.queue_depth(), 5);
+        assert_eq!(runtime.queue_capacity(), 12);
+
+        busy.initialize().await.unwrap();
+        busy.start(TestHandler).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(runtime.queue_depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_send_messages_reports_undelivered() {
+        let agent = AgentBuilder::new("test_agent")
+            .with_message_queue_size(3)
+            .build(1);
+
+        let batch: Vec<Message> = (0..5u8)
+            .map(|i| Message {
+                from: 0,
+                to: 1,
+                message_type: MessageType::Data,
+                payload: vec![i],
+                timestamp: 0,
+                id: None,
+            })
+            .collect();
+
+        // The loop is not running, so the queue fills after three messages
+        let failures = agent.send_messages(batch).unwrap_err();
+        assert_eq!(failures.len(), 2);
+        assert!(failures.iter().all(|(_, e)| *e == SendError::QueueFull));
+        let undelivered: Vec<u8> = failures.iter().map(|(m, _)| m.payload[0]).collect();
+        assert_eq!(undelivered, vec![3, 4]);
+        assert_eq!(agent.pending_messages(), 3);
+
+        agent.initialize().await.unwrap();
+        agent.start(TestHandler).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(agent.resource_usage().message_count.get(), 3);
+
+        // With the queue drained the retry goes through
+        let retry = failures.into_iter().map(|(m, _)| m);
+        assert!(agent.send_messages(retry).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_runtime_default_capabilities() {
+        let mut runtime = AgentRuntime::new().with_default_capabilities(AgentCapabilities {
+            can_inference: true,
+            supported_models: vec!["default_model".to_string()],
+            ..AgentCapabilities::default()
+        });
+
+        let inherited = runtime
+            .register_agent(AgentBuilder::new("plain").config)
+            .unwrap();
+        let explicit = runtime
+            .register_agent(AgentBuilder::new("trainer").with_training().config)
+            .unwrap();
+
+        inherited.initialize().await.unwrap();
+        explicit.initialize().await.unwrap();
+
+        let caps = inherited.context().lock().unwrap().capabilities.clone();
+        assert!(caps.can_inference);
+        assert_eq!(caps.supported_models, vec!["default_model".to_string()]);
+
+        // Explicit capabilities replace the defaults entirely
+        let caps = explicit.context().lock().unwrap().capabilities.clone();
+        assert!(caps.can_training);
+        assert!(!caps.can_inference);
+        assert!(caps.supported_models.is_empty());
+    }
+
+    #[test]
+    fn test_name_policy() {
+        let mut strict = AgentRuntime::new().with_name_policy(NamePolicy::Strict);
+        let first = strict
+            .register_agent(AgentBuilder::new("camera").config)
+            .unwrap();
+        let err = strict
+            .register_agent(AgentBuilder::new("camera").config)
+            .unwrap_err();
+        assert!(err.to_string().contains("camera"));
+        assert_eq!(strict.agents().len(), 1);
+        assert_eq!(strict.get_agent_by_name("camera").unwrap().id, first.id);
+
+        let mut lenient = AgentRuntime::new();
+        let first = lenient
+            .register_agent(AgentBuilder::new("camera").config)
+            .unwrap();
+        let second = lenient
+            .register_agent(AgentBuilder::new("camera").config)
+            .unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(lenient.agents().len(), 2);
+        assert_eq!(lenient.get_agent_by_name("camera").unwrap().id, first.id);
+        assert!(lenient.get_agent_by_name("microphone").is_none());
+    }
+
+    #[derive(Clone, Default)]
+    struct LifecycleHandler {
+        initialized: Arc<AtomicUsize>,
+        shut_down: Arc<AtomicUsize>,
+        fail_initialize: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentHandler for LifecycleHandler {
+        async fn handle_message(
+            &self,
+            _context: Arc<Mutex<AgentContext>>,
+            _message: Message,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn on_initialize(&self, _context: Arc<Mutex<AgentContext>>) -> Result<()> {
+            self.initialized.fetch_add(1, Ordering::SeqCst);
+            if self.fail_initialize {
+                anyhow::bail!("model missing");
+            }
+            Ok(())
+        }
+
+        async fn on_shutdown(&self, _context: Arc<Mutex<AgentContext>>) -> Result<()> {
+            self.shut_down.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_hooks_run_once() {
+        let agent = AgentBuilder::new("test_agent").build(1);
+        let handler = LifecycleHandler::default();
+
+        agent.initialize().await.unwrap();
+        agent.start(handler.clone()).await.unwrap();
+        assert!(agent.start(handler.clone()).await.is_err());
+        assert_eq!(handler.initialized.load(Ordering::SeqCst), 1);
+
+        agent.shutdown().await.unwrap();
+        agent.shutdown().await.unwrap();
+        assert_eq!(handler.shut_down.load(Ordering::SeqCst), 1);
+        assert_eq!(agent.status(), AgentStatus::Shutdown);
+    }
+
+    #[tokio::test]
+    async fn test_initialize_hook_error_is_returned() {
+        let agent = AgentBuilder::new("test_agent").build(1);
+        let handler = LifecycleHandler {
+            fail_initialize: true,
+            ..LifecycleHandler::default()
+        };
+
+        agent.initialize().await.unwrap();
+        let err = agent.start(handler.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("model missing"));
+        assert!(matches!(agent.status(), AgentStatus::Error(_)));
+
+        // Nothing started, so there is no shutdown hook to run
+        agent.shutdown().await.unwrap();
+        assert_eq!(handler.shut_down.load(Ordering::SeqCst), 0);
+    }
+
+    /// Records the order agents shut down in
+    #[derive(Clone, Default)]
+    struct ShutdownOrderHandler {
+        order: Arc<Mutex<Vec<AgentId>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentHandler for ShutdownOrderHandler {
+        async fn handle_message(
+            &self,
+            _context: Arc<Mutex<AgentContext>>,
+            _message: Message,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn on_shutdown(&self, context: Arc<Mutex<AgentContext>>) -> Result<()> {
+            let id = context.lock().unwrap().id;
+            self.order.lock().unwrap().push(id);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deregister_cascades_to_descendants() {
+        let mut runtime = AgentRuntime::new();
+        let root = runtime
+            .register_agent(AgentBuilder::new("root").config)
+            .unwrap();
+        let left = runtime
+            .register_child(root.id, AgentBuilder::new("left").config)
+            .unwrap();
+        let right = runtime
+            .register_child(root.id, AgentBuilder::new("right").config)
+            .unwrap();
+        let leaf = runtime
+            .register_child(left.id, AgentBuilder::new("leaf").config)
+            .unwrap();
+        let other = runtime
+            .register_agent(AgentBuilder::new("other").config)
+            .unwrap();
+        assert!(runtime
+            .register_child(99, AgentBuilder::new("orphan").config)
+            .is_err());
+
+        let handler = ShutdownOrderHandler::default();
+        runtime.start_all(handler.clone()).await.unwrap();
+        assert_eq!(runtime.children_of(root.id), vec![left.id, right.id]);
+        assert_eq!(runtime.parent_of(leaf.id), Some(left.id));
+
+        runtime.deregister_agent(root.id).await.unwrap();
+
+        for agent in [&root, &left, &right, &leaf] {
+            assert_eq!(agent.status(), AgentStatus::Shutdown);
+            assert!(runtime.get_agent(agent.id).is_none());
+        }
+        assert_eq!(
+            *handler.order.lock().unwrap(),
+            vec![leaf.id, left.id, right.id, root.id]
+        );
+        assert_eq!(other.status(), AgentStatus::Ready);
+        assert_eq!(runtime.agents().len(), 1);
+        assert!(runtime.parent_of(leaf.id).is_none());
+
+        runtime.shutdown_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_all_stops_children_first() {
+        let mut runtime = AgentRuntime::new();
+        let root = runtime
+            .register_agent(AgentBuilder::new("root").config)
+            .unwrap();
+        let child = runtime
+            .register_child(root.id, AgentBuilder::new("child").config)
+            .unwrap();
+        let grandchild = runtime
+            .register_child(child.id, AgentBuilder::new("grandchild").config)
+            .unwrap();
+
+        let handler = ShutdownOrderHandler::default();
+        runtime.start_all(handler.clone()).await.unwrap();
+        runtime.shutdown_all().await.unwrap();
+
+        assert_eq!(
+            *handler.order.lock().unwrap(),
+            vec![grandchild.id, child.id, root.id]
+        );
+    }
+
+    /// Spins until the agent shuts down
+    #[derive(Clone, Default)]
+    struct CooperativeHandler {
+        started: Arc<AtomicUsize>,
+        cancelled: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentHandler for CooperativeHandler {
+        async fn handle_message(
+            &self,
+            context: Arc<Mutex<AgentContext>>,
+            _message: Message,
+        ) -> Result<()> {
+            let cancellation = context.lock().unwrap().cancellation.clone();
+            self.started.fetch_add(1, Ordering::SeqCst);
+            while !cancellation.is_cancelled() {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+            self.cancelled.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_cancels_running_handler() {
+        let agent = AgentBuilder::new("test_agent").build(1);
+        let handler = CooperativeHandler::default();
+
+        agent.initialize().await.unwrap();
+        agent.start(handler.clone()).await.unwrap();
+        for _ in 0..2 {
+            agent
+                .send_message(Message {
+                    from: 0,
+                    to: 1,
+                    message_type: MessageType::InferenceRequest,
+                    payload: vec![],
+                    timestamp: 0,
+                    id: None,
+                })
+                .unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(handler.started.load(Ordering::SeqCst), 1);
+
+        tokio::time::timeout(std::time::Duration::from_secs(1), agent.shutdown())
+            .await
+            .expect("shutdown waited on a cancelled handler")
+            .unwrap();
+
+        // The running handler returned before shutdown did, and the queued
+        // message was never started
+        assert_eq!(handler.cancelled.load(Ordering::SeqCst), 1);
+        assert_eq!(handler.started.load(Ordering::SeqCst), 1);
+        assert_eq!(agent.status(), AgentStatus::Shutdown);
+    }
+
+    /// Takes a fixed time over every message, counting them
+    #[derive(Clone)]
+    struct SlowHandler {
+        delay: std::time::Duration,
+        handled: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentHandler for SlowHandler {
+        async fn handle_message(
+            &self,
+            _context: Arc<Mutex<AgentContext>>,
+            _message: Message,
+        ) -> Result<()> {
+            tokio::time::sleep(self.delay).await;
+            self.handled.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    async fn queue_for_drain(delay: std::time::Duration) -> (Agent, SlowHandler) {
+        let agent = AgentBuilder::new("test_agent").build(1);
+        let handler = SlowHandler {
+            delay,
+            handled: Arc::default(),
+        };
+        for i in 0..5u8 {
+            agent
+                .send_message(Message {
+                    from: 0,
+                    to: 1,
+                    message_type: MessageType::Data,
+                    payload: vec![i],
+                    timestamp: 0,
+                    id: None,
+                })
+                .unwrap();
+        }
+        agent.initialize().await.unwrap();
+        agent.start(handler.clone()).await.unwrap();
+        (agent, handler)
+    }
+
+    #[tokio::test]
+    async fn test_drain_handles_queued_messages() {
+        let (agent, handler) = queue_for_drain(std::time::Duration::from_millis(10)).await;
+
+        let report = agent.drain(None).await.unwrap();
+
+        assert_eq!(report.drained, 5);
+        assert_eq!(report.dropped, 0);
+        assert_eq!(handler.handled.load(Ordering::SeqCst), 5);
+        assert_eq!(agent.status(), AgentStatus::Shutdown);
+        let late = Message {
+            from: 0,
+            to: 1,
+            message_type: MessageType::Data,
+            payload: vec![],
+            timestamp: 0,
+            id: None,
+        };
+        let err = agent.send_message(late).unwrap_err();
+        assert_eq!(err.downcast_ref::<SendError>(), Some(&SendError::Draining));
+    }
+
+    #[tokio::test]
+    async fn test_drain_drops_messages_after_timeout() {
+        let (agent, handler) = queue_for_drain(std::time::Duration::from_millis(50)).await;
+
+        let report = agent
+            .drain(Some(std::time::Duration::from_millis(120)))
+            .await
+            .unwrap();
+
+        assert_eq!(report.drained + report.dropped, 5);
+        assert!(report.dropped > 0);
+        assert_eq!(handler.handled.load(Ordering::SeqCst), report.drained);
+        assert_eq!(agent.status(), AgentStatus::Shutdown);
+    }
+
+    /// Panics while holding the context lock on an empty payload
+    struct PanickingHandler;
+
+    #[async_trait::async_trait]
+    impl AgentHandler for PanickingHandler {
+        async fn handle_message(
+            &self,
+            context: Arc<Mutex<AgentContext>>,
+            message: Message,
+        ) -> Result<()> {
+            let ctx = context.lock().unwrap();
+            if message.payload.is_empty() {
+                panic!("handler bug");
+            }
+            ctx.resource_usage.message_count.increment();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_context_recovers_from_handler_panic() {
+        let agent = AgentBuilder::new("test_agent").build(1);
+        agent.initialize().await.unwrap();
+        agent.start(PanickingHandler).await.unwrap();
+
+        for payload in [vec![], vec![1]] {
+            agent
+                .send_message(Message {
+                    from: 0,
+                    to: 1,
+                    message_type: MessageType::Data,
+                    payload,
+                    timestamp: 0,
+                    id: None,
+                })
+                .unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // The loop survived the panic and handled the next message
+        assert_eq!(agent.resource_usage().message_count.get(), 1);
+        assert_eq!(agent.status(), AgentStatus::Ready);
+        assert!(agent.context().lock().is_ok());
+        agent.shutdown().await.unwrap();
+        assert_eq!(agent.status(), AgentStatus::Shutdown);
+    }
+
+    #[derive(Clone, Default)]
+    struct OrderRecorder {
+        seen: Arc<Mutex<Vec<(MessageType, u8)>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentHandler for OrderRecorder {
+        async fn handle_message(
+            &self,
+            _context: Arc<Mutex<AgentContext>>,
+            message: Message,
+        ) -> Result<()> {
+            let entry = (message.message_type, message.payload[0]);
+            self.seen.lock().unwrap().push(entry);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inbox_ordering_with_control_bursts() {
+        let agent = AgentBuilder::new("test_agent")
+            .with_inbox_fairness(InboxFairness {
+                control_burst: 2,
+                normal_per_burst: 1,
+            })
+            .build(1);
+        let handler = OrderRecorder::default();
+
+        let sends = [
+            (MessageType::Data, 0),
+            (MessageType::Control, 0),
+            (MessageType::Data, 1),
+            (MessageType::Control, 1),
+            (MessageType::Data, 2),
+            (MessageType::Control, 2),
+            (MessageType::Control, 3),
+        ];
+        for (message_type, seq) in sends {
+            agent
+                .send_message(Message {
+                    from: 0,
+                    to: 1,
+                    message_type,
+                    payload: vec![seq],
+                    timestamp: 0,
+                    id: None,
+                })
+                .unwrap();
+        }
+
+        agent.initialize().await.unwrap();
+        agent.start(handler.clone()).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // Control first, but every two control messages let one data message through
+        let expected = vec![
+            (MessageType::Control, 0),
+            (MessageType::Control, 1),
+            (MessageType::Data, 0),
+            (MessageType::Control, 2),
+            (MessageType::Control, 3),
+            (MessageType::Data, 1),
+            (MessageType::Data, 2),
+        ];
+        assert_eq!(*handler.seen.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_message_ids_handled_once() {
+        let deduplicating = AgentBuilder::new("dedup_agent")
+            .with_message_dedup(8)
+            .build(1);
+        let plain = AgentBuilder::new("plain_agent").build(2);
+
+        for agent in [&deduplicating, &plain] {
+            // A retried control message followed by a new one and one without an id
+            for (id, seq) in [
+                (Some(7), 0),
+                (Some(7), 0),
+                (Some(8), 1),
+                (None, 2),
+                (None, 2),
+            ] {
+                agent
+                    .send_message(Message {
+                        from: 0,
+                        to: agent.id,
+                        message_type: MessageType::Control,
+                        payload: vec![seq],
+                        timestamp: 0,
+                        id,
+                    })
+                    .unwrap();
+            }
+        }
+
+        let mut seen = Vec::new();
+        for agent in [&deduplicating, &plain] {
+            let handler = OrderRecorder::default();
+            agent.initialize().await.unwrap();
+            agent.start(handler.clone()).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            agent.shutdown().await.unwrap();
+            let payloads: Vec<u8> = handler
+                .seen
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, seq)| *seq)
+                .collect();
+            seen.push(payloads);
+        }
+        assert_eq!(seen[0], vec![0, 1, 2, 2]);
+        assert_eq!(seen[1], vec![0, 0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn test_seen_ids_forget_oldest_beyond_capacity() {
+        let mut seen = SeenIds::new(2);
+        assert!(seen.insert(1));
+        assert!(seen.insert(2));
+        assert!(!seen.insert(1));
+        assert!(seen.insert(3));
+        // 1 was evicted to make room for 3
+        assert!(seen.insert(1));
+        assert!(!seen.insert(3));
     }
 }
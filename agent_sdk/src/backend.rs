@@ -0,0 +1,344 @@
+//! Backend module for the NebulaOS Agent SDK
+//!
+//! Gives agents a single inference API whether models run through ONNX
+//! Runtime (`MLHandler`) or the NPU hardware abstraction layer (`NpuManager`).
+
+use crate::ml::{InferenceRequest, InferenceResponse, MLHandler, ModelConfig};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, Semaphore};
+
+/// What an inference backend offers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendCapabilities {
+    /// Inference runs on dedicated accelerator hardware
+    pub hardware_accelerated: bool,
+    pub max_batch_size: usize,
+    pub loaded_models: Vec<String>,
+}
+
+/// Common interface over the SDK's inference stacks
+#[async_trait]
+pub trait InferenceBackend: Send + Sync {
+    /// Short backend name for logs
+    fn name(&self) -> &str;
+
+    /// Load a model so requests can refer to it by `config.model_id`
+    async fn load(&self, config: ModelConfig) -> Result<()>;
+
+    /// Run inference on a loaded model
+    async fn infer(&self, request: InferenceRequest) -> Result<InferenceResponse>;
+
+    /// Describe the backend and the models it has loaded
+    async fn capabilities(&self) -> BackendCapabilities;
+}
+
+#[async_trait]
+impl InferenceBackend for MLHandler {
+    fn name(&self) -> &str {
+        "onnx"
+    }
+
+    async fn load(&self, config: ModelConfig) -> Result<()> {
+        self.load_model(config).await
+    }
+
+    async fn infer(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+        self.run_inference(request).await
+    }
+
+    async fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            hardware_accelerated: false,
+            max_batch_size: 1,
+            loaded_models: self.get_loaded_models(),
+        }
+    }
+}
+
+/// Bounded set of slots for CPU-bound ML work
+///
+/// Caps how many model loads and inferences run at once, independently of
+/// the async runtime, so many agents cannot exhaust tokio's blocking pool.
+#[derive(Debug, Clone)]
+pub struct MlWorkerPool {
+    permits: Arc<Semaphore>,
+    size: usize,
+}
+
+impl MlWorkerPool {
+    /// Create a pool allowing `size` concurrent operations (at least one)
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(size)),
+            size,
+        }
+    }
+
+    /// Pool sized to the machine's available parallelism
+    pub fn with_available_parallelism() -> Self {
+        Self::new(std::thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    /// Most operations the pool runs at once
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Slots not currently taken
+    pub fn available(&self) -> usize {
+        self.permits.available_permits()
+    }
+
+    /// Run blocking `work` on tokio's blocking pool once a slot is free
+    pub async fn spawn_blocking<F, T>(&self, work: F) -> Result<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self.permits.clone().acquire_owned().await?;
+        let result = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            work()
+        })
+        .await?;
+        Ok(result)
+    }
+
+    /// Drive an async ML operation on tokio's blocking pool once a slot is
+    /// free, so blocking model code inside it never stalls executor threads
+    pub async fn run<F>(&self, work: F) -> Result<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let handle = tokio::runtime::Handle::current();
+        self.spawn_blocking(move || handle.block_on(work)).await
+    }
+}
+
+/// Backend whose loads and inferences each take a slot of an [`MlWorkerPool`]
+pub struct PooledBackend {
+    inner: Arc<dyn InferenceBackend>,
+    pool: MlWorkerPool,
+}
+
+impl PooledBackend {
+    pub fn new(inner: Arc<dyn InferenceBackend>, pool: MlWorkerPool) -> Self {
+        Self { inner, pool }
+    }
+}
+
+#[async_trait]
+impl InferenceBackend for PooledBackend {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn load(&self, config: ModelConfig) -> Result<()> {
+        let inner = self.inner.clone();
+        self.pool
+            .run(async move { inner.load(config).await })
+            .await?
+    }
+
+    async fn infer(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+        let inner = self.inner.clone();
+        self.pool
+            .run(async move { inner.infer(request).await })
+            .await?
+    }
+
+    async fn capabilities(&self) -> BackendCapabilities {
+        self.inner.capabilities().await
+    }
+}
+
+/// Results each subscriber can fall behind by before missing some
+pub const DEFAULT_RESULT_BUS_CAPACITY: usize = 64;
+
+/// Broadcast channels carrying every inference result, one per model
+#[derive(Debug, Clone)]
+pub struct InferenceResultBus {
+    channels: Arc<Mutex<HashMap<String, broadcast::Sender<InferenceResponse>>>>,
+    capacity: usize,
+}
+
+impl InferenceResultBus {
+    /// Create a bus whose subscribers may lag by up to `capacity` results
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            channels: Arc::default(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Receive every result produced by `model_id` from now on
+    pub fn subscribe(&self, model_id: &str) -> broadcast::Receiver<InferenceResponse> {
+        self.channels
+            .lock()
+            .unwrap()
+            .entry(model_id.to_string())
+            .or_insert_with(|| broadcast::channel(self.capacity).0)
+            .subscribe()
+    }
+
+    /// Send a result to the model's subscribers, returning how many
+    /// received it
+    pub fn publish(&self, model_id: &str, response: &InferenceResponse) -> usize {
+        let mut channels = self.channels.lock().unwrap();
+        let Some(sender) = channels.get(model_id) else {
+            return 0;
+        };
+        match sender.send(response.clone()) {
+            Ok(receivers) => receivers,
+            Err(_) => {
+                // Every subscriber has gone away
+                channels.remove(model_id);
+                0
+            }
+        }
+    }
+}
+
+impl Default for InferenceResultBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESULT_BUS_CAPACITY)
+    }
+}
+
+/// Backend that publishes every result to an [`InferenceResultBus`]
+pub struct PublishingBackend {
+    inner: Arc<dyn InferenceBackend>,
+    bus: InferenceResultBus,
+}
+
+impl PublishingBackend {
+    pub fn new(inner: Arc<dyn InferenceBackend>, bus: InferenceResultBus) -> Self {
+        Self { inner, bus }
+    }
+}
+
+#[async_trait]
+impl InferenceBackend for PublishingBackend {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn load(&self, config: ModelConfig) -> Result<()> {
+        self.inner.load(config).await
+    }
+
+    async fn infer(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+        let model_id = request.model_id.clone();
+        let response = self.inner.infer(request).await?;
+        self.bus.publish(&model_id, &response);
+        Ok(response)
+    }
+
+    async fn capabilities(&self) -> BackendCapabilities {
+        self.inner.capabilities().await
+    }
+}
+
+#[cfg(all(test, feature = "ai", feature = "npu"))]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentBuilder, AgentHandler};
+    use crate::message::{Message, MessageType};
+    use crate::types::{AgentContext, InferencePriority};
+
+    /// Runs every inference request message through its backend
+    #[derive(Clone)]
+    struct InferenceAgent {
+        backend: Arc<dyn InferenceBackend>,
+        responses: Arc<Mutex<Vec<InferenceResponse>>>,
+    }
+
+    #[async_trait]
+    impl AgentHandler for InferenceAgent {
+        async fn handle_message(
+            &self,
+            _context: Arc<Mutex<AgentContext>>,
+            message: Message,
+        ) -> Result<()> {
+            let request = InferenceRequest {
+                task_id: 1,
+                model_id: "identity".to_string(),
+                input_data: message.payload.into(),
+                input_shape: vec![1, 4],
+                priority: InferencePriority::Normal,
+                named_inputs: Vec::new(),
+                timeout: None,
+            };
+            let response = self.backend.infer(request).await?;
+            self.responses.lock().unwrap().push(response);
+            Ok(())
+        }
+    }
+
+    async fn run_agent(backend: Arc<dyn InferenceBackend>, input: &[u8]) -> InferenceResponse {
+        backend
+            .load(ModelConfig {
+                model_id: "identity".to_string(),
+                model_path: concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx")
+                    .to_string(),
+                input_shape: vec![1, 4],
+                output_shape: vec![1, 4],
+                ..ModelConfig::default()
+            })
+            .await
+            .unwrap();
+
+        let agent = AgentBuilder::new("inference_agent").build(1);
+        let handler = InferenceAgent {
+            backend,
+            responses: Arc::default(),
+        };
+        agent.initialize().await.unwrap();
+        agent.start(handler.clone()).await.unwrap();
+
+        agent
+            .send_message(Message {
+                from: 0,
+                to: 1,
+                message_type: MessageType::InferenceRequest,
+                payload: input.to_vec(),
+                timestamp: 0,
+                id: None,
+            })
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        let response = handler.responses.lock().unwrap().pop();
+        response.expect("agent produced no inference response")
+    }
+
+    #[tokio::test]
+    async fn test_same_agent_runs_on_both_backends() {
+        let input: Vec<u8> = [1.0f32, 2.0, 3.0, 4.0]
+            .iter()
+            .flat_map(|x| x.to_ne_bytes())
+            .collect();
+
+        let mut onnx = MLHandler::new().unwrap();
+        onnx.initialize().await.unwrap();
+        let npu = crate::npu::init_mock_npu_subsystem().await.unwrap();
+
+        let backends: Vec<Arc<dyn InferenceBackend>> = vec![Arc::new(onnx), Arc::new(npu)];
+        for backend in backends {
+            let name = backend.name().to_string();
+            let response = run_agent(backend.clone(), &input).await;
+
+            assert!(response.success, "{} inference failed", name);
+            assert_eq!(response.output_data, input, "{} output differs", name);
+            assert_eq!(response.output_shape, vec![1, 4]);
+            let capabilities = backend.capabilities().await;
+            assert_eq!(capabilities.loaded_models, vec!["identity".to_string()]);
+        }
+    }
+}
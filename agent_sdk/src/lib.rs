@@ -5,19 +5,39 @@
 //! ML model integration, and system resource management.
 
 pub mod agent;
+pub mod backend;
+pub mod logging;
 pub mod message;
+pub mod middleware;
 pub mod ml;
+pub mod precision;
 pub mod runtime;
 pub mod types;
 
+// Assertion helpers for agent tests
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+
 // NPU Hardware Abstraction Layer (optional)
 #[cfg(feature = "npu")]
 pub mod npu;
 
 // Re-export the main types and traits for easy access
-pub use agent::{Agent, AgentBuilder, AgentConfig, AgentHandler, AgentRuntime};
-pub use message::{Message, MessageHandler, MessageType};
-pub use ml::{InferenceRequest, InferenceResponse, MLHandler, ModelConfig};
+pub use agent::{
+    Agent, AgentBuilder, AgentConfig, AgentHandler, AgentRuntime, DrainReport, InboxFairness,
+    NamePolicy, SendError,
+};
+pub use backend::{
+    BackendCapabilities, InferenceBackend, InferenceResultBus, MlWorkerPool, PooledBackend,
+    PublishingBackend,
+};
+pub use message::{Message, MessageHandler, MessageType, WireFormat};
+pub use middleware::{AuthMiddleware, LoggingMiddleware, MessageMiddleware, MetricsMiddleware};
+pub use ml::{
+    ExecutionProvider, InferenceInputSource, InferenceOutput, InferenceRequest, InferenceResponse,
+    LogSeverity, MLHandler, ModelConfig, ModelConfigBuilder, OnnxPolicy, OptimizationLevel,
+    PostProcessor, Preprocessor, ProcessedOutput, ProcessedResponse,
+};
 pub use runtime::NebulaRuntime;
 pub use types::*;
 
@@ -27,11 +47,14 @@ pub use types::{AgentCapabilities, AgentContext, AgentStatus};
 // Prelude for convenient imports
 pub mod prelude {
     pub use crate::agent::{Agent, AgentBuilder, AgentConfig, AgentHandler};
+    pub use crate::backend::InferenceBackend;
     pub use crate::message::{Message, MessageHandler, MessageType};
+    pub use crate::middleware::MessageMiddleware;
     pub use crate::ml::{InferenceRequest, InferenceResponse, MLHandler, ModelConfig};
     pub use crate::runtime::NebulaRuntime;
     pub use crate::types::{
-        AgentCapabilities, AgentContext, AgentId, AgentStatus, Error, Result, TaskId,
+        AgentCapabilities, AgentContext, AgentContextView, AgentId, AgentStatus, Error, Result,
+        TaskId,
     };
     pub use anyhow::Result as AnyResult;
     pub use log::{debug, error, info, warn};
@@ -50,14 +73,14 @@ pub const SDK_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Initialize the SDK with default configuration
 pub async fn init() -> Result<NebulaRuntime> {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    logging::init_env_logger("info");
     log::info!("NebulaOS Agent SDK v{} initialized", SDK_VERSION);
     NebulaRuntime::new().await
 }
 
 /// Initialize the SDK with custom logging
 pub async fn init_with_logger(log_level: &str) -> Result<NebulaRuntime> {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level)).init();
+    logging::init_env_logger(log_level);
     log::info!(
         "NebulaOS Agent SDK v{} initialized with log level: {}",
         SDK_VERSION,
@@ -0,0 +1,210 @@
+//! Logging module for the NebulaOS Agent SDK
+//!
+//! Tags log records with the agent that produced them. Code running inside
+//! an agent's message loop, including its handler, logs with an `agent`
+//! key-value naming the agent once the logger is wrapped in `AgentLogger`,
+//! which the SDK's `init` functions do.
+
+use log::kv::{self, Key, Source, ToValue, VisitSource};
+use log::{Log, Metadata, Record};
+use std::future::Future;
+use std::io::Write;
+
+/// Key-value key carrying the agent name
+pub const AGENT_KEY: &str = "agent";
+
+tokio::task_local! {
+    static CURRENT_AGENT: String;
+}
+
+/// Name of the agent whose task is running, if any
+pub fn current_agent() -> Option<String> {
+    CURRENT_AGENT.try_with(Clone::clone).ok()
+}
+
+/// Run `future` as part of the named agent
+pub(crate) async fn scope<F: Future>(agent: String, future: F) -> F::Output {
+    CURRENT_AGENT.scope(agent, future).await
+}
+
+/// Logger adding the current agent to records that don't already name one
+pub struct AgentLogger<L> {
+    inner: L,
+}
+
+impl<L: Log> AgentLogger<L> {
+    pub fn new(inner: L) -> Self {
+        Self { inner }
+    }
+}
+
+impl<L: Log> Log for AgentLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let agent = match current_agent() {
+            Some(agent) if record.key_values().get(Key::from_str(AGENT_KEY)).is_none() => agent,
+            _ => return self.inner.log(record),
+        };
+        let tagged = Tagged {
+            agent: &agent,
+            rest: record.key_values(),
+        };
+        self.inner
+            .log(&record.to_builder().key_values(&tagged).build());
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+/// A record's key-values with the agent in front
+struct Tagged<'a> {
+    agent: &'a str,
+    rest: &'a dyn Source,
+}
+
+impl Source for Tagged<'_> {
+    fn visit<'kvs>(&'kvs self, visitor: &mut dyn VisitSource<'kvs>) -> Result<(), kv::Error> {
+        visitor.visit_pair(Key::from_str(AGENT_KEY), self.agent.to_value())?;
+        self.rest.visit(visitor)
+    }
+}
+
+/// env_logger format showing the agent a record came from
+pub(crate) fn format_record(
+    buf: &mut env_logger::fmt::Formatter,
+    record: &Record,
+) -> std::io::Result<()> {
+    write!(
+        buf,
+        "[{} {} {}",
+        buf.timestamp(),
+        record.level(),
+        record.target()
+    )?;
+    if let Some(agent) = record.key_values().get(Key::from_str(AGENT_KEY)) {
+        write!(buf, " {}={}", AGENT_KEY, agent)?;
+    }
+    writeln!(buf, "] {}", record.args())
+}
+
+/// Install env_logger, filtered by `RUST_LOG` or else `default_filter`,
+/// behind an `AgentLogger`
+pub(crate) fn init_env_logger(default_filter: &str) {
+    let logger =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter))
+            .format(format_record)
+            .build();
+    let max_level = logger.filter();
+    log::set_boxed_logger(Box::new(AgentLogger::new(logger)))
+        .expect("a logger is already installed");
+    log::set_max_level(max_level);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentBuilder, AgentHandler};
+    use crate::message::{Message, MessageType};
+    use crate::types::AgentContext;
+    use async_trait::async_trait;
+    use std::sync::{Arc, Mutex, OnceLock};
+
+    /// Keeps every record's message and agent
+    #[derive(Default)]
+    struct CapturingLogger {
+        records: Mutex<Vec<(String, Option<String>)>>,
+    }
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            let agent = record
+                .key_values()
+                .get(Key::from_str(AGENT_KEY))
+                .map(|agent| agent.to_string());
+            self.records
+                .lock()
+                .unwrap()
+                .push((record.args().to_string(), agent));
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// The process-wide logger, installed on first use
+    fn captured() -> &'static CapturingLogger {
+        static LOGGER: OnceLock<&'static AgentLogger<CapturingLogger>> = OnceLock::new();
+        let logger = LOGGER.get_or_init(|| {
+            let logger: &'static AgentLogger<CapturingLogger> =
+                Box::leak(Box::new(AgentLogger::new(CapturingLogger::default())));
+            log::set_logger(logger).unwrap();
+            log::set_max_level(log::LevelFilter::Debug);
+            logger
+        });
+        &logger.inner
+    }
+
+    struct LoggingHandler;
+
+    #[async_trait]
+    impl AgentHandler for LoggingHandler {
+        async fn handle_message(
+            &self,
+            _context: Arc<std::sync::Mutex<AgentContext>>,
+            message: Message,
+        ) -> anyhow::Result<()> {
+            log::info!("handled {}", String::from_utf8_lossy(&message.payload));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_logs_carry_agent_name() {
+        let logger = captured();
+
+        for (id, name) in [(1, "alpha_agent"), (2, "beta_agent")] {
+            let agent = AgentBuilder::new(name).build(id);
+            agent.initialize().await.unwrap();
+            agent.start(LoggingHandler).await.unwrap();
+            agent
+                .send_message(Message {
+                    from: 0,
+                    to: id,
+                    message_type: MessageType::Data,
+                    payload: format!("log_test_{}", name).into_bytes(),
+                    timestamp: 0,
+                    id: None,
+                })
+                .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            agent.shutdown().await.unwrap();
+        }
+
+        let records = logger.records.lock().unwrap().clone();
+        for name in ["alpha_agent", "beta_agent"] {
+            let handled = format!("handled log_test_{}", name);
+            let agent = records
+                .iter()
+                .find(|(message, _)| *message == handled)
+                .map(|(_, agent)| agent.clone());
+            assert_eq!(agent, Some(Some(name.to_string())));
+        }
+        assert!(records
+            .iter()
+            .any(|(message, agent)| message.starts_with("Processing message")
+                && agent.as_deref() == Some("beta_agent")));
+
+        // Outside an agent nothing is added
+        log::info!("log_test_outside");
+        let records = logger.records.lock().unwrap();
+        assert!(records.contains(&("log_test_outside".to_string(), None)));
+    }
+}
@@ -10,13 +10,48 @@ use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 
 /// Represents a message exchanged between agents
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
     pub from: AgentId,
     pub to: AgentId,
     pub message_type: MessageType,
     pub payload: Vec<u8>,
     pub timestamp: u64, // Unix timestamp
+    /// Identifies a logical message across retries, for agents that drop duplicates
+    #[serde(default)]
+    pub id: Option<u64>,
+}
+
+impl Message {
+    /// Serialize the whole message for transport to another process
+    pub fn encode(&self, format: WireFormat) -> Vec<u8> {
+        // Every field has a plain representation in both formats, so
+        // serializing into memory cannot fail
+        match format {
+            WireFormat::Bincode => bincode::serialize(self).expect("message is bincode-encodable"),
+            WireFormat::Json => serde_json::to_vec(self).expect("message is JSON-encodable"),
+        }
+    }
+
+    /// Parse a message produced by `encode` with the same format
+    pub fn decode(bytes: &[u8], format: WireFormat) -> Result<Message> {
+        match format {
+            WireFormat::Bincode => bincode::deserialize(bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to decode bincode message: {}", e)),
+            WireFormat::Json => serde_json::from_slice(bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to decode JSON message: {}", e)),
+        }
+    }
+}
+
+/// Encoding used for messages that cross a process boundary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WireFormat {
+    /// Compact binary framing, matching the agentd scheme
+    #[default]
+    Bincode,
+    /// Human-readable JSON
+    Json,
 }
 
 /// Types of messages agents can send
@@ -34,6 +69,13 @@ pub enum MessageType {
     Error,
 }
 
+impl MessageType {
+    /// Whether messages of this type skip ahead of normal traffic in an agent's inbox
+    pub fn is_priority(&self) -> bool {
+        matches!(self, MessageType::Control)
+    }
+}
+
 /// Trait defining message handling behavior for agents
 #[async_trait]
 pub trait MessageHandler {
@@ -76,12 +118,50 @@ mod tests {
             message_type: MessageType::Data,
             payload: vec![1, 2, 3],
             timestamp: chrono::Utc::now().timestamp() as u64,
+            id: None,
         };
 
+        let before = context.lock().unwrap().last_activity;
         assert!(handler
             .handle_message(context.clone(), message)
             .await
             .is_ok());
-        assert_eq!(context.lock().unwrap().message_queue.len(), 0);
+        assert!(context.lock().unwrap().last_activity >= before);
+    }
+
+    fn sample_message() -> Message {
+        Message {
+            from: 3,
+            to: 7,
+            message_type: MessageType::InferenceRequest,
+            payload: vec![0, 1, 2, 254, 255],
+            timestamp: 1_700_000_000,
+            id: Some(42),
+        }
+    }
+
+    #[test]
+    fn test_message_round_trips_through_wire_formats() {
+        let message = sample_message();
+        for format in [WireFormat::Bincode, WireFormat::Json] {
+            let bytes = message.encode(format);
+            assert_eq!(Message::decode(&bytes, format).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated_buffers() {
+        for format in [WireFormat::Bincode, WireFormat::Json] {
+            let bytes = sample_message().encode(format);
+            for len in 0..bytes.len() {
+                assert!(
+                    Message::decode(&bytes[..len], format).is_err(),
+                    "{:?} accepted {} of {} bytes",
+                    format,
+                    len,
+                    bytes.len()
+                );
+            }
+        }
     }
 }
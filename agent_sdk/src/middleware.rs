@@ -0,0 +1,239 @@
+//! Middleware module for the NebulaOS Agent SDK
+//!
+//! Middleware wraps an agent's message handler to implement cross-cutting
+//! concerns such as logging, metrics and sender validation without
+//! repeating that logic in every handler.
+
+use crate::message::{Message, MessageType};
+use crate::types::*;
+use anyhow::Result;
+use async_trait::async_trait;
+use log::{debug, warn};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// A step in an agent's message processing chain
+///
+/// Middleware runs in registration order before the handler, and in reverse
+/// order after it.
+#[async_trait]
+pub trait MessageMiddleware: Send + Sync {
+    /// Inspect or modify a message before it reaches the handler.
+    /// Returning `Ok(None)` drops the message.
+    async fn before(
+        &self,
+        _context: Arc<Mutex<AgentContext>>,
+        message: Message,
+    ) -> Result<Option<Message>> {
+        Ok(Some(message))
+    }
+
+    /// Observe the handler's outcome for a message of the given type
+    async fn after(
+        &self,
+        _context: Arc<Mutex<AgentContext>>,
+        _message_type: &MessageType,
+        _result: &Result<()>,
+    ) {
+    }
+}
+
+/// Logs every message and handler outcome
+pub struct LoggingMiddleware;
+
+#[async_trait]
+impl MessageMiddleware for LoggingMiddleware {
+    async fn before(
+        &self,
+        _context: Arc<Mutex<AgentContext>>,
+        message: Message,
+    ) -> Result<Option<Message>> {
+        debug!(
+            "Message {:?} from agent {} ({} bytes)",
+            message.message_type,
+            message.from,
+            message.payload.len()
+        );
+        Ok(Some(message))
+    }
+
+    async fn after(
+        &self,
+        _context: Arc<Mutex<AgentContext>>,
+        message_type: &MessageType,
+        result: &Result<()>,
+    ) {
+        match result {
+            Ok(()) => debug!("Handled {:?} message", message_type),
+            Err(e) => warn!("Handler failed for {:?} message: {}", message_type, e),
+        }
+    }
+}
+
+/// Counts handled and failed messages
+#[derive(Default)]
+pub struct MetricsMiddleware {
+    pub handled: Counter,
+    pub failed: Counter,
+}
+
+/// Clones count into the same counters, so one can be given to an agent
+/// and the other kept for reading
+impl Clone for MetricsMiddleware {
+    fn clone(&self) -> Self {
+        Self {
+            handled: self.handled.share(),
+            failed: self.failed.share(),
+        }
+    }
+}
+
+impl MetricsMiddleware {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl MessageMiddleware for MetricsMiddleware {
+    async fn after(
+        &self,
+        _context: Arc<Mutex<AgentContext>>,
+        _message_type: &MessageType,
+        result: &Result<()>,
+    ) {
+        if result.is_ok() {
+            self.handled.increment();
+        } else {
+            self.failed.increment();
+        }
+    }
+}
+
+/// Drops messages from senders that are not explicitly allowed
+pub struct AuthMiddleware {
+    allowed_senders: HashSet<AgentId>,
+}
+
+impl AuthMiddleware {
+    pub fn new(allowed_senders: impl IntoIterator<Item = AgentId>) -> Self {
+        Self {
+            allowed_senders: allowed_senders.into_iter().collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl MessageMiddleware for AuthMiddleware {
+    async fn before(
+        &self,
+        _context: Arc<Mutex<AgentContext>>,
+        message: Message,
+    ) -> Result<Option<Message>> {
+        if self.allowed_senders.contains(&message.from) {
+            Ok(Some(message))
+        } else {
+            warn!("Dropping message from unauthorized agent {}", message.from);
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentBuilder, AgentHandler};
+
+    /// Drops every message of one type
+    struct DropTypeMiddleware(MessageType);
+
+    #[async_trait]
+    impl MessageMiddleware for DropTypeMiddleware {
+        async fn before(
+            &self,
+            _context: Arc<Mutex<AgentContext>>,
+            message: Message,
+        ) -> Result<Option<Message>> {
+            if message.message_type == self.0 {
+                Ok(None)
+            } else {
+                Ok(Some(message))
+            }
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingHandler {
+        seen: Arc<Mutex<Vec<MessageType>>>,
+    }
+
+    #[async_trait]
+    impl AgentHandler for RecordingHandler {
+        async fn handle_message(
+            &self,
+            _context: Arc<Mutex<AgentContext>>,
+            message: Message,
+        ) -> Result<()> {
+            self.seen.lock().unwrap().push(message.message_type);
+            Ok(())
+        }
+    }
+
+    fn message(from: AgentId, message_type: MessageType) -> Message {
+        Message {
+            from,
+            to: 1,
+            message_type,
+            payload: vec![],
+            timestamp: 0,
+            id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_drops_messages() {
+        let agent = AgentBuilder::new("test_agent").build(1);
+        let handler = RecordingHandler::default();
+        let metrics = MetricsMiddleware::new();
+
+        agent.initialize().await.unwrap();
+        agent
+            .start_with_middleware(
+                handler.clone(),
+                vec![
+                    Box::new(LoggingMiddleware),
+                    Box::new(DropTypeMiddleware(MessageType::Control)),
+                    Box::new(metrics.clone()),
+                ],
+            )
+            .await
+            .unwrap();
+
+        agent.send_message(message(0, MessageType::Data)).unwrap();
+        agent.send_message(message(0, MessageType::Control)).unwrap();
+        agent.send_message(message(0, MessageType::Data)).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let seen = handler.seen.lock().unwrap().clone();
+        assert_eq!(seen, vec![MessageType::Data, MessageType::Data]);
+        assert_eq!(metrics.handled.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_auth_middleware_filters_senders() {
+        let agent = AgentBuilder::new("test_agent").build(1);
+        let handler = RecordingHandler::default();
+
+        agent.initialize().await.unwrap();
+        agent
+            .start_with_middleware(handler.clone(), vec![Box::new(AuthMiddleware::new([7]))])
+            .await
+            .unwrap();
+
+        agent.send_message(message(7, MessageType::Data)).unwrap();
+        agent.send_message(message(8, MessageType::Error)).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        assert_eq!(*handler.seen.lock().unwrap(), vec![MessageType::Data]);
+    }
+}
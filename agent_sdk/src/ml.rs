@@ -23,6 +23,30 @@ pub struct ModelConfig {
     pub output_shape: Vec<usize>,
     pub precision: Precision,
     pub batch_size: usize,
+    pub post_processor: PostProcessor,
+    pub optimization_level: OptimizationLevel,
+    /// Threads used within a single operator (None = runtime default)
+    pub intra_op_threads: Option<usize>,
+    /// Threads used to run independent operators in parallel (None = runtime default)
+    pub inter_op_threads: Option<usize>,
+    /// Warn instead of failing when the declared shapes don't match the model
+    pub lenient_shapes: bool,
+    /// Allocate session tensors from ONNX Runtime's CPU memory arena.
+    /// Disabling it trades some speed for a smaller footprint per model.
+    pub cpu_arena: bool,
+    /// Pre-plan allocations from the memory pattern of earlier runs
+    pub memory_pattern: bool,
+    /// Execution providers to try in order; the first that can load the
+    /// model is used
+    pub provider_chain: Vec<ExecutionProvider>,
+    /// Most inferences run on the model at once; further requests wait
+    /// their turn (None = unlimited)
+    pub max_concurrent_inferences: Option<usize>,
+    /// Have ONNX Runtime profile the model's session, writing a
+    /// `<prefix>_<timestamp>.json` trace when the model is unloaded
+    pub profiling_prefix: Option<std::path::PathBuf>,
+    /// Steps applied in order to each request's raw input before inference
+    pub preprocessing: Vec<Preprocessor>,
 }
 
 impl Default for ModelConfig {
@@ -34,18 +58,507 @@ impl Default for ModelConfig {
             output_shape: vec![1, 1000],       // Default classification output
             precision: Precision::FP32,
             batch_size: 1,
+            post_processor: PostProcessor::Raw,
+            optimization_level: OptimizationLevel::Level3,
+            intra_op_threads: None,
+            inter_op_threads: None,
+            lenient_shapes: false,
+            cpu_arena: true,
+            memory_pattern: true,
+            provider_chain: vec![ExecutionProvider::Cpu],
+            max_concurrent_inferences: None,
+            profiling_prefix: None,
+            preprocessing: Vec::new(),
         }
     }
 }
 
+impl ModelConfig {
+    /// Start building a config with fluent setters
+    pub fn builder() -> ModelConfigBuilder {
+        ModelConfigBuilder::new()
+    }
+}
+
+/// Builder for `ModelConfig` that validates the result
+///
+/// Unset fields take `ModelConfig`'s defaults, except the model id and
+/// path, which must be given.
+#[derive(Debug, Clone)]
+pub struct ModelConfigBuilder {
+    config: ModelConfig,
+}
+
+impl ModelConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: ModelConfig {
+                model_id: String::new(),
+                model_path: String::new(),
+                ..ModelConfig::default()
+            },
+        }
+    }
+
+    /// Set the id requests refer to the model by
+    pub fn id(mut self, model_id: &str) -> Self {
+        self.config.model_id = model_id.to_string();
+        self
+    }
+
+    /// Set the path of the model file
+    pub fn path(mut self, model_path: &str) -> Self {
+        self.config.model_path = model_path.to_string();
+        self
+    }
+
+    pub fn input_shape(mut self, shape: Vec<usize>) -> Self {
+        self.config.input_shape = shape;
+        self
+    }
+
+    pub fn output_shape(mut self, shape: Vec<usize>) -> Self {
+        self.config.output_shape = shape;
+        self
+    }
+
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.config.precision = precision;
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.config.batch_size = batch_size;
+        self
+    }
+
+    pub fn optimization_level(mut self, level: OptimizationLevel) -> Self {
+        self.config.optimization_level = level;
+        self
+    }
+
+    pub fn post_processor(mut self, post_processor: PostProcessor) -> Self {
+        self.config.post_processor = post_processor;
+        self
+    }
+
+    /// Add a step to the input preprocessing pipeline
+    pub fn preprocess(mut self, step: Preprocessor) -> Self {
+        self.config.preprocessing.push(step);
+        self
+    }
+
+    /// Set the execution providers to try in order
+    pub fn provider_chain(mut self, providers: Vec<ExecutionProvider>) -> Self {
+        self.config.provider_chain = providers;
+        self
+    }
+
+    /// Check the config and return it
+    pub fn build(self) -> Result<ModelConfig> {
+        let config = self.config;
+        if config.model_id.is_empty() {
+            return Err(anyhow::anyhow!("Model config needs a model id"));
+        }
+        if config.model_path.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Model {} needs a model path",
+                config.model_id
+            ));
+        }
+        if config.batch_size == 0 {
+            return Err(anyhow::anyhow!(
+                "Model {} batch size must be positive",
+                config.model_id
+            ));
+        }
+        if config.input_shape.contains(&0) || config.output_shape.contains(&0) {
+            return Err(anyhow::anyhow!(
+                "Model {} shapes must not contain zero dimensions",
+                config.model_id
+            ));
+        }
+        if config.provider_chain.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Model {} needs at least one execution provider",
+                config.model_id
+            ));
+        }
+        Ok(config)
+    }
+}
+
+impl Default for ModelConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hardware backend ONNX Runtime runs a model on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ExecutionProvider {
+    Cpu,
+    Cuda,
+    TensorRt,
+    CoreMl,
+    DirectMl,
+}
+
+impl ExecutionProvider {
+    /// Short lowercase name for logs and telemetry
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExecutionProvider::Cpu => "cpu",
+            ExecutionProvider::Cuda => "cuda",
+            ExecutionProvider::TensorRt => "tensorrt",
+            ExecutionProvider::CoreMl => "coreml",
+            ExecutionProvider::DirectMl => "directml",
+        }
+    }
+
+    /// Whether the loaded ONNX Runtime can use this provider on this machine.
+    /// Without the `ai` feature only the CPU is available.
+    pub fn is_available(&self) -> bool {
+        #[cfg(feature = "ai")]
+        {
+            ort::ExecutionProvider::from(*self).is_available()
+        }
+        #[cfg(not(feature = "ai"))]
+        {
+            *self == ExecutionProvider::Cpu
+        }
+    }
+}
+
+#[cfg(feature = "ai")]
+impl From<ExecutionProvider> for ort::ExecutionProvider {
+    fn from(provider: ExecutionProvider) -> Self {
+        match provider {
+            ExecutionProvider::Cpu => ort::ExecutionProvider::CPU(Default::default()),
+            ExecutionProvider::Cuda => ort::ExecutionProvider::CUDA(Default::default()),
+            ExecutionProvider::TensorRt => ort::ExecutionProvider::TensorRT(Default::default()),
+            ExecutionProvider::CoreMl => ort::ExecutionProvider::CoreML(Default::default()),
+            ExecutionProvider::DirectMl => ort::ExecutionProvider::DirectML(Default::default()),
+        }
+    }
+}
+
+/// ONNX Runtime graph optimization level applied when a model is loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OptimizationLevel {
+    Disabled,
+    Level1,
+    Level2,
+    /// All optimizations; slowest to load, usually fastest to run
+    #[default]
+    Level3,
+}
+
+#[cfg(feature = "ai")]
+impl From<OptimizationLevel> for ort::GraphOptimizationLevel {
+    fn from(level: OptimizationLevel) -> Self {
+        match level {
+            OptimizationLevel::Disabled => ort::GraphOptimizationLevel::Disable,
+            OptimizationLevel::Level1 => ort::GraphOptimizationLevel::Level1,
+            OptimizationLevel::Level2 => ort::GraphOptimizationLevel::Level2,
+            OptimizationLevel::Level3 => ort::GraphOptimizationLevel::Level3,
+        }
+    }
+}
+
+/// Lowest severity of ONNX Runtime's own log messages that get reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LogSeverity {
+    Verbose,
+    Info,
+    #[default]
+    Warning,
+    Error,
+    Fatal,
+}
+
+#[cfg(feature = "ai")]
+impl From<LogSeverity> for ort::LoggingLevel {
+    fn from(severity: LogSeverity) -> Self {
+        match severity {
+            LogSeverity::Verbose => ort::LoggingLevel::Verbose,
+            LogSeverity::Info => ort::LoggingLevel::Info,
+            LogSeverity::Warning => ort::LoggingLevel::Warning,
+            LogSeverity::Error => ort::LoggingLevel::Error,
+            LogSeverity::Fatal => ort::LoggingLevel::Fatal,
+        }
+    }
+}
+
+/// Step preparing a raw f32 input tensor for a model
+///
+/// Image steps expect channels-last data, `[H, W, C]` or `[N, H, W, C]`,
+/// until `ToNCHW` moves the channels first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Preprocessor {
+    /// Nearest-neighbour resize of the height and width dimensions
+    Resize { height: usize, width: usize },
+    /// Per-channel `(x - mean) / std`; a single value applies to every channel
+    Normalize { mean: Vec<f32>, std: Vec<f32> },
+    /// Reorder `[N, H, W, C]`, or `[H, W, C]` as a batch of one, to `[N, C, H, W]`
+    ToNCHW,
+    /// Replace values with their int8 codes
+    Quantize(crate::precision::QuantParams),
+}
+
+impl Preprocessor {
+    /// Apply this step to `data` of the given shape, returning the new
+    /// data and shape
+    pub fn apply(&self, data: Vec<f32>, shape: Vec<usize>) -> Result<(Vec<f32>, Vec<usize>)> {
+        match self {
+            Preprocessor::Resize { height, width } => {
+                let (n, h, w, c) = channels_last_dims(&shape)?;
+                if [h, w, *height, *width].contains(&0) {
+                    return Err(anyhow::anyhow!(
+                        "Cannot resize {:?} to {}x{}",
+                        shape,
+                        height,
+                        width
+                    ));
+                }
+                let mut resized = Vec::with_capacity(n * height * width * c);
+                for batch in 0..n {
+                    for y in 0..*height {
+                        let src_y = y * h / height;
+                        for x in 0..*width {
+                            let src_x = x * w / width;
+                            let start = ((batch * h + src_y) * w + src_x) * c;
+                            resized.extend_from_slice(&data[start..start + c]);
+                        }
+                    }
+                }
+                let mut shape = shape;
+                let rank = shape.len();
+                shape[rank - 3] = *height;
+                shape[rank - 2] = *width;
+                Ok((resized, shape))
+            }
+            Preprocessor::Normalize { mean, std } => {
+                let channels = shape.last().copied().unwrap_or(1).max(1);
+                for (name, values) in [("mean", mean), ("std", std)] {
+                    if values.len() != 1 && values.len() != channels {
+                        return Err(anyhow::anyhow!(
+                            "Normalize {} has {} values for {} channels",
+                            name,
+                            values.len(),
+                            channels
+                        ));
+                    }
+                }
+                if std.contains(&0.0) {
+                    return Err(anyhow::anyhow!("Normalize std must be non-zero"));
+                }
+                let per_channel = |values: &[f32], channel: usize| values[channel % values.len()];
+                let data = data
+                    .iter()
+                    .enumerate()
+                    .map(|(i, x)| {
+                        let channel = i % channels;
+                        (x - per_channel(mean, channel)) / per_channel(std, channel)
+                    })
+                    .collect();
+                Ok((data, shape))
+            }
+            Preprocessor::ToNCHW => {
+                let (n, h, w, c) = channels_last_dims(&shape)?;
+                let mut transposed = vec![0.0; data.len()];
+                for batch in 0..n {
+                    for y in 0..h {
+                        for x in 0..w {
+                            for channel in 0..c {
+                                let src = ((batch * h + y) * w + x) * c + channel;
+                                let dst = ((batch * c + channel) * h + y) * w + x;
+                                transposed[dst] = data[src];
+                            }
+                        }
+                    }
+                }
+                Ok((transposed, vec![n, c, h, w]))
+            }
+            Preprocessor::Quantize(params) => {
+                let data = crate::precision::f32_to_int8(&data, *params)
+                    .into_iter()
+                    .map(f32::from)
+                    .collect();
+                Ok((data, shape))
+            }
+        }
+    }
+}
+
+/// Split a channels-last shape into (batch, height, width, channels)
+fn channels_last_dims(shape: &[usize]) -> Result<(usize, usize, usize, usize)> {
+    match *shape {
+        [h, w, c] => Ok((1, h, w, c)),
+        [n, h, w, c] => Ok((n, h, w, c)),
+        _ => Err(anyhow::anyhow!(
+            "Expected an [H, W, C] or [N, H, W, C] tensor, got shape {:?}",
+            shape
+        )),
+    }
+}
+
+/// What happens when ONNX Runtime cannot be set up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OnnxPolicy {
+    /// Return the error to the caller
+    #[default]
+    Strict,
+    /// Log the error and serve mock output, as without the `ai` feature
+    Lenient,
+}
+
+/// Post-processing applied to a model's f32 output tensor
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum PostProcessor {
+    /// Return the output values untouched
+    #[default]
+    Raw,
+    /// Normalize logits into probabilities
+    Softmax,
+    /// Most likely class and its probability
+    ArgMax,
+    /// The k most likely classes, highest probability first
+    TopK(usize),
+}
+
+impl PostProcessor {
+    /// Apply this post-processor to raw output values
+    pub fn apply(&self, values: &[f32]) -> ProcessedOutput {
+        match self {
+            PostProcessor::Raw => ProcessedOutput::Raw(values.to_vec()),
+            PostProcessor::Softmax => ProcessedOutput::Probabilities(helpers::softmax(values)),
+            PostProcessor::ArgMax => ProcessedOutput::Classes(helpers::top_k(&helpers::softmax(values), 1)),
+            PostProcessor::TopK(k) => ProcessedOutput::Classes(helpers::top_k(&helpers::softmax(values), *k)),
+        }
+    }
+}
+
+/// Output of a post-processor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProcessedOutput {
+    Raw(Vec<f32>),
+    Probabilities(Vec<f32>),
+    /// (class index, probability) pairs
+    Classes(Vec<(usize, f32)>),
+}
+
 /// Request for inference operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InferenceRequest {
     pub task_id: TaskId,
     pub model_id: String,
-    pub input_data: Vec<u8>,
+    /// Build from a `Vec<u8>` or a `PathBuf` with `.into()`
+    pub input_data: InferenceInputSource,
     pub input_shape: Vec<usize>,
     pub priority: InferencePriority,
+    /// Inputs for models taking several tensors, as (input name,
+    /// native-endian f32 bytes, shape). When set, `input_data` and
+    /// `input_shape` are ignored.
+    #[serde(default)]
+    pub named_inputs: Vec<(String, Vec<u8>, Vec<usize>)>,
+    /// Longest the inference may run; `None` uses the handler's default
+    #[serde(default)]
+    pub timeout: Option<std::time::Duration>,
+}
+
+impl InferenceRequest {
+    /// Fail the inference if it runs longer than `timeout`
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Add an input tensor for the model input called `name`
+    pub fn with_named_input(mut self, name: &str, data: Vec<u8>, shape: Vec<usize>) -> Self {
+        self.named_inputs.push((name.to_string(), data, shape));
+        self
+    }
+
+    /// Total size of the request's input tensors in bytes
+    pub fn input_bytes(&self) -> usize {
+        if self.named_inputs.is_empty() {
+            self.input_data.byte_len()
+        } else {
+            self.named_inputs
+                .iter()
+                .map(|(_, data, _)| data.len())
+                .sum()
+        }
+    }
+}
+
+/// Where the input tensor of an inference request comes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InferenceInputSource {
+    /// Bytes in memory, shared so cloning a request doesn't copy the tensor
+    Bytes(std::sync::Arc<[u8]>),
+    /// A file of input bytes, read only when the tensor is built so large
+    /// inputs need not be held in memory beforehand
+    FilePath(std::path::PathBuf),
+}
+
+impl InferenceInputSource {
+    /// Size of the input in bytes; 0 for a file that can't be read
+    pub fn byte_len(&self) -> usize {
+        match self {
+            InferenceInputSource::Bytes(bytes) => bytes.len(),
+            InferenceInputSource::FilePath(path) => std::fs::metadata(path)
+                .map(|metadata| metadata.len() as usize)
+                .unwrap_or(0),
+        }
+    }
+
+    /// Get the input bytes, reading the whole file for a path
+    pub fn load(&self) -> std::io::Result<std::sync::Arc<[u8]>> {
+        match self {
+            InferenceInputSource::Bytes(bytes) => Ok(bytes.clone()),
+            InferenceInputSource::FilePath(path) => Ok(std::fs::read(path)?.into()),
+        }
+    }
+
+    /// Decode up to `len` native-endian f32 values into `out`, streaming
+    /// file inputs rather than reading them whole
+    pub fn decode_f32_into(&self, len: usize, out: &mut Vec<f32>) -> std::io::Result<()> {
+        match self {
+            InferenceInputSource::Bytes(bytes) => {
+                helpers::decode_f32_into(bytes, len, out);
+                Ok(())
+            }
+            InferenceInputSource::FilePath(path) => {
+                helpers::read_f32_into(std::fs::File::open(path)?, len, out)
+            }
+        }
+    }
+}
+
+impl From<std::sync::Arc<[u8]>> for InferenceInputSource {
+    fn from(bytes: std::sync::Arc<[u8]>) -> Self {
+        InferenceInputSource::Bytes(bytes)
+    }
+}
+
+impl From<Vec<u8>> for InferenceInputSource {
+    fn from(bytes: Vec<u8>) -> Self {
+        InferenceInputSource::Bytes(bytes.into())
+    }
+}
+
+impl From<std::path::PathBuf> for InferenceInputSource {
+    fn from(path: std::path::PathBuf) -> Self {
+        InferenceInputSource::FilePath(path)
+    }
+}
+
+impl FromIterator<u8> for InferenceInputSource {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        InferenceInputSource::Bytes(iter.into_iter().collect())
+    }
 }
 
 /// Response from inference operation
@@ -53,46 +566,293 @@ pub struct InferenceRequest {
 pub struct InferenceResponse {
     pub task_id: TaskId,
     pub success: bool,
+    /// Data of the first output, kept for single-output callers
     pub output_data: Vec<u8>,
     pub output_shape: Vec<usize>,
+    /// Every output the model produced, in model order
+    pub outputs: Vec<InferenceOutput>,
     pub latency_ms: u64,
     pub error: Option<String>,
 }
 
+/// One named output tensor of a model, as native-endian f32 bytes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InferenceOutput {
+    pub name: String,
+    pub data: Vec<u8>,
+    pub shape: Vec<usize>,
+}
+
+/// Inference response with the model's post-processor applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessedResponse {
+    pub response: InferenceResponse,
+    pub output: ProcessedOutput,
+}
+
+/// How long `unload_model` waits for in-flight inferences by default
+pub const DEFAULT_UNLOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How long an inference may run when its request sets no timeout
+pub const DEFAULT_INFERENCE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
 /// Handler for ML operations
 pub struct MLHandler {
     #[cfg(feature = "ai")]
-    sessions: std::collections::HashMap<String, Session>,
+    sessions: std::sync::RwLock<std::collections::HashMap<String, std::sync::Arc<Session>>>,
     #[cfg(feature = "ai")]
     environment: Option<std::sync::Arc<Environment>>,
-    models: std::collections::HashMap<String, ModelConfig>,
+    models: std::sync::RwLock<std::collections::HashMap<String, ModelConfig>>,
+    /// Provider each loaded model was placed on
+    providers: std::sync::RwLock<std::collections::HashMap<String, ExecutionProvider>>,
+    /// Permits for models with `max_concurrent_inferences` set
+    inference_limits: std::sync::RwLock<
+        std::collections::HashMap<String, std::sync::Arc<tokio::sync::Semaphore>>,
+    >,
+    /// Profiling prefix of each model loaded with one, kept after unload
+    /// so the finished trace can still be found
+    profiling: std::sync::RwLock<std::collections::HashMap<String, std::path::PathBuf>>,
+    input_pool: std::sync::Arc<InputBufferPool>,
+    inflight: InflightTracker,
+    telemetry: std::sync::RwLock<Option<TelemetryCallback>>,
+    /// Timeout for requests that do not set their own
+    default_inference_timeout: std::sync::RwLock<std::time::Duration>,
+    onnx_policy: OnnxPolicy,
+    /// ONNX Runtime failed to start under `OnnxPolicy::Lenient`
+    #[cfg(feature = "ai")]
+    mock_fallback: bool,
+}
+
+/// Counts running inferences per model so a model can be drained before
+/// it is unloaded
+#[derive(Debug, Default)]
+struct InflightTracker {
+    state: std::sync::Mutex<InflightState>,
+    idle: tokio::sync::Notify,
+}
+
+#[derive(Debug, Default)]
+struct InflightState {
+    active: std::collections::HashMap<String, usize>,
+    draining: std::collections::HashSet<String>,
+}
+
+/// Marks one inference as running until dropped
+struct InflightGuard<'a> {
+    tracker: &'a InflightTracker,
+    model_id: String,
+}
+
+impl Drop for InflightGuard<'_> {
+    fn drop(&mut self) {
+        self.tracker.finish(&self.model_id);
+    }
+}
+
+impl InflightTracker {
+    /// Register an inference on a model, unless the model is draining
+    fn begin(&self, model_id: &str) -> Result<InflightGuard<'_>> {
+        let mut state = self.state.lock().unwrap();
+        if state.draining.contains(model_id) {
+            return Err(anyhow::anyhow!("Model {} is being unloaded", model_id));
+        }
+        *state.active.entry(model_id.to_string()).or_default() += 1;
+        Ok(InflightGuard {
+            tracker: self,
+            model_id: model_id.to_string(),
+        })
+    }
+
+    fn finish(&self, model_id: &str) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(count) = state.active.get_mut(model_id) {
+                *count -= 1;
+                if *count == 0 {
+                    state.active.remove(model_id);
+                }
+            }
+        }
+        self.idle.notify_waiters();
+    }
+
+    fn active(&self, model_id: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .active
+            .get(model_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn start_draining(&self, model_id: &str) -> Result<()> {
+        if !self.state.lock().unwrap().draining.insert(model_id.to_string()) {
+            return Err(anyhow::anyhow!("Model {} is already being unloaded", model_id));
+        }
+        Ok(())
+    }
+
+    fn stop_draining(&self, model_id: &str) {
+        self.state.lock().unwrap().draining.remove(model_id);
+    }
+
+    /// Wait until no inferences are running on the model; false on timeout
+    async fn wait_idle(&self, model_id: &str, timeout: std::time::Duration) -> bool {
+        let wait = async {
+            loop {
+                // Register for wakeups before checking to avoid a lost notify
+                let notified = self.idle.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+                if self.active(model_id) == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.is_ok()
+    }
+}
+
+/// Pool of reusable f32 input buffers, kept per model
+///
+/// Inference borrows a buffer sized for the model's input and returns it
+/// afterwards, so steady-state requests don't allocate a new backing store.
+#[derive(Debug, Default)]
+pub struct InputBufferPool {
+    buffers: std::sync::Mutex<std::collections::HashMap<String, Vec<Vec<f32>>>>,
+    allocations: std::sync::atomic::AtomicUsize,
+}
+
+impl InputBufferPool {
+    /// Maximum idle buffers retained per model
+    const MAX_IDLE_PER_MODEL: usize = 8;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Borrow an empty buffer with room for at least `len` values
+    pub fn acquire(&self, model_id: &str, len: usize) -> Vec<f32> {
+        let pooled = self
+            .buffers
+            .lock()
+            .unwrap()
+            .get_mut(model_id)
+            .and_then(|idle| idle.pop());
+
+        match pooled {
+            Some(mut buffer) => {
+                buffer.clear();
+                buffer.reserve(len);
+                buffer
+            }
+            None => {
+                self.allocations
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                Vec::with_capacity(len)
+            }
+        }
+    }
+
+    /// Return a buffer to the pool for reuse
+    pub fn release(&self, model_id: &str, buffer: Vec<f32>) {
+        let mut buffers = self.buffers.lock().unwrap();
+        let idle = buffers.entry(model_id.to_string()).or_default();
+        if idle.len() < Self::MAX_IDLE_PER_MODEL {
+            idle.push(buffer);
+        }
+    }
+
+    /// Drop all idle buffers held for a model
+    pub fn clear_model(&self, model_id: &str) {
+        self.buffers.lock().unwrap().remove(model_id);
+    }
+
+    /// Number of buffers the pool has had to allocate
+    pub fn allocations(&self) -> usize {
+        self.allocations.load(std::sync::atomic::Ordering::Relaxed)
+    }
 }
 
 impl MLHandler {
     /// Create a new ML handler
+    ///
+    /// Construction never fails; the ONNX environment is only created by
+    /// `initialize`. The `Result` is kept for API compatibility.
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            #[cfg(feature = "ai")]
-            sessions: std::collections::HashMap::new(),
-            #[cfg(feature = "ai")]
-            environment: None,
-            models: std::collections::HashMap::new(),
-        })
+        Ok(Self::default())
+    }
+
+    /// Create a handler that treats ONNX Runtime setup failures per `policy`
+    pub fn with_onnx_policy(policy: OnnxPolicy) -> Self {
+        Self {
+            onnx_policy: policy,
+            ..Self::default()
+        }
+    }
+
+    /// Whether inferences return mock output instead of running a model
+    pub fn uses_mock_inference(&self) -> bool {
+        #[cfg(feature = "ai")]
+        {
+            self.mock_fallback
+        }
+        #[cfg(not(feature = "ai"))]
+        {
+            true
+        }
     }
 
     /// Initialize the ONNX runtime environment
     #[cfg(feature = "ai")]
     pub async fn initialize(&mut self) -> Result<()> {
+        self.initialize_with_log_severity(LogSeverity::default())
+            .await
+    }
+
+    /// Initialize the ONNX runtime environment, reporting ONNX Runtime's
+    /// log messages of `severity` and above
+    #[cfg(feature = "ai")]
+    pub async fn initialize_with_log_severity(&mut self, severity: LogSeverity) -> Result<()> {
         info!("Initializing ONNX Runtime environment");
 
-        let env = Environment::builder()
-            .with_name("NebulaOSAgent")
-            .build()?
-            .into_arc();
+        self.initialize_environment(|| {
+            Ok(Environment::builder()
+                .with_name("NebulaOSAgent")
+                .with_log_level(severity.into())
+                .build()?
+                .into_arc())
+        })
+    }
 
-        self.environment = Some(env);
-        info!("✅ ONNX Runtime initialized successfully");
-        Ok(())
+    /// Install the environment `build` creates, applying the handler's
+    /// `OnnxPolicy` if it fails
+    #[cfg(feature = "ai")]
+    fn initialize_environment(
+        &mut self,
+        build: impl FnOnce() -> Result<std::sync::Arc<Environment>>,
+    ) -> Result<()> {
+        match build() {
+            Ok(env) => {
+                self.environment = Some(env);
+                self.mock_fallback = false;
+                info!("✅ ONNX Runtime initialized successfully");
+                Ok(())
+            }
+            Err(e) => match self.onnx_policy {
+                OnnxPolicy::Strict => Err(e.context("Failed to create ONNX Runtime environment")),
+                OnnxPolicy::Lenient => {
+                    warn!(
+                        "ONNX Runtime environment unavailable ({}); inference will return mock output",
+                        e
+                    );
+                    self.mock_fallback = true;
+                    Ok(())
+                }
+            },
+        }
     }
 
     #[cfg(not(feature = "ai"))]
@@ -101,8 +861,13 @@ impl MLHandler {
         Ok(())
     }
 
+    #[cfg(not(feature = "ai"))]
+    pub async fn initialize_with_log_severity(&mut self, _severity: LogSeverity) -> Result<()> {
+        self.initialize().await
+    }
+
     /// Load a model from file
-    pub async fn load_model(&mut self, config: ModelConfig) -> Result<()> {
+    pub async fn load_model(&self, config: ModelConfig) -> Result<()> {
         info!(
             "Loading model: {} from {}",
             config.model_id, config.model_path
@@ -119,10 +884,42 @@ impl MLHandler {
                     ));
                 }
 
-                // Load the ONNX model
-                let session = ort::SessionBuilder::new(env)?
-                    .with_optimization_level(ort::GraphOptimizationLevel::Level3)?
-                    .with_model_from_file(&config.model_path)?;
+                // Load the ONNX model on the first provider that accepts it
+                let mut selected = None;
+                for &provider in &config.provider_chain {
+                    if !provider.is_available() {
+                        warn!(
+                            "Model {}: {} execution provider unavailable",
+                            config.model_id,
+                            provider.name()
+                        );
+                        continue;
+                    }
+                    match Self::build_session(env, &config, provider) {
+                        Ok(session) => {
+                            selected = Some((session, provider));
+                            break;
+                        }
+                        Err(e) => warn!(
+                            "Model {}: {} execution provider failed: {}",
+                            config.model_id,
+                            provider.name(),
+                            e
+                        ),
+                    }
+                }
+                let (session, provider) = selected.ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Model {}: no execution provider in {:?} could load it",
+                        config.model_id,
+                        config.provider_chain
+                    )
+                })?;
+                info!(
+                    "Model {} using {} execution provider",
+                    config.model_id,
+                    provider.name()
+                );
 
                 // Validate model inputs/outputs
                 let inputs = &session.inputs;
@@ -137,36 +934,291 @@ impl MLHandler {
                     outputs.iter().map(|o| &o.name).collect::<Vec<_>>()
                 );
 
-                self.sessions.insert(config.model_id.clone(), session);
+                let checks = [
+                    ("input", &config.input_shape, inputs.first().map(|i| &i.dimensions)),
+                    ("output", &config.output_shape, outputs.first().map(|o| &o.dimensions)),
+                ];
+                for (kind, declared, actual) in checks {
+                    let Some(actual) = actual else { continue };
+                    if let Err(mismatch) = check_shape(kind, declared, actual) {
+                        if config.lenient_shapes {
+                            warn!("Model {}: {}", config.model_id, mismatch);
+                        } else {
+                            return Err(anyhow::anyhow!("Model {}: {}", config.model_id, mismatch));
+                        }
+                    }
+                }
+
+                self.sessions
+                    .write()
+                    .unwrap()
+                    .insert(config.model_id.clone(), std::sync::Arc::new(session));
+                self.providers
+                    .write()
+                    .unwrap()
+                    .insert(config.model_id.clone(), provider);
                 info!("✅ Model {} loaded successfully", config.model_id);
+            } else if self.mock_fallback {
+                warn!(
+                    "Model {}: ONNX Runtime unavailable, serving mock output",
+                    config.model_id
+                );
+                self.providers
+                    .write()
+                    .unwrap()
+                    .insert(config.model_id.clone(), ExecutionProvider::Cpu);
             } else {
                 return Err(anyhow::anyhow!("ONNX environment not initialized"));
             }
         }
 
-        self.models.insert(config.model_id.clone(), config);
-        Ok(())
-    }
-
-    /// Run inference on loaded model
-    pub async fn run_inference(&self, request: InferenceRequest) -> Result<InferenceResponse> {
-        let start_time = std::time::Instant::now();
-        debug!("Running inference for model: {}", request.model_id);
-
-        #[cfg(feature = "ai")]
+        #[cfg(not(feature = "ai"))]
         {
-            if let Some(session) = self.sessions.get(&request.model_id) {
-                let result = self.run_onnx_inference(session, &request).await;
-                let latency = start_time.elapsed().as_millis() as u64;
+            let provider = config
+                .provider_chain
+                .iter()
+                .copied()
+                .find(ExecutionProvider::is_available)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Model {}: no execution provider in {:?} is available",
+                        config.model_id,
+                        config.provider_chain
+                    )
+                })?;
+            self.providers
+                .write()
+                .unwrap()
+                .insert(config.model_id.clone(), provider);
+            if config.profiling_prefix.is_some() {
+                warn!(
+                    "Model {}: profiling requires the `ai` feature; ignoring",
+                    config.model_id
+                );
+            }
+        }
 
-                match result {
-                    Ok(output_data) => {
+        {
+            let mut profiling = self.profiling.write().unwrap();
+            match &config.profiling_prefix {
+                Some(prefix) => {
+                    profiling.insert(config.model_id.clone(), prefix.clone());
+                }
+                None => {
+                    profiling.remove(&config.model_id);
+                }
+            }
+        }
+
+        {
+            let mut limits = self.inference_limits.write().unwrap();
+            match config.max_concurrent_inferences {
+                Some(limit) => {
+                    let permits = tokio::sync::Semaphore::new(limit.max(1));
+                    limits.insert(config.model_id.clone(), std::sync::Arc::new(permits));
+                }
+                None => {
+                    limits.remove(&config.model_id);
+                }
+            }
+        }
+
+        self.models
+            .write()
+            .unwrap()
+            .insert(config.model_id.clone(), config);
+        Ok(())
+    }
+
+    /// Load a model whose file may not be readable yet, such as one still
+    /// being fetched to network storage
+    ///
+    /// Missing files and other I/O errors are retried up to `retries` times,
+    /// waiting `delay` before the first retry and doubling it after each.
+    /// Permission errors are not retried.
+    pub async fn load_model_with_retry(
+        &self,
+        config: ModelConfig,
+        retries: u32,
+        delay: std::time::Duration,
+    ) -> Result<()> {
+        let mut delay = delay;
+        let mut attempt = 0;
+        loop {
+            match tokio::fs::File::open(&config.model_path).await {
+                Ok(_) => return self.load_model(config).await,
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                    return Err(anyhow::anyhow!(
+                        "Model file {} is not readable: {}",
+                        config.model_path,
+                        e
+                    ));
+                }
+                Err(e) if attempt == retries => {
+                    return Err(if e.kind() == std::io::ErrorKind::NotFound {
+                        anyhow::anyhow!(
+                            "Model file not found after {} retries: {}",
+                            retries,
+                            config.model_path
+                        )
+                    } else {
+                        anyhow::anyhow!(
+                            "Model file {} unreadable after {} retries: {}",
+                            config.model_path,
+                            retries,
+                            e
+                        )
+                    });
+                }
+                Err(e) => {
+                    warn!(
+                        "Model {}: cannot open {} ({}), retrying in {:?}",
+                        config.model_id, config.model_path, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Run inference on loaded model
+    pub async fn run_inference(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+        // Wait for the model's concurrency limit, if any; queueing time is
+        // not counted as latency
+        let limit = self
+            .inference_limits
+            .read()
+            .unwrap()
+            .get(&request.model_id)
+            .cloned();
+        let _permit = match limit {
+            Some(limit) => Some(limit.acquire_owned().await?),
+            None => None,
+        };
+
+        let start_time = std::time::Instant::now();
+        let request = self.preprocess(request)?;
+        let model_id = request.model_id.clone();
+        let input_bytes = request.input_bytes();
+        let timeout = request
+            .timeout
+            .unwrap_or_else(|| self.default_inference_timeout());
+        let response = tokio::time::timeout(timeout, self.execute_inference(request))
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Inference on model {} timed out after {:?}",
+                    model_id,
+                    timeout
+                )
+            })??;
+
+        let telemetry = self.telemetry.read().unwrap().clone();
+        if let Some(callback) = telemetry {
+            let device = if cfg!(feature = "ai") {
+                self.execution_provider(&model_id)
+                    .map_or("cpu", |provider| provider.name())
+            } else {
+                "mock"
+            };
+            callback(&InferenceTelemetry {
+                model_id,
+                task_id: response.task_id,
+                latency: start_time.elapsed(),
+                device: device.to_string(),
+                success: response.success,
+                input_bytes,
+                output_bytes: response.outputs.iter().map(|o| o.data.len()).sum(),
+            });
+        }
+        Ok(response)
+    }
+
+    /// Timeout applied to requests that do not set their own
+    pub fn default_inference_timeout(&self) -> std::time::Duration {
+        *self.default_inference_timeout.read().unwrap()
+    }
+
+    /// Change the timeout applied to requests that do not set their own
+    ///
+    /// ONNX sessions run on tokio's blocking pool, so a timed-out request
+    /// returns at its deadline; the abandoned session run finishes in the
+    /// background.
+    pub fn set_default_inference_timeout(&self, timeout: std::time::Duration) {
+        *self.default_inference_timeout.write().unwrap() = timeout;
+    }
+
+    /// Run the model's preprocessing pipeline over the request's input,
+    /// replacing it with the prepared tensor
+    fn preprocess(&self, mut request: InferenceRequest) -> Result<InferenceRequest> {
+        let steps = self
+            .models
+            .read()
+            .unwrap()
+            .get(&request.model_id)
+            .map(|config| config.preprocessing.clone())
+            .unwrap_or_default();
+        if steps.is_empty() || !request.named_inputs.is_empty() {
+            return Ok(request);
+        }
+
+        let len = request.input_shape.iter().product::<usize>();
+        let mut data = Vec::with_capacity(len);
+        request
+            .input_data
+            .decode_f32_into(len, &mut data)
+            .map_err(|e| anyhow::anyhow!("Failed to read input data: {}", e))?;
+        if data.len() != len {
+            return Err(anyhow::anyhow!("Input data length mismatch"));
+        }
+
+        let mut shape = std::mem::take(&mut request.input_shape);
+        for step in &steps {
+            (data, shape) = step.apply(data, shape)?;
+        }
+        request.input_data = helpers::f32_to_bytes(&data).into();
+        request.input_shape = shape;
+        Ok(request)
+    }
+
+    /// Report every completed inference to `callback`, replacing any
+    /// previous one
+    pub fn set_telemetry(&self, callback: TelemetryCallback) {
+        *self.telemetry.write().unwrap() = Some(callback);
+    }
+
+    async fn execute_inference(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+        let start_time = std::time::Instant::now();
+        debug!("Running inference for model: {}", request.model_id);
+        let _inflight = self.inflight.begin(&request.model_id)?;
+
+        #[cfg(feature = "ai")]
+        {
+            let session = self
+                .sessions
+                .read()
+                .unwrap()
+                .get(&request.model_id)
+                .cloned();
+            if let Some(session) = session {
+                let result = self.run_onnx_inference(session, &request).await;
+                let latency = start_time.elapsed().as_millis() as u64;
+
+                match result {
+                    Ok(outputs) => {
                         info!("✅ Inference completed in {}ms", latency);
+                        let (output_data, output_shape) = outputs
+                            .first()
+                            .map(|output| (output.data.clone(), output.shape.clone()))
+                            .unwrap_or_default();
                         Ok(InferenceResponse {
                             task_id: request.task_id,
                             success: true,
                             output_data,
-                            output_shape: vec![], // TODO: Get actual output shape
+                            output_shape,
+                            outputs,
                             latency_ms: latency,
                             error: None,
                         })
@@ -178,11 +1230,14 @@ impl MLHandler {
                             success: false,
                             output_data: vec![],
                             output_shape: vec![],
+                            outputs: vec![],
                             latency_ms: latency,
                             error: Some(e.to_string()),
                         })
                     }
                 }
+            } else if self.mock_fallback && self.is_model_loaded(&request.model_id) {
+                Ok(Self::mock_inference(&request, start_time).await)
             } else {
                 Err(anyhow::anyhow!("Model {} not loaded", request.model_id))
             }
@@ -190,93 +1245,369 @@ impl MLHandler {
 
         #[cfg(not(feature = "ai"))]
         {
-            // Mock inference for testing without AI features
-            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-            let latency = start_time.elapsed().as_millis() as u64;
-
-            Ok(InferenceResponse {
-                task_id: request.task_id,
-                success: true,
-                output_data: vec![0.5f32.to_ne_bytes().to_vec(); 10].concat(), // Mock output
-                output_shape: vec![1, 10],
-                latency_ms: latency,
-                error: None,
-            })
+            Ok(Self::mock_inference(&request, start_time).await)
+        }
+    }
+
+    /// Fixed output served when no ONNX Runtime is available
+    async fn mock_inference(
+        request: &InferenceRequest,
+        start_time: std::time::Instant,
+    ) -> InferenceResponse {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let latency = start_time.elapsed().as_millis() as u64;
+
+        let output_data = vec![0.5f32.to_ne_bytes().to_vec(); 10].concat(); // Mock output
+        let output_shape = vec![1, 10];
+        InferenceResponse {
+            task_id: request.task_id,
+            success: true,
+            outputs: vec![InferenceOutput {
+                name: "output".to_string(),
+                data: output_data.clone(),
+                shape: output_shape.clone(),
+            }],
+            output_data,
+            output_shape,
+            latency_ms: latency,
+            error: None,
+        }
+    }
+
+    /// Blocking version of `run_inference` for non-async callers
+    ///
+    /// Drives the async path on a private current-thread runtime. This must
+    /// not be called from within an async context; doing so returns an error
+    /// instead of blocking the executor.
+    pub fn run_inference_blocking(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(anyhow::anyhow!(
+                "run_inference_blocking called from within an async runtime; use run_inference instead"
+            ));
         }
+
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(self.run_inference(request))
+    }
+
+    /// Run inference and apply the model's configured post-processor
+    pub async fn run_inference_processed(
+        &self,
+        request: InferenceRequest,
+    ) -> Result<ProcessedResponse> {
+        let post_processor = self
+            .models
+            .read()
+            .unwrap()
+            .get(&request.model_id)
+            .map(|config| config.post_processor.clone())
+            .unwrap_or_default();
+
+        let response = self.run_inference(request).await?;
+        let values = helpers::bytes_to_f32(&response.output_data);
+        let output = post_processor.apply(&values);
+
+        Ok(ProcessedResponse { response, output })
+    }
+
+    /// Change the post-processor of a loaded model
+    pub fn set_post_processor(&mut self, model_id: &str, post_processor: PostProcessor) -> Result<()> {
+        let config = self
+            .models
+            .get_mut()
+            .unwrap()
+            .get_mut(model_id)
+            .ok_or_else(|| anyhow::anyhow!("Model {} not loaded", model_id))?;
+        config.post_processor = post_processor;
+        Ok(())
     }
 
+    #[cfg(feature = "ai")]
+    fn build_session(
+        env: &std::sync::Arc<Environment>,
+        config: &ModelConfig,
+        provider: ExecutionProvider,
+    ) -> Result<Session> {
+        let allocator = if config.cpu_arena {
+            ort::AllocatorType::Arena
+        } else {
+            ort::AllocatorType::Device
+        };
+        let mut builder = ort::SessionBuilder::new(env)?
+            .with_execution_providers([provider.into()])?
+            .with_optimization_level(config.optimization_level.into())?
+            .with_allocator(allocator)?
+            .with_memory_pattern(config.memory_pattern)?;
+        if let Some(threads) = config.intra_op_threads {
+            builder = builder.with_intra_threads(threads.try_into()?)?;
+        }
+        if let Some(threads) = config.inter_op_threads {
+            builder = builder.with_inter_threads(threads.try_into()?)?;
+        }
+        if let Some(prefix) = &config.profiling_prefix {
+            builder = builder.with_profiling(prefix.to_string_lossy())?;
+        }
+        Ok(builder.with_model_from_file(&config.model_path)?)
+    }
+
+    /// Run the session on tokio's blocking pool, so it neither stalls the
+    /// executor nor outlives the request's timeout
     #[cfg(feature = "ai")]
     async fn run_onnx_inference(
         &self,
+        session: std::sync::Arc<Session>,
+        request: &InferenceRequest,
+    ) -> Result<Vec<InferenceOutput>> {
+        let input_pool = self.input_pool.clone();
+        let request = request.clone();
+        tokio::task::spawn_blocking(move || {
+            Self::run_onnx_blocking(&session, &input_pool, &request)
+        })
+        .await?
+    }
+
+    #[cfg(feature = "ai")]
+    fn run_onnx_blocking(
         session: &Session,
+        input_pool: &InputBufferPool,
         request: &InferenceRequest,
-    ) -> Result<Vec<u8>> {
+    ) -> Result<Vec<InferenceOutput>> {
+        if !request.named_inputs.is_empty() {
+            return Self::run_named_inputs(session, &request.named_inputs);
+        }
+
         // Convert input data to ndarray
         let input_len = request.input_shape.iter().product::<usize>();
 
-        // Assume f32 input data for simplicity
-        let input_f32: Vec<f32> = request
+        // Assume f32 input data for simplicity, decoded into a pooled buffer
+        let mut input_f32 = input_pool.acquire(&request.model_id, input_len);
+        let decoded = request
             .input_data
-            .chunks_exact(4)
-            .take(input_len)
-            .map(|chunk| f32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-            .collect();
+            .decode_f32_into(input_len, &mut input_f32);
 
-        if input_f32.len() != input_len {
-            return Err(anyhow::anyhow!("Input data length mismatch"));
+        let result = if let Err(e) = decoded {
+            Err(anyhow::anyhow!("Failed to read input data: {}", e))
+        } else if input_f32.len() != input_len {
+            Err(anyhow::anyhow!("Input data length mismatch"))
+        } else {
+            Self::run_session(
+                session,
+                &[(request.input_shape.as_slice(), input_f32.as_slice())],
+            )
+        };
+
+        input_pool.release(&request.model_id, input_f32);
+        result
+    }
+
+    /// Run a session on named inputs, passing each to the model input of
+    /// the same name
+    #[cfg(feature = "ai")]
+    fn run_named_inputs(
+        session: &Session,
+        named_inputs: &[(String, Vec<u8>, Vec<usize>)],
+    ) -> Result<Vec<InferenceOutput>> {
+        if let Some((name, _, _)) = named_inputs
+            .iter()
+            .find(|(name, _, _)| !session.inputs.iter().any(|input| &input.name == name))
+        {
+            return Err(anyhow::anyhow!("Model has no input named {}", name));
+        }
+
+        let mut decoded = Vec::with_capacity(session.inputs.len());
+        for input in &session.inputs {
+            let (_, data, shape) = named_inputs
+                .iter()
+                .find(|(name, _, _)| name == &input.name)
+                .ok_or_else(|| anyhow::anyhow!("Missing model input {}", input.name))?;
+            let values = helpers::bytes_to_f32(data);
+            if values.len() != shape.iter().product::<usize>() {
+                return Err(anyhow::anyhow!("Input {} data length mismatch", input.name));
+            }
+            decoded.push((shape.as_slice(), values));
         }
 
-        // Create input tensor
-        let input_tensor =
-            ndarray::Array::from_shape_vec(request.input_shape.clone(), input_f32)?.into_dyn();
+        let inputs: Vec<(&[usize], &[f32])> = decoded
+            .iter()
+            .map(|(shape, values)| (*shape, values.as_slice()))
+            .collect();
+        Self::run_session(session, &inputs)
+    }
+
+    /// Run a session on input tensors given in the model's input order
+    #[cfg(feature = "ai")]
+    fn run_session(
+        session: &Session,
+        inputs: &[(&[usize], &[f32])],
+    ) -> Result<Vec<InferenceOutput>> {
+        // Create input tensors as views over the callers' buffers
+        let tensors = inputs
+            .iter()
+            .map(|(shape, data)| -> Result<_> {
+                let view = ndarray::ArrayView::from_shape(shape.to_vec(), *data)?;
+                Ok(ndarray::CowArray::from(view))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         // Run inference
         let allocator = session.allocator();
-        let tensor_ref = input_tensor.try_into()?;
-        let input_value = Value::from_array(allocator, &tensor_ref)?;
+        let input_values = tensors
+            .iter()
+            .map(|tensor| Value::from_array(allocator, tensor))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
 
-        let outputs = session.run(vec![input_value])?;
+        let outputs = session.run(input_values)?;
 
         if outputs.is_empty() {
             return Err(anyhow::anyhow!("No output from model"));
         }
 
-        // Extract output data
-        let output_tensor = outputs[0].try_extract::<f32>()?;
-        let output_data: Vec<u8> = output_tensor
-            .view()
+        // Extract every output with its name and shape
+        outputs
             .iter()
-            .flat_map(|&x| x.to_ne_bytes())
-            .collect();
-
-        Ok(output_data)
+            .zip(&session.outputs)
+            .map(|(value, info)| -> Result<InferenceOutput> {
+                let tensor = value.try_extract::<f32>()?;
+                let view = tensor.view();
+                Ok(InferenceOutput {
+                    name: info.name.clone(),
+                    data: view.iter().flat_map(|&x| x.to_ne_bytes()).collect(),
+                    shape: view.shape().to_vec(),
+                })
+            })
+            .collect()
     }
 
     /// Get information about loaded models
     pub fn get_loaded_models(&self) -> Vec<String> {
-        self.models.keys().cloned().collect()
+        self.models.read().unwrap().keys().cloned().collect()
     }
 
     /// Check if a model is loaded
     pub fn is_model_loaded(&self, model_id: &str) -> bool {
-        self.models.contains_key(model_id)
+        self.models.read().unwrap().contains_key(model_id)
+    }
+
+    /// Execution provider a loaded model runs on
+    pub fn execution_provider(&self, model_id: &str) -> Option<ExecutionProvider> {
+        self.providers.read().unwrap().get(model_id).copied()
+    }
+
+    /// Newest profiling trace written for a model loaded with a
+    /// `profiling_prefix`
+    ///
+    /// ONNX Runtime finishes the trace when the model's session ends, so
+    /// the current load's trace only appears after the model is unloaded.
+    pub fn profiling_path(&self, model_id: &str) -> Option<std::path::PathBuf> {
+        let prefix = self.profiling.read().unwrap().get(model_id).cloned()?;
+        let stem = format!("{}_", prefix.file_name()?.to_string_lossy());
+        let dir = match prefix.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => std::path::PathBuf::from("."),
+        };
+        std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name().map_or(false, |name| {
+                    let name = name.to_string_lossy();
+                    name.starts_with(&stem) && name.ends_with(".json")
+                })
+            })
+            .max()
     }
 
-    /// Unload a model
-    pub async fn unload_model(&mut self, model_id: &str) -> Result<()> {
+    /// Unload a model once its in-flight inferences finish, waiting at
+    /// most `DEFAULT_UNLOAD_TIMEOUT`
+    pub async fn unload_model(&self, model_id: &str) -> Result<()> {
+        self.unload_model_with_timeout(model_id, DEFAULT_UNLOAD_TIMEOUT)
+            .await
+    }
+
+    /// Unload a model once its in-flight inferences finish
+    ///
+    /// New inferences on the model are rejected while it drains. If the
+    /// running ones don't finish within `timeout`, the model stays loaded,
+    /// accepts inferences again and an error is returned.
+    pub async fn unload_model_with_timeout(
+        &self,
+        model_id: &str,
+        timeout: std::time::Duration,
+    ) -> Result<()> {
+        self.inflight.start_draining(model_id)?;
+        if !self.inflight.wait_idle(model_id, timeout).await {
+            self.inflight.stop_draining(model_id);
+            return Err(anyhow::anyhow!(
+                "Timed out after {:?} waiting for inferences on model {}",
+                timeout,
+                model_id
+            ));
+        }
+
         #[cfg(feature = "ai")]
-        self.sessions.remove(model_id);
+        self.sessions.write().unwrap().remove(model_id);
 
-        self.models.remove(model_id);
+        self.models.write().unwrap().remove(model_id);
+        self.providers.write().unwrap().remove(model_id);
+        self.inference_limits.write().unwrap().remove(model_id);
+        self.input_pool.clear_model(model_id);
+        self.inflight.stop_draining(model_id);
         info!("Model {} unloaded", model_id);
         Ok(())
     }
 }
 
 impl Default for MLHandler {
+    /// An empty, uninitialized handler with no models loaded
     fn default() -> Self {
-        Self::new().unwrap()
+        Self {
+            #[cfg(feature = "ai")]
+            sessions: std::sync::RwLock::new(std::collections::HashMap::new()),
+            #[cfg(feature = "ai")]
+            environment: None,
+            models: std::sync::RwLock::new(std::collections::HashMap::new()),
+            providers: std::sync::RwLock::new(std::collections::HashMap::new()),
+            inference_limits: std::sync::RwLock::new(std::collections::HashMap::new()),
+            profiling: std::sync::RwLock::new(std::collections::HashMap::new()),
+            input_pool: std::sync::Arc::default(),
+            inflight: InflightTracker::default(),
+            telemetry: std::sync::RwLock::new(None),
+            default_inference_timeout: std::sync::RwLock::new(DEFAULT_INFERENCE_TIMEOUT),
+            onnx_policy: OnnxPolicy::default(),
+            #[cfg(feature = "ai")]
+            mock_fallback: false,
+        }
+    }
+}
+
+/// Compare a declared shape with a model's, where `None` marks a dynamic
+/// dimension that matches any size
+#[cfg_attr(not(feature = "ai"), allow(dead_code))]
+fn check_shape(kind: &str, declared: &[usize], actual: &[Option<u32>]) -> Result<(), String> {
+    let matches = declared.len() == actual.len()
+        && declared
+            .iter()
+            .zip(actual)
+            .all(|(&want, have)| match have {
+                Some(have) => *have as usize == want,
+                None => true,
+            });
+    if matches {
+        return Ok(());
     }
+
+    let actual: Vec<String> = actual
+        .iter()
+        .map(|dim| dim.map_or("?".to_string(), |d| d.to_string()))
+        .collect();
+    Err(format!(
+        "declared {} shape {:?} does not match the model's [{}]",
+        kind,
+        declared,
+        actual.join(", ")
+    ))
 }
 
 /// Helper functions for common ML operations
@@ -303,6 +1634,149 @@ pub mod helpers {
         Ok(tensor)
     }
 
+    /// Decode native-endian f32 bytes, ignoring any trailing partial value
+    pub fn bytes_to_f32(data: &[u8]) -> Vec<f32> {
+        data.chunks_exact(4)
+            .map(|chunk| f32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect()
+    }
+
+    /// Encode f32 values as native-endian bytes
+    pub fn f32_to_bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|x| x.to_ne_bytes()).collect()
+    }
+
+    /// Decode up to `len` native-endian f32 values into `out`, reusing its allocation
+    pub fn decode_f32_into(data: &[u8], len: usize, out: &mut Vec<f32>) {
+        out.clear();
+        out.extend(
+            data.chunks_exact(4)
+                .take(len)
+                .map(|chunk| f32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])),
+        );
+    }
+
+    /// Like `decode_f32_into`, but reads the bytes from `reader` as they
+    /// are decoded
+    pub fn read_f32_into(
+        reader: impl std::io::Read,
+        len: usize,
+        out: &mut Vec<f32>,
+    ) -> std::io::Result<()> {
+        use std::io::Read;
+
+        out.clear();
+        let mut reader = std::io::BufReader::new(reader);
+        let mut chunk = [0u8; 4];
+        while out.len() < len {
+            match reader.read_exact(&mut chunk) {
+                Ok(()) => out.push(f32::from_ne_bytes(chunk)),
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Numerically stable softmax
+    pub fn softmax(logits: &[f32]) -> Vec<f32> {
+        let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let exps: Vec<f32> = logits.iter().map(|&x| (x - max).exp()).collect();
+        let sum: f32 = exps.iter().sum();
+        exps.iter().map(|&x| x / sum).collect()
+    }
+
+    /// Indices and values of the `k` largest entries, largest first
+    pub fn top_k(values: &[f32], k: usize) -> Vec<(usize, f32)> {
+        let mut indexed: Vec<(usize, f32)> = values.iter().copied().enumerate().collect();
+        indexed.sort_by(|a, b| b.1.total_cmp(&a.1));
+        indexed.truncate(k);
+        indexed
+    }
+
+    /// Check that flat `data` fills `shape` exactly and return it for that
+    /// shape. Data is row-major, so the values themselves don't move.
+    pub fn reshape(data: &[f32], shape: &[usize]) -> Result<Vec<f32>> {
+        check_element_count(data.len(), shape)?;
+        Ok(data.to_vec())
+    }
+
+    /// Flatten equally sized rows into row-major data and its `[rows, cols]` shape
+    pub fn flatten(rows: &[Vec<f32>]) -> Result<(Vec<f32>, Vec<usize>)> {
+        let cols = rows.first().map_or(0, Vec::len);
+        if let Some((index, row)) = rows.iter().enumerate().find(|(_, row)| row.len() != cols) {
+            return Err(anyhow::anyhow!(
+                "row {} has {} values, expected {}",
+                index,
+                row.len(),
+                cols
+            ));
+        }
+        Ok((rows.concat(), vec![rows.len(), cols]))
+    }
+
+    /// Repeat `data` of `shape` along size-1 or missing leading dimensions
+    /// to fill `target`, following NumPy broadcasting rules
+    pub fn broadcast_to(data: &[f32], shape: &[usize], target: &[usize]) -> Result<Vec<f32>> {
+        check_element_count(data.len(), shape)?;
+        if shape.len() > target.len() {
+            return Err(anyhow::anyhow!(
+                "cannot broadcast {:?} to fewer dimensions {:?}",
+                shape,
+                target
+            ));
+        }
+
+        // Align trailing dimensions; missing leading ones act as size 1
+        let source: Vec<usize> = std::iter::repeat(1)
+            .take(target.len() - shape.len())
+            .chain(shape.iter().copied())
+            .collect();
+        if source.iter().zip(target).any(|(&s, &t)| s != t && s != 1) {
+            return Err(anyhow::anyhow!(
+                "cannot broadcast {:?} to {:?}",
+                shape,
+                target
+            ));
+        }
+
+        // Row-major source strides, zero along broadcast dimensions
+        let mut strides = vec![0; target.len()];
+        let mut stride = 1;
+        for axis in (0..target.len()).rev() {
+            if source[axis] != 1 {
+                strides[axis] = stride;
+            }
+            stride *= source[axis];
+        }
+
+        let total: usize = target.iter().product();
+        let mut output = Vec::with_capacity(total);
+        for index in 0..total {
+            let mut remaining = index;
+            let mut offset = 0;
+            for axis in (0..target.len()).rev() {
+                offset += (remaining % target[axis]) * strides[axis];
+                remaining /= target[axis];
+            }
+            output.push(data[offset]);
+        }
+        Ok(output)
+    }
+
+    fn check_element_count(len: usize, shape: &[usize]) -> Result<()> {
+        let expected: usize = shape.iter().product();
+        if len != expected {
+            return Err(anyhow::anyhow!(
+                "{} values cannot fill shape {:?} ({} values)",
+                len,
+                shape,
+                expected
+            ));
+        }
+        Ok(())
+    }
+
     /// Convert text to simple token IDs (mock tokenization)
     pub fn text_to_tokens(text: &str, max_length: usize) -> Vec<u32> {
         let mut tokens: Vec<u32> = text.chars().take(max_length).map(|c| c as u32).collect();
@@ -324,6 +1798,235 @@ mod tests {
         assert!(handler.get_loaded_models().is_empty());
     }
 
+    #[test]
+    fn test_model_config_builder() {
+        let config = ModelConfig::builder()
+            .id("classifier")
+            .path("models/classifier.onnx")
+            .input_shape(vec![1, 3, 32, 32])
+            .precision(Precision::FP16)
+            .batch_size(8)
+            .optimization_level(OptimizationLevel::Level1)
+            .build()
+            .unwrap();
+        assert_eq!(config.model_id, "classifier");
+        assert_eq!(config.model_path, "models/classifier.onnx");
+        assert_eq!(config.input_shape, vec![1, 3, 32, 32]);
+        assert_eq!(config.precision, Precision::FP16);
+        assert_eq!(config.batch_size, 8);
+        assert_eq!(config.optimization_level, OptimizationLevel::Level1);
+        assert_eq!(config.output_shape, ModelConfig::default().output_shape);
+
+        let builder = ModelConfig::builder().id("classifier").path("model.onnx");
+        let err = builder.clone().batch_size(0).build().unwrap_err();
+        assert!(err.to_string().contains("batch size"), "{}", err);
+        assert!(ModelConfig::builder().path("model.onnx").build().is_err());
+        assert!(ModelConfig::builder().id("classifier").build().is_err());
+        assert!(builder.input_shape(vec![1, 0]).build().is_err());
+    }
+
+    #[test]
+    fn test_preprocessing_normalizes_and_transposes() {
+        let handler = MLHandler::new().unwrap();
+        handler.models.write().unwrap().insert(
+            "image".to_string(),
+            ModelConfig {
+                model_id: "image".to_string(),
+                preprocessing: vec![
+                    Preprocessor::Normalize {
+                        mean: vec![1.0, 2.0],
+                        std: vec![2.0, 4.0],
+                    },
+                    Preprocessor::ToNCHW,
+                ],
+                ..ModelConfig::default()
+            },
+        );
+
+        // A 2x2 image with two channels, channels last
+        let pixels = [1.0f32, 4.0, 3.0, 8.0, 5.0, 12.0, 7.0, 16.0];
+        let request = InferenceRequest {
+            task_id: 1,
+            model_id: "image".to_string(),
+            input_data: helpers::f32_to_bytes(&pixels).into(),
+            input_shape: vec![2, 2, 2],
+            priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
+        };
+
+        let prepared = handler.preprocess(request.clone()).unwrap();
+        assert_eq!(prepared.input_shape, vec![1, 2, 2, 2]);
+        let InferenceInputSource::Bytes(bytes) = &prepared.input_data else {
+            panic!("prepared input should be in memory");
+        };
+        assert_eq!(
+            helpers::bytes_to_f32(bytes),
+            vec![0.0, 1.0, 2.0, 3.0, 0.5, 1.5, 2.5, 3.5]
+        );
+
+        let short = InferenceRequest {
+            input_shape: vec![2, 2, 3],
+            ..request
+        };
+        assert!(handler.preprocess(short).is_err());
+    }
+
+    #[test]
+    fn test_resize_and_quantize_steps() {
+        let resize = Preprocessor::Resize {
+            height: 4,
+            width: 4,
+        };
+        let (data, shape) = resize
+            .apply(vec![1.0, 2.0, 3.0, 4.0], vec![2, 2, 1])
+            .unwrap();
+        assert_eq!(shape, vec![4, 4, 1]);
+        assert_eq!(&data[..4], &[1.0, 1.0, 2.0, 2.0]);
+        assert_eq!(&data[12..], &[3.0, 3.0, 4.0, 4.0]);
+        assert!(resize.apply(vec![1.0; 4], vec![4]).is_err());
+
+        let quantize = Preprocessor::Quantize(crate::precision::QuantParams::new(0.5, 1));
+        let (data, _) = quantize.apply(vec![1.0, -1.0], vec![2]).unwrap();
+        assert_eq!(data, vec![3.0, -1.0]);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "ai")]
+    async fn test_onnx_policy_when_environment_fails() {
+        let fail = || -> Result<std::sync::Arc<Environment>> {
+            Err(anyhow::anyhow!("simulated ONNX Runtime failure"))
+        };
+        let config = ModelConfig {
+            model_id: "missing_runtime".to_string(),
+            ..ModelConfig::default()
+        };
+        let request = InferenceRequest {
+            task_id: 3,
+            model_id: "missing_runtime".to_string(),
+            input_data: vec![0; 4].into(),
+            input_shape: vec![1, 1],
+            priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
+        };
+
+        let mut strict = MLHandler::with_onnx_policy(OnnxPolicy::Strict);
+        let err = strict.initialize_environment(fail).unwrap_err();
+        assert!(format!("{:#}", err).contains("simulated"), "{:#}", err);
+        assert!(!strict.uses_mock_inference());
+        assert!(strict.load_model(config.clone()).await.is_err());
+
+        let mut lenient = MLHandler::with_onnx_policy(OnnxPolicy::Lenient);
+        lenient.initialize_environment(fail).unwrap();
+        assert!(lenient.uses_mock_inference());
+        lenient.load_model(config).await.unwrap();
+        let response = lenient.run_inference(request).await.unwrap();
+        assert!(response.success);
+        assert_eq!(response.task_id, 3);
+        assert_eq!(response.output_shape, vec![1, 10]);
+    }
+
+    #[test]
+    fn test_default_matches_new() {
+        let default_handler = MLHandler::default();
+        let new_handler = MLHandler::new().unwrap();
+
+        assert!(default_handler.get_loaded_models().is_empty());
+        assert_eq!(
+            default_handler.get_loaded_models(),
+            new_handler.get_loaded_models()
+        );
+
+        #[cfg(feature = "ai")]
+        {
+            assert!(default_handler.environment.is_none());
+            assert!(default_handler.sessions.read().unwrap().is_empty());
+            assert_eq!(
+                default_handler.environment.is_none(),
+                new_handler.environment.is_none()
+            );
+        }
+    }
+
+    #[test]
+    fn test_reshape_and_flatten() {
+        let data: Vec<f32> = (0..6).map(|x| x as f32).collect();
+        assert_eq!(helpers::reshape(&data, &[2, 3]).unwrap(), data);
+        assert_eq!(helpers::reshape(&data, &[1, 6, 1]).unwrap(), data);
+
+        let rows = vec![vec![0.0, 1.0, 2.0], vec![3.0, 4.0, 5.0]];
+        let (flat, shape) = helpers::flatten(&rows).unwrap();
+        assert_eq!(flat, data);
+        assert_eq!(shape, vec![2, 3]);
+        assert!(helpers::flatten(&[vec![1.0], vec![2.0, 3.0]]).is_err());
+    }
+
+    #[test]
+    fn test_reshape_rejects_element_count_mismatch() {
+        let err = helpers::reshape(&[1.0; 6], &[1, 3, 224, 224]).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("6 values cannot fill shape [1, 3, 224, 224]"));
+    }
+
+    #[test]
+    fn test_broadcast_to() {
+        let row = [1.0, 2.0, 3.0];
+        let tiled = helpers::broadcast_to(&row, &[1, 3], &[4, 3]).unwrap();
+        assert_eq!(tiled, [1.0, 2.0, 3.0].repeat(4));
+
+        // Missing leading dimensions broadcast too, e.g. per-channel values into NCHW
+        let channels = helpers::broadcast_to(&[0.5, 1.5], &[2, 1, 1], &[1, 2, 2, 2]).unwrap();
+        assert_eq!(channels, vec![0.5, 0.5, 0.5, 0.5, 1.5, 1.5, 1.5, 1.5]);
+
+        assert!(helpers::broadcast_to(&row, &[1, 3], &[4, 2]).is_err());
+        assert!(helpers::broadcast_to(&row, &[1, 3], &[3]).is_err());
+    }
+
+    #[test]
+    fn test_request_clone_shares_input() {
+        let request = InferenceRequest {
+            task_id: 1,
+            model_id: "test_model".to_string(),
+            input_data: vec![7u8; 8 * 1024 * 1024].into(),
+            input_shape: vec![1, 2 * 1024 * 1024],
+            priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
+        };
+
+        let copy = request.clone();
+        let (InferenceInputSource::Bytes(original), InferenceInputSource::Bytes(copied)) =
+            (&request.input_data, &copy.input_data)
+        else {
+            panic!("expected in-memory inputs");
+        };
+        assert!(std::sync::Arc::ptr_eq(original, copied));
+        assert_eq!(std::sync::Arc::strong_count(original), 2);
+    }
+
+    #[test]
+    fn test_file_input_decodes_like_bytes() {
+        let values = [1.0f32, -2.5, 3.25, 4.0, 5.5];
+        let bytes: Vec<u8> = values.iter().flat_map(|x| x.to_ne_bytes()).collect();
+        let path = std::env::temp_dir().join(format!("nebula_input_{}.bin", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+
+        let in_memory = InferenceInputSource::from(bytes);
+        let on_disk = InferenceInputSource::from(path.clone());
+        assert_eq!(on_disk.byte_len(), in_memory.byte_len());
+        assert_eq!(on_disk.load().unwrap(), in_memory.load().unwrap());
+        for len in [0, 3, 5, 8] {
+            let (mut expected, mut actual) = (Vec::new(), Vec::new());
+            in_memory.decode_f32_into(len, &mut expected).unwrap();
+            on_disk.decode_f32_into(len, &mut actual).unwrap();
+            assert_eq!(actual, expected);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(on_disk.decode_f32_into(4, &mut Vec::new()).is_err());
+    }
+
     #[tokio::test]
     async fn test_mock_inference() {
         let handler = MLHandler::new().unwrap();
@@ -331,9 +2034,11 @@ mod tests {
         let request = InferenceRequest {
             task_id: 1,
             model_id: "test_model".to_string(),
-            input_data: vec![0; 100],
+            input_data: vec![0; 100].into(),
             input_shape: vec![1, 25],
             priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
         };
 
         // This should work even without loading a model in mock mode
@@ -345,6 +2050,717 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_load_model_retries_until_file_appears() {
+        let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx");
+        let dir = tempfile::tempdir().unwrap();
+        let model_path = dir.path().join("late.onnx");
+
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+        let config = ModelConfig {
+            model_id: "late".to_string(),
+            model_path: model_path.to_string_lossy().into_owned(),
+            input_shape: vec![1, 4],
+            output_shape: vec![1, 4],
+            ..ModelConfig::default()
+        };
+        let delay = std::time::Duration::from_millis(50);
+
+        // Gives up once the retries run out
+        let err = handler
+            .load_model_with_retry(config.clone(), 1, delay)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not found after 1 retries"));
+
+        // The file lands between the first and second retry
+        let writer = {
+            let model_path = model_path.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(75)).await;
+                std::fs::copy(fixture, model_path).unwrap();
+            })
+        };
+        handler
+            .load_model_with_retry(config, 5, delay)
+            .await
+            .unwrap();
+        writer.await.unwrap();
+        assert!(handler.is_model_loaded("late"));
+    }
+
+    #[cfg(feature = "ai")]
+    #[tokio::test]
+    async fn test_load_model_at_optimization_levels() {
+        let model_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../agents/filesystem_agent/simple_file_access_predictor.onnx"
+        );
+        if !std::path::Path::new(model_path).exists() {
+            return;
+        }
+
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+
+        for (model_id, level) in [
+            ("disabled", OptimizationLevel::Disabled),
+            ("level3", OptimizationLevel::Level3),
+        ] {
+            let config = ModelConfig {
+                model_id: model_id.to_string(),
+                model_path: model_path.to_string(),
+                input_shape: vec![1, 8],
+                output_shape: vec![1, 1],
+                optimization_level: level,
+                ..ModelConfig::default()
+            };
+            handler.load_model(config).await.unwrap();
+            assert!(handler.is_model_loaded(model_id));
+        }
+    }
+
+    #[cfg(feature = "ai")]
+    #[tokio::test]
+    async fn test_load_model_with_thread_counts() {
+        let model_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../agents/filesystem_agent/simple_file_access_predictor.onnx"
+        );
+        if !std::path::Path::new(model_path).exists() {
+            return;
+        }
+
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+        handler
+            .load_model(ModelConfig {
+                model_id: "pinned".to_string(),
+                model_path: model_path.to_string(),
+                input_shape: vec![1, 8],
+                output_shape: vec![1, 1],
+                intra_op_threads: Some(2),
+                inter_op_threads: Some(1),
+                ..ModelConfig::default()
+            })
+            .await
+            .unwrap();
+
+        let response = handler
+            .run_inference(InferenceRequest {
+                task_id: 1,
+                model_id: "pinned".to_string(),
+                input_data: [0.5f32; 8].iter().flat_map(|x| x.to_ne_bytes()).collect(),
+                input_shape: vec![1, 8],
+                priority: InferencePriority::Normal,
+                named_inputs: Vec::new(),
+                timeout: None,
+            })
+            .await
+            .unwrap();
+
+        assert!(response.success);
+        assert!(!response.output_data.is_empty());
+    }
+
+    #[cfg(feature = "ai")]
+    #[tokio::test]
+    async fn test_load_models_without_arena() {
+        let model_path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx");
+
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+
+        let model_ids: Vec<String> = (0..4).map(|i| format!("small_{}", i)).collect();
+        for model_id in &model_ids {
+            handler
+                .load_model(ModelConfig {
+                    model_id: model_id.clone(),
+                    model_path: model_path.to_string(),
+                    input_shape: vec![1, 4],
+                    output_shape: vec![1, 4],
+                    cpu_arena: false,
+                    memory_pattern: false,
+                    ..ModelConfig::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let input = [0.5f32, 1.5, 2.5, 3.5];
+        for (task_id, model_id) in model_ids.iter().enumerate() {
+            let response = handler
+                .run_inference(InferenceRequest {
+                    task_id,
+                    model_id: model_id.clone(),
+                    input_data: input.iter().flat_map(|x| x.to_ne_bytes()).collect(),
+                    input_shape: vec![1, 4],
+                    priority: InferencePriority::Normal,
+                    named_inputs: Vec::new(),
+                    timeout: None,
+                })
+                .await
+                .unwrap();
+
+            assert!(response.success);
+            assert_eq!(helpers::bytes_to_f32(&response.output_data), input.to_vec());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provider_chain_falls_back() {
+        let model_path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx");
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+
+        // TensorRT is not part of the stock ONNX Runtime build
+        assert!(!ExecutionProvider::TensorRt.is_available());
+        let config = |model_id: &str, provider_chain| ModelConfig {
+            model_id: model_id.to_string(),
+            model_path: model_path.to_string(),
+            input_shape: vec![1, 4],
+            output_shape: vec![1, 4],
+            provider_chain,
+            ..ModelConfig::default()
+        };
+
+        handler
+            .load_model(config(
+                "fallback",
+                vec![ExecutionProvider::TensorRt, ExecutionProvider::Cpu],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            handler.execution_provider("fallback"),
+            Some(ExecutionProvider::Cpu)
+        );
+
+        let err = handler
+            .load_model(config("stranded", vec![ExecutionProvider::TensorRt]))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no execution provider"));
+        assert!(!handler.is_model_loaded("stranded"));
+        assert_eq!(handler.execution_provider("stranded"), None);
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_inferences_serializes_model() {
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+        handler
+            .load_model(ModelConfig {
+                model_id: "heavy".to_string(),
+                model_path: concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx")
+                    .to_string(),
+                input_shape: vec![1, 4],
+                output_shape: vec![1, 4],
+                max_concurrent_inferences: Some(1),
+                ..ModelConfig::default()
+            })
+            .await
+            .unwrap();
+
+        // Execution window of each inference, from its reported latency
+        let windows = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        handler.set_telemetry(std::sync::Arc::new({
+            let windows = windows.clone();
+            move |telemetry: &InferenceTelemetry| {
+                let end = std::time::Instant::now();
+                windows.lock().unwrap().push((end - telemetry.latency, end));
+            }
+        }));
+
+        let requests = (0..3).map(|task_id| {
+            handler.run_inference(InferenceRequest {
+                task_id,
+                model_id: "heavy".to_string(),
+                input_data: vec![0u8; 16].into(),
+                input_shape: vec![1, 4],
+                priority: InferencePriority::Normal,
+                named_inputs: Vec::new(),
+                timeout: None,
+            })
+        });
+        for response in futures::future::join_all(requests).await {
+            assert!(response.unwrap().success);
+        }
+
+        let mut windows = windows.lock().unwrap().clone();
+        assert_eq!(windows.len(), 3);
+        windows.sort();
+        for pair in windows.windows(2) {
+            assert!(pair[1].0 >= pair[0].1, "inferences overlapped");
+        }
+    }
+
+    #[test]
+    fn test_check_shape() {
+        assert!(check_shape("input", &[1, 8], &[Some(1), Some(8)]).is_ok());
+        // Dynamic dimensions match any size
+        assert!(check_shape("input", &[4, 8], &[None, Some(8)]).is_ok());
+
+        let err = check_shape("input", &[1, 3, 224, 224], &[Some(1), Some(8)]).unwrap_err();
+        assert_eq!(
+            err,
+            "declared input shape [1, 3, 224, 224] does not match the model's [1, 8]"
+        );
+        assert!(check_shape("output", &[1, 2], &[None, Some(1)]).is_err());
+    }
+
+    #[cfg(feature = "ai")]
+    #[tokio::test]
+    async fn test_load_model_rejects_mismatched_shapes() {
+        let model_path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx");
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+
+        let config = ModelConfig {
+            model_id: "misconfigured".to_string(),
+            model_path: model_path.to_string(),
+            input_shape: vec![1, 3, 224, 224],
+            output_shape: vec![1, 4],
+            ..ModelConfig::default()
+        };
+        let err = handler.load_model(config.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("declared input shape"));
+        assert!(!handler.is_model_loaded("misconfigured"));
+
+        // Lenient mode loads anyway
+        handler
+            .load_model(ModelConfig {
+                lenient_shapes: true,
+                ..config
+            })
+            .await
+            .unwrap();
+        assert!(handler.is_model_loaded("misconfigured"));
+    }
+
+    #[cfg(feature = "ai")]
+    #[tokio::test]
+    async fn test_multi_output_model() {
+        // Identity and Transpose of a [1, 4] input, giving [1, 4] and [4, 1]
+        let model_path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx");
+
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+        handler
+            .load_model(ModelConfig {
+                model_id: "multi".to_string(),
+                model_path: model_path.to_string(),
+                input_shape: vec![1, 4],
+                output_shape: vec![1, 4],
+                ..ModelConfig::default()
+            })
+            .await
+            .unwrap();
+
+        let input = [1.0f32, 2.0, 3.0, 4.0];
+        let response = handler
+            .run_inference(InferenceRequest {
+                task_id: 1,
+                model_id: "multi".to_string(),
+                input_data: input.iter().flat_map(|x| x.to_ne_bytes()).collect(),
+                input_shape: vec![1, 4],
+                priority: InferencePriority::Normal,
+                named_inputs: Vec::new(),
+                timeout: None,
+            })
+            .await
+            .unwrap();
+
+        assert!(response.success);
+        assert_eq!(response.outputs.len(), 2);
+        assert_eq!(response.outputs[0].name, "identity");
+        assert_eq!(response.outputs[0].shape, vec![1, 4]);
+        assert_eq!(response.outputs[1].name, "transposed");
+        assert_eq!(response.outputs[1].shape, vec![4, 1]);
+        for output in &response.outputs {
+            assert_eq!(helpers::bytes_to_f32(&output.data), input.to_vec());
+        }
+
+        // The first output is still exposed through the single-output fields
+        assert_eq!(response.output_shape, vec![1, 4]);
+        assert_eq!(response.output_data, response.outputs[0].data);
+    }
+
+    #[cfg(feature = "ai")]
+    #[tokio::test]
+    async fn test_inference_from_file_matches_in_memory() {
+        let model_path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx");
+
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+        handler
+            .load_model(ModelConfig {
+                model_id: "multi".to_string(),
+                model_path: model_path.to_string(),
+                input_shape: vec![1, 4],
+                output_shape: vec![1, 4],
+                ..ModelConfig::default()
+            })
+            .await
+            .unwrap();
+
+        let bytes: Vec<u8> = [0.25f32, 1.5, -3.0, 8.0]
+            .iter()
+            .flat_map(|x| x.to_ne_bytes())
+            .collect();
+        let path = std::env::temp_dir().join(format!("nebula_tensor_{}.bin", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+
+        let request = |input_data: InferenceInputSource| InferenceRequest {
+            task_id: 1,
+            model_id: "multi".to_string(),
+            input_data,
+            input_shape: vec![1, 4],
+            priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
+        };
+        let in_memory = handler.run_inference(request(bytes.into())).await.unwrap();
+        let from_file = handler
+            .run_inference(request(path.clone().into()))
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(from_file.success);
+        assert_eq!(from_file.outputs, in_memory.outputs);
+        assert_eq!(from_file.output_data, in_memory.output_data);
+    }
+
+    #[cfg(feature = "ai")]
+    #[tokio::test]
+    async fn test_profiling_writes_trace() {
+        let model_path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx");
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut handler = MLHandler::new().unwrap();
+        handler
+            .initialize_with_log_severity(LogSeverity::Error)
+            .await
+            .unwrap();
+        handler
+            .load_model(ModelConfig {
+                model_id: "profiled".to_string(),
+                model_path: model_path.to_string(),
+                input_shape: vec![1, 4],
+                output_shape: vec![1, 4],
+                profiling_prefix: Some(dir.path().join("profile")),
+                ..ModelConfig::default()
+            })
+            .await
+            .unwrap();
+
+        let response = handler
+            .run_inference(InferenceRequest {
+                task_id: 1,
+                model_id: "profiled".to_string(),
+                input_data: vec![0u8; 16].into(),
+                input_shape: vec![1, 4],
+                priority: InferencePriority::Normal,
+                named_inputs: Vec::new(),
+                timeout: None,
+            })
+            .await
+            .unwrap();
+        assert!(response.success);
+        handler.unload_model("profiled").await.unwrap();
+
+        let trace = handler
+            .profiling_path("profiled")
+            .expect("no profiling trace");
+        assert!(trace.starts_with(dir.path()));
+        assert!(std::fs::metadata(&trace).unwrap().len() > 0);
+        assert_eq!(handler.profiling_path("unknown"), None);
+    }
+
+    #[cfg(feature = "ai")]
+    #[tokio::test]
+    async fn test_named_inputs_reach_matching_model_inputs() {
+        let model_path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/two_input.onnx");
+        let to_bytes = |values: [f32; 4]| values.iter().flat_map(|x| x.to_ne_bytes()).collect();
+
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+        handler
+            .load_model(ModelConfig {
+                model_id: "two_input".to_string(),
+                model_path: model_path.to_string(),
+                input_shape: vec![1, 4],
+                output_shape: vec![1, 4],
+                ..ModelConfig::default()
+            })
+            .await
+            .unwrap();
+
+        // The model computes input_ids - attention_mask; inputs are given
+        // in the opposite order to the model's declaration
+        let request = InferenceRequest {
+            task_id: 1,
+            model_id: "two_input".to_string(),
+            input_data: Vec::<u8>::new().into(),
+            input_shape: vec![],
+            priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
+        }
+        .with_named_input("attention_mask", to_bytes([1.0, 1.0, 0.0, 0.0]), vec![1, 4])
+        .with_named_input("input_ids", to_bytes([5.0, 6.0, 7.0, 8.0]), vec![1, 4]);
+        let response = handler.run_inference(request.clone()).await.unwrap();
+
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.outputs[0].name, "difference");
+        assert_eq!(
+            helpers::bytes_to_f32(&response.output_data),
+            vec![4.0, 5.0, 7.0, 8.0]
+        );
+
+        let mut missing = request.clone();
+        missing.named_inputs.truncate(1);
+        let response = handler.run_inference(missing).await.unwrap();
+        assert!(!response.success);
+        assert!(response.error.unwrap().contains("input_ids"));
+
+        let unknown = request.with_named_input("token_type_ids", to_bytes([0.0; 4]), vec![1, 4]);
+        let response = handler.run_inference(unknown).await.unwrap();
+        assert!(!response.success);
+        assert!(response.error.unwrap().contains("token_type_ids"));
+    }
+
+    #[cfg(feature = "ai")]
+    #[tokio::test]
+    async fn test_telemetry_observes_each_inference() {
+        let model_path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx");
+
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+        handler
+            .load_model(ModelConfig {
+                model_id: "multi".to_string(),
+                model_path: model_path.to_string(),
+                input_shape: vec![1, 4],
+                output_shape: vec![1, 4],
+                ..ModelConfig::default()
+            })
+            .await
+            .unwrap();
+
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        handler.set_telemetry(std::sync::Arc::new(
+            move |telemetry: &InferenceTelemetry| sink.lock().unwrap().push(telemetry.clone()),
+        ));
+
+        for task_id in 1..=3 {
+            let response = handler
+                .run_inference(InferenceRequest {
+                    task_id,
+                    model_id: "multi".to_string(),
+                    input_data: [0.5f32; 4].iter().flat_map(|x| x.to_ne_bytes()).collect(),
+                    input_shape: vec![1, 4],
+                    priority: InferencePriority::Normal,
+                    named_inputs: Vec::new(),
+                    timeout: None,
+                })
+                .await
+                .unwrap();
+            assert!(response.success);
+        }
+        // A bad input is reported as a failed inference
+        handler
+            .run_inference(InferenceRequest {
+                task_id: 4,
+                model_id: "multi".to_string(),
+                input_data: vec![0; 3].into(),
+                input_shape: vec![1, 4],
+                priority: InferencePriority::Normal,
+                named_inputs: Vec::new(),
+                timeout: None,
+            })
+            .await
+            .unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 4);
+        for (telemetry, task_id) in seen.iter().zip(1..) {
+            assert_eq!(telemetry.task_id, task_id);
+            assert_eq!(telemetry.model_id, "multi");
+            assert_eq!(telemetry.device, "cpu");
+        }
+        for telemetry in &seen[..3] {
+            assert!(telemetry.success);
+            assert_eq!(telemetry.input_bytes, 16);
+            // Two [1, 4] f32 outputs
+            assert_eq!(telemetry.output_bytes, 32);
+            assert!(telemetry.latency > std::time::Duration::ZERO);
+        }
+        assert!(!seen[3].success);
+        assert_eq!(seen[3].input_bytes, 3);
+        assert_eq!(seen[3].output_bytes, 0);
+    }
+
+    #[test]
+    #[cfg(not(feature = "ai"))]
+    fn test_blocking_inference() {
+        let handler = MLHandler::new().unwrap();
+
+        let request = InferenceRequest {
+            task_id: 7,
+            model_id: "test_model".to_string(),
+            input_data: vec![0; 100].into(),
+            input_shape: vec![1, 25],
+            priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
+        };
+
+        let response = handler.run_inference_blocking(request).unwrap();
+        assert!(response.success);
+        assert_eq!(response.task_id, 7);
+        assert_eq!(response.output_shape, vec![1, 10]);
+    }
+
+    #[tokio::test]
+    async fn test_blocking_inference_rejected_in_async_context() {
+        let handler = MLHandler::new().unwrap();
+
+        let request = InferenceRequest {
+            task_id: 1,
+            model_id: "test_model".to_string(),
+            input_data: Vec::<u8>::new().into(),
+            input_shape: vec![],
+            priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
+        };
+
+        assert!(handler.run_inference_blocking(request).is_err());
+    }
+
+    #[tokio::test]
+    #[cfg(not(feature = "ai"))]
+    async fn test_slow_inference_times_out() {
+        let handler = MLHandler::new().unwrap();
+        let request = InferenceRequest {
+            task_id: 1,
+            model_id: "test_model".to_string(),
+            input_data: vec![0; 4].into(),
+            input_shape: vec![1, 1],
+            priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
+        };
+
+        // The mock inference takes 50ms
+        let short = request
+            .clone()
+            .with_timeout(std::time::Duration::from_millis(10));
+        let err = handler.run_inference(short).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+
+        handler.set_default_inference_timeout(std::time::Duration::from_millis(10));
+        assert!(handler.run_inference(request.clone()).await.is_err());
+
+        let response = handler
+            .run_inference(request.with_timeout(std::time::Duration::from_secs(5)))
+            .await
+            .unwrap();
+        assert!(response.success);
+    }
+
+    #[cfg(feature = "ai")]
+    #[tokio::test]
+    async fn test_onnx_inference_times_out() {
+        let mut handler = MLHandler::new().unwrap();
+        handler.initialize().await.unwrap();
+        handler
+            .load_model(ModelConfig {
+                model_id: "identity".to_string(),
+                model_path: concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx")
+                    .to_string(),
+                input_shape: vec![1, 4],
+                output_shape: vec![1, 4],
+                ..ModelConfig::default()
+            })
+            .await
+            .unwrap();
+        let request = InferenceRequest {
+            task_id: 1,
+            model_id: "identity".to_string(),
+            input_data: [1.0f32; 4].iter().flat_map(|x| x.to_ne_bytes()).collect(),
+            input_shape: vec![1, 4],
+            priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
+        };
+
+        // Run synchronously, the session would finish before the deadline
+        // is ever checked; on the blocking pool it cannot
+        let expired = request.clone().with_timeout(std::time::Duration::ZERO);
+        let err = handler.run_inference(expired).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+
+        let response = handler
+            .run_inference(request.with_timeout(std::time::Duration::from_secs(5)))
+            .await
+            .unwrap();
+        assert!(response.success);
+    }
+
+    #[tokio::test]
+    async fn test_unload_waits_for_inflight_inference() {
+        let handler = std::sync::Arc::new(MLHandler::new().unwrap());
+        let finished = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        // Stands in for a slow inference holding the model
+        let inference = {
+            let handler = handler.clone();
+            let finished = finished.clone();
+            tokio::spawn(async move {
+                let _inflight = handler.inflight.begin("test_model").unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                finished.store(true, std::sync::atomic::Ordering::SeqCst);
+            })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        let unload = {
+            let handler = handler.clone();
+            tokio::spawn(async move { handler.unload_model("test_model").await })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        // Draining models reject new work
+        assert!(handler.inflight.begin("test_model").is_err());
+
+        unload.await.unwrap().unwrap();
+        assert!(finished.load(std::sync::atomic::Ordering::SeqCst));
+        inference.await.unwrap();
+
+        // Once unloaded, the model id is usable again
+        assert!(handler.inflight.begin("test_model").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_unload_times_out_and_keeps_model() {
+        let handler = MLHandler::new().unwrap();
+        let inflight = handler.inflight.begin("test_model").unwrap();
+
+        let result = handler
+            .unload_model_with_timeout("test_model", std::time::Duration::from_millis(20))
+            .await;
+        assert!(result.is_err());
+
+        // The model accepts inferences again after a failed unload
+        drop(inflight);
+        assert!(handler.inflight.begin("test_model").is_ok());
+    }
+
     #[test]
     fn test_image_to_tensor() {
         let image_data = vec![128; 32 * 32 * 3]; // 32x32 RGB image
@@ -354,6 +2770,62 @@ mod tests {
         assert!((tensor[0] - 128.0 / 255.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_input_pool_reuses_buffers() {
+        let pool = InputBufferPool::new();
+        let input: Vec<u8> = [1.0f32; 64].iter().flat_map(|x| x.to_ne_bytes()).collect();
+
+        // Decoding with a fresh Vec per call would allocate 100 times
+        for _ in 0..100 {
+            let mut buffer = pool.acquire("model", 64);
+            helpers::decode_f32_into(&input, 64, &mut buffer);
+            assert_eq!(buffer.len(), 64);
+            assert_eq!(buffer[0], 1.0);
+            pool.release("model", buffer);
+        }
+
+        assert_eq!(pool.allocations(), 1);
+
+        // Buffers are tracked per model
+        let other = pool.acquire("other_model", 64);
+        assert_eq!(pool.allocations(), 2);
+        pool.release("other_model", other);
+    }
+
+    #[test]
+    fn test_softmax_sums_to_one() {
+        let logits = [1.0, 2.0, 3.0, 0.5];
+        let probs = helpers::softmax(&logits);
+
+        assert_eq!(probs.len(), 4);
+        assert!((probs.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        assert!(probs[2] > probs[1] && probs[1] > probs[0] && probs[0] > probs[3]);
+    }
+
+    #[test]
+    fn test_post_processor_top_k() {
+        let logits = [0.1, 4.0, -1.0, 2.5, 3.0];
+
+        match PostProcessor::TopK(3).apply(&logits) {
+            ProcessedOutput::Classes(classes) => {
+                let indices: Vec<usize> = classes.iter().map(|(i, _)| *i).collect();
+                assert_eq!(indices, vec![1, 4, 3]);
+                assert!(classes[0].1 > classes[1].1 && classes[1].1 > classes[2].1);
+            }
+            other => panic!("unexpected output: {:?}", other),
+        }
+
+        match PostProcessor::ArgMax.apply(&logits) {
+            ProcessedOutput::Classes(classes) => assert_eq!(classes[0].0, 1),
+            other => panic!("unexpected output: {:?}", other),
+        }
+
+        assert_eq!(
+            PostProcessor::Raw.apply(&logits),
+            ProcessedOutput::Raw(logits.to_vec())
+        );
+    }
+
     #[test]
     fn test_text_to_tokens() {
         let tokens = helpers::text_to_tokens("Hello", 10);
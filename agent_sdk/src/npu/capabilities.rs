@@ -1,6 +1,8 @@
 //! NPU Capabilities definitions
 
-use crate::npu::{ComputeUnit, DataType, MemoryType, ModelFormat, PerformanceSpecs};
+use crate::npu::{
+    ComputeUnit, DataType, MemoryType, ModelFormat, PerformanceSpecs, PrecisionPreference,
+};
 use serde::{Deserialize, Serialize};
 
 /// NPU device capabilities
@@ -44,6 +46,9 @@ pub struct ComputeCapability {
     pub max_tensor_dims: u32,
     /// Supports concurrent inference
     pub concurrent_inference: bool,
+    /// Most inferences run at once when `concurrent_inference` is set
+    #[serde(default = "default_max_concurrent_inferences")]
+    pub max_concurrent_inferences: u32,
     /// Supports mixed precision
     pub mixed_precision: bool,
 }
@@ -61,11 +66,16 @@ impl Default for ComputeCapability {
             max_batch_size: 32,
             max_tensor_dims: 8,
             concurrent_inference: true,
+            max_concurrent_inferences: default_max_concurrent_inferences(),
             mixed_precision: true,
         }
     }
 }
 
+fn default_max_concurrent_inferences() -> u32 {
+    4
+}
+
 /// Memory capabilities of the NPU
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryCapability {
@@ -133,6 +143,19 @@ impl NpuCapabilities {
         self.compute.supported_data_types.contains(data_type)
     }
 
+    /// Supported precision to run Float32 data at, favouring the cheapest
+    /// for `Speed` and the most exact for `Accuracy`. `None` if the device
+    /// supports none of Float32, Float16 or Int8.
+    pub fn preferred_precision(&self, preference: PrecisionPreference) -> Option<DataType> {
+        let candidates = match preference {
+            PrecisionPreference::Speed => [DataType::Int8, DataType::Float16, DataType::Float32],
+            PrecisionPreference::Accuracy => [DataType::Float32, DataType::Float16, DataType::Int8],
+        };
+        candidates
+            .into_iter()
+            .find(|data_type| self.supports_data_type(data_type))
+    }
+
     /// Check if a model format is supported
     pub fn supports_model_format(&self, format: &ModelFormat) -> bool {
         self.model_support.supported_formats.contains(format)
@@ -153,6 +176,16 @@ impl NpuCapabilities {
         self.compute.concurrent_inference
     }
 
+    /// Most inferences the device runs at once; 1 without concurrent
+    /// inference support
+    pub fn max_concurrent_inferences(&self) -> u32 {
+        if self.compute.concurrent_inference {
+            self.compute.max_concurrent_inferences.max(1)
+        } else {
+            1
+        }
+    }
+
     /// Get maximum supported batch size
     pub fn max_batch_size(&self) -> u32 {
         self.compute.max_batch_size
@@ -167,4 +200,124 @@ impl NpuCapabilities {
     pub fn supports_memory_type(&self, memory_type: &MemoryType) -> bool {
         self.memory.supported_memory_types.contains(memory_type)
     }
+
+    /// Summarize these capabilities as a flat, serializable report
+    pub fn report(&self) -> CapabilityReport {
+        CapabilityReport {
+            compute_units: self
+                .compute
+                .compute_units
+                .iter()
+                .map(|unit| ComputeUnitCount {
+                    unit: unit.clone(),
+                    cores: self.get_core_count(unit),
+                })
+                .collect(),
+            data_types: self.compute.supported_data_types.clone(),
+            max_batch_size: self.compute.max_batch_size,
+            concurrent_inference: self.compute.concurrent_inference,
+            max_concurrent_inferences: self.max_concurrent_inferences(),
+            mixed_precision: self.compute.mixed_precision,
+            total_memory_bytes: self.memory.total_memory_bytes,
+            max_allocation_bytes: self.memory.max_allocation_bytes,
+            memory_types: self.memory.supported_memory_types.clone(),
+            unified_memory: self.memory.unified_memory,
+            model_formats: self.model_support.supported_formats.clone(),
+            quantization: self.model_support.quantization.clone(),
+            dynamic_shapes: self.model_support.dynamic_shapes,
+            peak_tops: self.performance.peak_tops,
+            sustained_tops: self.performance.sustained_tops,
+            memory_bandwidth_gbps: self.performance.memory_bandwidth_gbps,
+            power_consumption_watts: self.performance.power_consumption_watts,
+            frequency_mhz: self.performance.frequency_mhz,
+        }
+    }
+}
+
+/// Flat summary of a device's capabilities for tooling and JSON APIs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapabilityReport {
+    pub compute_units: Vec<ComputeUnitCount>,
+    pub data_types: Vec<DataType>,
+    pub max_batch_size: u32,
+    pub concurrent_inference: bool,
+    pub max_concurrent_inferences: u32,
+    pub mixed_precision: bool,
+    pub total_memory_bytes: u64,
+    pub max_allocation_bytes: u64,
+    pub memory_types: Vec<MemoryType>,
+    pub unified_memory: bool,
+    pub model_formats: Vec<ModelFormat>,
+    pub quantization: Vec<DataType>,
+    pub dynamic_shapes: bool,
+    pub peak_tops: f64,
+    pub sustained_tops: f64,
+    pub memory_bandwidth_gbps: f64,
+    pub power_consumption_watts: f64,
+    pub frequency_mhz: u32,
+}
+
+/// Number of cores of one compute unit type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComputeUnitCount {
+    pub unit: ComputeUnit,
+    pub cores: u32,
+}
+
+impl CapabilityReport {
+    /// Number of cores reported for a compute unit (0 if absent)
+    pub fn core_count(&self, unit: &ComputeUnit) -> u32 {
+        self.compute_units
+            .iter()
+            .find(|count| count.unit == *unit)
+            .map(|count| count.cores)
+            .unwrap_or(0)
+    }
+}
+
+impl std::fmt::Display for CapabilityReport {
+    /// One `key: value` line per field, for `--list-capabilities` style output
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for count in &self.compute_units {
+            writeln!(f, "compute_unit.{:?}: {} cores", count.unit, count.cores)?;
+        }
+        writeln!(f, "data_types: {:?}", self.data_types)?;
+        writeln!(f, "max_batch_size: {}", self.max_batch_size)?;
+        writeln!(f, "concurrent_inference: {}", self.concurrent_inference)?;
+        writeln!(
+            f,
+            "max_concurrent_inferences: {}",
+            self.max_concurrent_inferences
+        )?;
+        writeln!(f, "mixed_precision: {}", self.mixed_precision)?;
+        writeln!(f, "total_memory_bytes: {}", self.total_memory_bytes)?;
+        writeln!(f, "max_allocation_bytes: {}", self.max_allocation_bytes)?;
+        writeln!(f, "memory_types: {:?}", self.memory_types)?;
+        writeln!(f, "unified_memory: {}", self.unified_memory)?;
+        writeln!(f, "model_formats: {:?}", self.model_formats)?;
+        writeln!(f, "quantization: {:?}", self.quantization)?;
+        writeln!(f, "dynamic_shapes: {}", self.dynamic_shapes)?;
+        writeln!(f, "peak_tops: {}", self.peak_tops)?;
+        writeln!(f, "sustained_tops: {}", self.sustained_tops)?;
+        writeln!(f, "memory_bandwidth_gbps: {}", self.memory_bandwidth_gbps)?;
+        writeln!(f, "power_consumption_watts: {}", self.power_consumption_watts)?;
+        write!(f, "frequency_mhz: {}", self.frequency_mhz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_round_trips_through_serde() {
+        let report = NpuCapabilities::default().report();
+        assert_eq!(report.core_count(&ComputeUnit::TensorCore), 8);
+        assert_eq!(report.core_count(&ComputeUnit::ScalarCore), 0);
+
+        let json = serde_json::to_string(&report).unwrap();
+        let decoded: CapabilityReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, report);
+        assert!(report.to_string().contains("compute_unit.TensorCore: 8 cores"));
+    }
 }
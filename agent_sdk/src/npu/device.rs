@@ -2,7 +2,10 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use crate::npu::hal::{MemoryHandle, ModelHandle};
 use crate::npu::{
@@ -41,8 +44,20 @@ pub trait NpuDevice: Send + Sync {
     async fn is_available(&self) -> bool;
 
     /// Get current device health status
+    ///
+    /// Implementations may serve a recent cached reading instead of querying the hardware.
     async fn get_health(&self) -> Result<DeviceHealth>;
 
+    /// Get the last health reading without touching the hardware
+    fn get_cached_health(&self) -> Option<DeviceHealth> {
+        None
+    }
+
+    /// Query the hardware for a fresh health reading, bypassing any cache
+    async fn refresh_health(&self) -> Result<DeviceHealth> {
+        self.get_health().await
+    }
+
     /// Get current power state
     async fn get_power_state(&self) -> Result<PowerState>;
 
@@ -128,6 +143,84 @@ impl NpuDeviceInfo {
     }
 }
 
+/// Default age after which a cached health reading is refreshed
+pub const DEFAULT_HEALTH_CACHE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Caches device health readings and serializes hardware queries
+///
+/// Concurrent callers share a single in-flight query, so the driver is
+/// queried at most once per interval no matter how many callers there are.
+pub struct HealthCache {
+    interval: Duration,
+    reading: Mutex<Option<(Instant, DeviceHealth)>>,
+    query_lock: tokio::sync::Mutex<()>,
+}
+
+impl HealthCache {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            reading: Mutex::new(None),
+            query_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Last reading, regardless of age
+    pub fn cached(&self) -> Option<DeviceHealth> {
+        self.reading
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(_, health)| health.clone())
+    }
+
+    /// Return the cached reading if it is fresh, otherwise run `query`
+    pub async fn get<F, Fut>(&self, query: F) -> Result<DeviceHealth>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<DeviceHealth>>,
+    {
+        if let Some(health) = self.fresh() {
+            return Ok(health);
+        }
+
+        let _guard = self.query_lock.lock().await;
+        // Another caller may have refreshed while we waited
+        if let Some(health) = self.fresh() {
+            return Ok(health);
+        }
+        self.store(query().await?)
+    }
+
+    /// Always run `query` and cache its result
+    pub async fn refresh<F, Fut>(&self, query: F) -> Result<DeviceHealth>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<DeviceHealth>>,
+    {
+        let _guard = self.query_lock.lock().await;
+        self.store(query().await?)
+    }
+
+    fn fresh(&self) -> Option<DeviceHealth> {
+        match self.reading.lock().unwrap().as_ref() {
+            Some((at, health)) if at.elapsed() < self.interval => Some(health.clone()),
+            _ => None,
+        }
+    }
+
+    fn store(&self, health: DeviceHealth) -> Result<DeviceHealth> {
+        *self.reading.lock().unwrap() = Some((Instant::now(), health.clone()));
+        Ok(health)
+    }
+}
+
+impl Default for HealthCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_HEALTH_CACHE_INTERVAL)
+    }
+}
+
 /// Device discovery and enumeration
 pub struct DeviceDiscovery;
 
@@ -191,20 +284,64 @@ impl DeviceDiscovery {
     }
 }
 
+/// Retry schedule for device initialization
+#[derive(Debug, Clone)]
+pub struct InitRetryPolicy {
+    /// Total attempts per device, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after each failure
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for InitRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Outcome of [`DeviceManager::init_all_devices`]
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInitSummary {
+    pub initialized: Vec<NpuDeviceId>,
+    /// Devices that failed every attempt, with the last error
+    pub failed: Vec<(NpuDeviceId, String)>,
+}
+
+impl DeviceInitSummary {
+    pub fn all_initialized(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
 /// Device manager for tracking and managing multiple NPU devices
 pub struct DeviceManager {
     devices: tokio::sync::RwLock<
         std::collections::HashMap<NpuDeviceId, Arc<dyn NpuDevice + Send + Sync>>,
     >,
+    unavailable: tokio::sync::RwLock<HashSet<NpuDeviceId>>,
+    retry_policy: InitRetryPolicy,
 }
 
 impl DeviceManager {
     pub fn new() -> Self {
         Self {
             devices: tokio::sync::RwLock::new(std::collections::HashMap::new()),
+            unavailable: tokio::sync::RwLock::new(HashSet::new()),
+            retry_policy: InitRetryPolicy::default(),
         }
     }
 
+    pub fn with_retry_policy(mut self, policy: InitRetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
     /// Add a device to the manager
     pub async fn add_device(&self, device: Arc<dyn NpuDevice + Send + Sync>) {
         let id = device.id();
@@ -216,6 +353,7 @@ impl DeviceManager {
         &self,
         device_id: &NpuDeviceId,
     ) -> Option<Arc<dyn NpuDevice + Send + Sync>> {
+        self.unavailable.write().await.remove(device_id);
         self.devices.write().await.remove(device_id)
     }
 
@@ -233,11 +371,16 @@ impl DeviceManager {
     }
 
     /// Get available devices (ready for work)
+    ///
+    /// Devices that failed initialization are never returned.
     pub async fn get_available_devices(&self) -> Vec<Arc<dyn NpuDevice + Send + Sync>> {
         let devices = self.get_all_devices().await;
         let mut available = Vec::new();
 
         for device in devices {
+            if self.is_marked_unavailable(&device.id()).await {
+                continue;
+            }
             if device.is_available().await {
                 available.push(device);
             }
@@ -246,6 +389,11 @@ impl DeviceManager {
         available
     }
 
+    /// Whether a device was marked unavailable after failing initialization
+    pub async fn is_marked_unavailable(&self, device_id: &NpuDeviceId) -> bool {
+        self.unavailable.read().await.contains(device_id)
+    }
+
     /// Get devices by type
     pub async fn get_devices_by_type(
         &self,
@@ -258,15 +406,59 @@ impl DeviceManager {
             .collect()
     }
 
-    /// Initialize all devices
-    pub async fn init_all_devices(&self) -> Result<()> {
+    /// Initialize all devices, retrying failures according to the retry policy
+    ///
+    /// Devices that still fail after the last attempt are marked unavailable.
+    pub async fn init_all_devices(&self) -> Result<DeviceInitSummary> {
         let devices = self.get_all_devices().await;
+        let mut summary = DeviceInitSummary::default();
+
         for device in devices {
-            if let Err(e) = device.init().await {
-                log::error!("Failed to initialize device {}: {}", device.id(), e);
+            let id = device.id();
+            match self.init_with_retry(device.as_ref()).await {
+                Ok(()) => {
+                    self.unavailable.write().await.remove(&id);
+                    summary.initialized.push(id);
+                }
+                Err(e) => {
+                    log::error!(
+                        "Failed to initialize device {}, marking unavailable: {}",
+                        id,
+                        e
+                    );
+                    self.unavailable.write().await.insert(id.clone());
+                    summary.failed.push((id, e.to_string()));
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    async fn init_with_retry(&self, device: &(dyn NpuDevice + Send + Sync)) -> Result<()> {
+        let policy = &self.retry_policy;
+        let mut backoff = policy.initial_backoff;
+        let mut attempt = 1;
+
+        loop {
+            match device.init().await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= policy.max_attempts => return Err(e),
+                Err(e) => {
+                    log::warn!(
+                        "Device {} init attempt {}/{} failed, retrying in {:?}: {}",
+                        device.id(),
+                        attempt,
+                        policy.max_attempts,
+                        backoff,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(policy.max_backoff);
+                    attempt += 1;
+                }
             }
         }
-        Ok(())
     }
 
     /// Shutdown all devices
@@ -286,3 +478,197 @@ impl Default for DeviceManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::npu::mock::MockNpuDevice;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::SystemTime;
+
+    /// Mock device whose first `failures` init calls fail
+    struct FlakyDevice {
+        inner: MockNpuDevice,
+        failures: AtomicU32,
+        init_calls: AtomicU32,
+    }
+
+    impl FlakyDevice {
+        async fn new(failures: u32) -> Self {
+            Self {
+                inner: MockNpuDevice::new().await.unwrap(),
+                failures: AtomicU32::new(failures),
+                init_calls: AtomicU32::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl NpuDevice for FlakyDevice {
+        fn id(&self) -> NpuDeviceId {
+            self.inner.id()
+        }
+
+        fn info(&self) -> NpuDeviceInfo {
+            self.inner.info()
+        }
+
+        fn capabilities(&self) -> Arc<NpuCapabilities> {
+            self.inner.capabilities()
+        }
+
+        async fn init(&self) -> Result<()> {
+            self.init_calls.fetch_add(1, Ordering::SeqCst);
+            let remaining = self.failures.load(Ordering::SeqCst);
+            if remaining > 0 {
+                self.failures.store(remaining - 1, Ordering::SeqCst);
+                anyhow::bail!("device busy");
+            }
+            self.inner.init().await
+        }
+
+        async fn shutdown(&self) -> Result<()> {
+            self.inner.shutdown().await
+        }
+
+        async fn execute_inference(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+            self.inner.execute_inference(request).await
+        }
+
+        async fn load_model(&self, model_path: &str) -> Result<ModelHandle> {
+            self.inner.load_model(model_path).await
+        }
+
+        async fn unload_model(&self, handle: ModelHandle) -> Result<()> {
+            self.inner.unload_model(handle).await
+        }
+
+        async fn is_available(&self) -> bool {
+            self.inner.is_available().await
+        }
+
+        async fn get_health(&self) -> Result<DeviceHealth> {
+            self.inner.get_health().await
+        }
+
+        async fn get_power_state(&self) -> Result<PowerState> {
+            self.inner.get_power_state().await
+        }
+
+        async fn set_power_state(&self, state: PowerState) -> Result<()> {
+            self.inner.set_power_state(state).await
+        }
+
+        async fn get_memory_info(&self) -> Result<Vec<MemoryRegion>> {
+            self.inner.get_memory_info().await
+        }
+
+        async fn allocate_memory(&self, size_bytes: u64) -> Result<MemoryHandle> {
+            self.inner.allocate_memory(size_bytes).await
+        }
+
+        async fn free_memory(&self, handle: MemoryHandle) -> Result<()> {
+            self.inner.free_memory(handle).await
+        }
+
+        async fn get_utilization(&self) -> f64 {
+            self.inner.get_utilization().await
+        }
+
+        async fn get_temperature(&self) -> f32 {
+            self.inner.get_temperature().await
+        }
+
+        async fn reset(&self) -> Result<()> {
+            self.inner.reset().await
+        }
+    }
+
+    fn fast_retries(max_attempts: u32) -> InitRetryPolicy {
+        InitRetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_init_retries_transient_failures() {
+        let manager = DeviceManager::new().with_retry_policy(fast_retries(3));
+        let device = Arc::new(FlakyDevice::new(2).await);
+        manager.add_device(device.clone()).await;
+
+        let summary = manager.init_all_devices().await.unwrap();
+
+        assert!(summary.all_initialized());
+        assert_eq!(summary.initialized, vec![device.id()]);
+        assert_eq!(device.init_calls.load(Ordering::SeqCst), 3);
+        assert_eq!(manager.get_available_devices().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_persistent_init_failure_marks_device_unavailable() {
+        let manager = DeviceManager::new().with_retry_policy(fast_retries(2));
+        let device = Arc::new(FlakyDevice::new(5).await);
+        manager.add_device(device.clone()).await;
+
+        let summary = manager.init_all_devices().await.unwrap();
+
+        assert!(summary.initialized.is_empty());
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(device.init_calls.load(Ordering::SeqCst), 2);
+        assert!(manager.is_marked_unavailable(&device.id()).await);
+        assert!(manager.get_available_devices().await.is_empty());
+        assert_eq!(manager.get_all_devices().await.len(), 1);
+    }
+
+    fn healthy() -> DeviceHealth {
+        DeviceHealth {
+            is_healthy: true,
+            temperature_celsius: 40.0,
+            power_consumption_watts: 5.0,
+            memory_errors: 0,
+            compute_errors: 0,
+            last_check: SystemTime::now(),
+            status_message: "ok".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_get_health_queries_driver_once_per_interval() {
+        let cache = HealthCache::new(Duration::from_millis(200));
+        let queries = &AtomicU32::new(0);
+        let query = || async move {
+            queries.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(healthy())
+        };
+
+        assert!(cache.cached().is_none());
+        let results = futures::future::join_all((0..50).map(|_| cache.get(query))).await;
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+        assert!(cache.cached().is_some());
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        futures::future::join_all((0..50).map(|_| cache.get(query))).await;
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+
+        cache.refresh(query).await.unwrap();
+        assert_eq!(queries.load(Ordering::SeqCst), 3);
+    }
+
+    async fn failing_query() -> Result<DeviceHealth> {
+        anyhow::bail!("register read failed")
+    }
+
+    #[tokio::test]
+    async fn test_failed_health_query_is_not_cached() {
+        let cache = HealthCache::default();
+
+        let result = cache.get(failing_query).await;
+
+        assert!(result.is_err());
+        assert!(cache.cached().is_none());
+    }
+}
@@ -9,11 +9,13 @@ use std::sync::Arc;
 use crate::npu::capabilities::ModelSupport;
 use crate::npu::hal::{MemoryHandle, ModelHandle};
 use crate::npu::{
-    ComputeCapability, ComputeUnit, DataType, DeviceHealth, InferenceRequest, InferenceResponse,
-    MemoryCapability, MemoryRegion, MemoryType, ModelFormat, NpuCapabilities, NpuDevice,
-    NpuDeviceId, NpuDeviceInfo, NpuDeviceType, NpuVendor, PerformanceSpecs, PowerState,
+    ComputeCapability, ComputeUnit, DataType, DeviceHealth, HealthCache, InferenceRequest,
+    InferenceResponse, MemoryCapability, MemoryRegion, MemoryType, ModelFormat, NpuCapabilities,
+    NpuDevice, NpuDeviceId, NpuDeviceInfo, NpuDeviceType, NpuVendor, PerformanceSpecs, PowerState,
 };
 
+use crate::npu::types::metadata_keys;
+
 use super::apple_neural_engine::{AppleNeuralEngineDriver, CoreMLModelHandle};
 
 /// Apple Neural Engine device implementation
@@ -23,6 +25,7 @@ pub struct AppleNeuralDevice {
     driver: std::sync::Mutex<AppleNeuralEngineDriver>,
     // Store loaded models with their handles - in practice this would be a proper cache
     loaded_models: std::sync::Mutex<HashMap<u64, CoreMLModelHandle>>,
+    health: HealthCache,
 }
 
 impl AppleNeuralDevice {
@@ -57,6 +60,7 @@ impl AppleNeuralDevice {
                 max_batch_size: 1, // Core ML typically processes single batches
                 max_tensor_dims: 4,
                 concurrent_inference: true,
+                max_concurrent_inferences: 4,
                 mixed_precision: true,
             },
             memory: MemoryCapability {
@@ -91,8 +95,21 @@ impl AppleNeuralDevice {
             capabilities,
             driver: std::sync::Mutex::new(driver),
             loaded_models: std::sync::Mutex::new(HashMap::new()),
+            health: HealthCache::default(),
         })
     }
+
+    fn read_health() -> DeviceHealth {
+        DeviceHealth {
+            is_healthy: true,
+            temperature_celsius: 45.0,    // Typical NPU temperature
+            power_consumption_watts: 6.0, // Current consumption
+            memory_errors: 0,
+            compute_errors: 0,
+            last_check: std::time::SystemTime::now(),
+            status_message: "Apple Neural Engine operating normally".to_string(),
+        }
+    }
 }
 
 #[async_trait]
@@ -127,33 +144,40 @@ impl NpuDevice for AppleNeuralDevice {
 
         // Load or get the model - load it dynamically if not already loaded
         let mut driver = self.driver.lock().unwrap();
+        let cache_hit = driver.is_model_loaded(&request.model_path);
         let model_handle = driver.load_model(&request.model_path)?;
+        let execution_provider = if model_handle.is_neural_engine_optimized {
+            "CoreML"
+        } else {
+            "CPU"
+        };
+        let quantized = request.inputs[0].data_type().is_quantized();
 
-        // Convert request to format suitable for Apple Neural Engine
-        let input_data: Vec<f32> = request.inputs[0]
-            .data
-            .chunks_exact(4)
-            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-            .collect();
+        // The driver runs in f32, so widen half-precision inputs and scale
+        // int8 inputs back by the scale they were quantized with
+        let scale = request
+            .quant_scales()
+            .and_then(|scales| scales.first().copied().flatten());
+        let input_data: Vec<f32> = request.inputs[0].dequantize(scale)?;
 
-        let input_shape: Vec<usize> = request.inputs[0].shape.iter().map(|&x| x as usize).collect();
+        let input_shape: Vec<usize> = request.inputs[0].shape().iter().map(|&x| x as usize).collect();
 
         // Use the driver for actual inference
         let output_data = driver.execute_inference(&model_handle, &input_data, &input_shape)?;
 
-        // Convert back to bytes
-        let output_bytes: Vec<u8> = output_data.iter().flat_map(|&f| f.to_le_bytes()).collect();
-
         let response = InferenceResponse {
-            outputs: vec![crate::npu::InferenceOutput {
-                data: output_bytes,
-                shape: request.inputs[0].shape.clone(),
-                data_type: request.inputs[0].data_type.clone(),
-            }],
+            outputs: vec![crate::npu::Tensor::from_f32(
+                &output_data,
+                request.inputs[0].shape().to_vec(),
+            )?],
             execution_time: std::time::Duration::from_micros(500), // Fast Neural Engine
             device_id: self.id(),
             metadata: std::collections::HashMap::new(),
-        };
+        }
+        .with_metadata(metadata_keys::DEVICE_TYPE, &self.info.device_type)
+        .with_metadata(metadata_keys::EXECUTION_PROVIDER, execution_provider)
+        .with_metadata(metadata_keys::QUANTIZED, quantized)
+        .with_metadata(metadata_keys::CACHE_HIT, cache_hit);
 
         Ok(response)
     }
@@ -185,15 +209,17 @@ impl NpuDevice for AppleNeuralDevice {
     }
 
     async fn get_health(&self) -> Result<DeviceHealth> {
-        Ok(DeviceHealth {
-            is_healthy: true,
-            temperature_celsius: 45.0,    // Typical NPU temperature
-            power_consumption_watts: 6.0, // Current consumption
-            memory_errors: 0,
-            compute_errors: 0,
-            last_check: std::time::SystemTime::now(),
-            status_message: "Apple Neural Engine operating normally".to_string(),
-        })
+        self.health.get(|| async { Ok(Self::read_health()) }).await
+    }
+
+    fn get_cached_health(&self) -> Option<DeviceHealth> {
+        self.health.cached()
+    }
+
+    async fn refresh_health(&self) -> Result<DeviceHealth> {
+        self.health
+            .refresh(|| async { Ok(Self::read_health()) })
+            .await
     }
 
     async fn get_power_state(&self) -> Result<PowerState> {
@@ -241,3 +267,72 @@ impl NpuDevice for AppleNeuralDevice {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::npu::{TaskPriority, Tensor};
+
+    #[tokio::test]
+    async fn test_apple_response_metadata() {
+        let device = AppleNeuralDevice::new().await.unwrap();
+        let request = InferenceRequest {
+            model_path: "model.mlmodel".to_string(),
+            inputs: vec![Tensor::from_f32(&[1.0], vec![1, 1]).unwrap()],
+            timeout: std::time::Duration::from_secs(1),
+            priority: TaskPriority::Normal,
+            agent_id: None,
+            metadata: HashMap::new(),
+        };
+
+        let first = device.execute_inference(request.clone()).await.unwrap();
+        assert_eq!(first.device_type(), Some(NpuDeviceType::AppleNeuralEngine));
+        assert!(first.execution_provider().is_some());
+        assert_eq!(first.quantized(), Some(false));
+        assert_eq!(first.cache_hit(), Some(false));
+
+        let second = device.execute_inference(request).await.unwrap();
+        assert_eq!(second.cache_hit(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_apple_runs_reduced_precision_inputs() {
+        let device = AppleNeuralDevice::new().await.unwrap();
+        let input = Tensor::from_f32(&[0.5, -1.0], vec![1, 2]).unwrap();
+        for data_type in [DataType::Float16, DataType::Int8] {
+            let (converted, scale) = input.to_precision(data_type.clone()).unwrap();
+            let mut metadata = HashMap::new();
+            if let Some(scale) = scale {
+                metadata.insert(metadata_keys::QUANT_SCALE.to_string(), scale.to_string());
+            }
+            let request = InferenceRequest {
+                model_path: "model.mlmodel".to_string(),
+                inputs: vec![converted],
+                timeout: std::time::Duration::from_secs(1),
+                priority: TaskPriority::Normal,
+                agent_id: None,
+                metadata,
+            };
+
+            let response = device.execute_inference(request).await.unwrap();
+            assert_eq!(response.quantized(), Some(data_type.is_quantized()));
+            // The mock driver doubles its f32 input
+            let output: Vec<f32> = response.outputs[0].view().unwrap();
+            for (value, expected) in output.iter().zip([1.0, -2.0]) {
+                assert!((value - expected).abs() < 0.02, "{:?}: {}", data_type, value);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apple_capability_report() {
+        let device = AppleNeuralDevice::new().await.unwrap();
+        let report = device.capabilities().report();
+        assert_eq!(report.core_count(&ComputeUnit::TensorCore), 16);
+
+        let json = serde_json::to_string(&report).unwrap();
+        let decoded: crate::npu::CapabilityReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, report);
+        assert_eq!(decoded.core_count(&ComputeUnit::TensorCore), 16);
+    }
+}
@@ -92,6 +92,11 @@ impl AppleNeuralEngineDriver {
         Ok(handle)
     }
 
+    /// Check whether a model path has already been loaded
+    pub fn is_model_loaded(&self, path: &str) -> bool {
+        self.loaded_models.contains_key(path)
+    }
+
     /// Execute inference using a loaded model
     pub fn execute_inference(
         &self,
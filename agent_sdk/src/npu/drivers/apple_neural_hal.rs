@@ -34,11 +34,11 @@ impl NpuHal for AppleNeuralEngineHal {
 
     async fn create_scheduler(
         &self,
-        _devices: Arc<RwLock<Vec<Arc<dyn NpuDevice + Send + Sync>>>>,
+        devices: Arc<RwLock<Vec<Arc<dyn NpuDevice + Send + Sync>>>>,
     ) -> Result<Arc<dyn NpuScheduler + Send + Sync>> {
         // For now, use the mock scheduler
         // In a full implementation, we'd create an Apple-specific scheduler
-        Ok(Arc::new(MockScheduler::default()))
+        Ok(Arc::new(MockScheduler::new(devices)))
     }
 
     fn get_hal_info(&self) -> HalInfo {
@@ -0,0 +1,149 @@
+//! Device manifests for the mock NPU HAL
+//!
+//! A manifest describes the devices the mock HAL should report, so specific
+//! hardware can be simulated without recompiling. Manifests are JSON:
+//!
+//! ```json
+//! {
+//!   "devices": [
+//!     { "name": "Edge NPU", "device_type": "IntelNpu", "peak_tops": 11.0,
+//!       "memory_mb": 2048, "precisions": ["Float16", "Int8"] }
+//!   ]
+//! }
+//! ```
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::npu::{DataType, NpuCapabilities, NpuDeviceType, NpuError};
+
+/// Environment variable naming a manifest for `init_mock_npu_subsystem`
+pub const NPU_MANIFEST_ENV: &str = "NEBULA_NPU_MANIFEST";
+
+/// A list of simulated devices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceManifest {
+    pub devices: Vec<DeviceSpec>,
+}
+
+/// Specification of one simulated device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceSpec {
+    pub name: String,
+    #[serde(default = "default_device_type")]
+    pub device_type: NpuDeviceType,
+    pub peak_tops: f64,
+    pub memory_mb: u64,
+    pub precisions: Vec<DataType>,
+}
+
+fn default_device_type() -> NpuDeviceType {
+    NpuDeviceType::Mock
+}
+
+impl DeviceManifest {
+    /// Parse a manifest from JSON text
+    pub fn from_json(json: &str) -> Result<Self> {
+        let manifest: Self = serde_json::from_str(json)
+            .map_err(|e| NpuError::ConfigError(format!("invalid NPU manifest: {}", e)))?;
+        if manifest.devices.is_empty() {
+            return Err(NpuError::ConfigError("NPU manifest lists no devices".to_string()).into());
+        }
+        Ok(manifest)
+    }
+
+    /// Read and parse a manifest file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            NpuError::ConfigError(format!("cannot read NPU manifest {}: {}", path.display(), e))
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// Load the manifest named by `NEBULA_NPU_MANIFEST`, if the variable is set
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var_os(NPU_MANIFEST_ENV) {
+            Some(path) => Self::load(path).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl DeviceSpec {
+    /// Capabilities of this device, starting from the defaults
+    pub fn capabilities(&self) -> NpuCapabilities {
+        let mut capabilities = NpuCapabilities::default();
+        capabilities.performance.peak_tops = self.peak_tops;
+        capabilities.performance.sustained_tops = self.peak_tops * 0.8;
+        capabilities.memory.total_memory_bytes = self.memory_mb * 1024 * 1024;
+        capabilities.memory.max_allocation_bytes = capabilities
+            .memory
+            .max_allocation_bytes
+            .min(capabilities.memory.total_memory_bytes);
+        capabilities.compute.supported_data_types = self.precisions.clone();
+        capabilities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::npu::mock::MockNpuHal;
+    use crate::npu::NpuManager;
+    use std::sync::Arc;
+
+    const TWO_DEVICES: &str = r#"{
+        "devices": [
+            { "name": "Edge NPU", "device_type": "IntelNpu", "peak_tops": 11.0,
+              "memory_mb": 2048, "precisions": ["Float16", "Int8"] },
+            { "name": "Big NPU", "peak_tops": 45.0,
+              "memory_mb": 16384, "precisions": ["Float32"] }
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn test_manifest_devices_discovered() {
+        let manifest = DeviceManifest::from_json(TWO_DEVICES).unwrap();
+        let hal = Arc::new(MockNpuHal::with_manifest(manifest));
+        let manager = NpuManager::new(hal).await.unwrap();
+
+        let devices = manager.get_devices().await;
+        assert_eq!(devices.len(), 2);
+
+        let edge = devices[0].info();
+        assert_eq!(edge.name, "Edge NPU");
+        assert_eq!(edge.device_type, NpuDeviceType::IntelNpu);
+        let caps = devices[0].capabilities();
+        assert_eq!(caps.performance.peak_tops, 11.0);
+        assert_eq!(caps.memory.total_memory_bytes, 2048 * 1024 * 1024);
+        assert_eq!(
+            caps.compute.supported_data_types,
+            vec![DataType::Float16, DataType::Int8]
+        );
+
+        let big = devices[1].info();
+        assert_eq!(big.name, "Big NPU");
+        assert_eq!(big.device_type, NpuDeviceType::Mock);
+        assert_ne!(devices[0].id(), devices[1].id());
+        assert_eq!(devices[1].capabilities().performance.peak_tops, 45.0);
+    }
+
+    #[test]
+    fn test_malformed_manifest_rejected() {
+        for json in [
+            "not json",
+            r#"{ "devices": [] }"#,
+            r#"{ "devices": [{ "name": "x", "peak_tops": "fast", "memory_mb": 1, "precisions": [] }] }"#,
+        ] {
+            let err = DeviceManifest::from_json(json).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<NpuError>(),
+                Some(NpuError::ConfigError(_))
+            ));
+        }
+    }
+}
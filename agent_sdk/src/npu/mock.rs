@@ -2,14 +2,20 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 use crate::npu::hal::{HalFeature, HalInfo, MemoryHandle, ModelHandle};
+use crate::npu::manifest::{DeviceManifest, DeviceSpec};
 use crate::npu::scheduler::MockScheduler;
+use crate::npu::types::metadata_keys;
 use crate::npu::{
-    DeviceHealth, InferenceOutput, InferenceRequest, InferenceResponse, MemoryRegion,
+    DeviceHealth, HealthCache, InferenceOutput, InferenceRequest, InferenceResponse, MemoryRegion,
     NpuCapabilities, NpuDevice, NpuDeviceId, NpuDeviceInfo, NpuDeviceType, NpuHal, NpuScheduler,
     NpuUsageStats, NpuVendor, PowerState,
 };
@@ -17,11 +23,23 @@ use crate::npu::{
 /// Mock HAL implementation
 pub struct MockNpuHal {
     devices: Vec<Arc<dyn NpuDevice + Send + Sync>>,
+    manifest: Option<DeviceManifest>,
 }
 
 impl MockNpuHal {
     pub async fn new() -> Result<Self> {
-        Ok(Self { devices: vec![] })
+        Ok(Self {
+            devices: vec![],
+            manifest: None,
+        })
+    }
+
+    /// Mock HAL reporting the devices described by a manifest
+    pub fn with_manifest(manifest: DeviceManifest) -> Self {
+        Self {
+            devices: vec![],
+            manifest: Some(manifest),
+        }
     }
 }
 
@@ -29,16 +47,27 @@ impl MockNpuHal {
 impl NpuHal for MockNpuHal {
     async fn discover_devices(&self) -> Result<Vec<Arc<dyn NpuDevice + Send + Sync>>> {
         let mut devices: Vec<Arc<dyn NpuDevice + Send + Sync>> = Vec::new();
-        // Add mock devices for testing
-        devices.push(Arc::new(MockNpuDevice::new().await?) as Arc<dyn NpuDevice + Send + Sync>);
+        match &self.manifest {
+            Some(manifest) => {
+                for (index, spec) in manifest.devices.iter().enumerate() {
+                    devices.push(Arc::new(MockNpuDevice::from_spec(index, spec)));
+                }
+            }
+            None => {
+                // Add mock devices for testing
+                devices.push(
+                    Arc::new(MockNpuDevice::new().await?) as Arc<dyn NpuDevice + Send + Sync>
+                );
+            }
+        }
         Ok(devices)
     }
 
     async fn create_scheduler(
         &self,
-        _devices: Arc<RwLock<Vec<Arc<dyn NpuDevice + Send + Sync>>>>,
+        devices: Arc<RwLock<Vec<Arc<dyn NpuDevice + Send + Sync>>>>,
     ) -> Result<Arc<dyn NpuScheduler + Send + Sync>> {
-        Ok(Arc::new(MockScheduler::default()))
+        Ok(Arc::new(MockScheduler::new(devices)))
     }
 
     fn get_hal_info(&self) -> crate::npu::HalInfo {
@@ -59,10 +88,22 @@ impl NpuHal for MockNpuHal {
     }
 }
 
+/// Execution time a mock device simulates for each inference
+const DEFAULT_MOCK_LATENCY: Duration = Duration::from_millis(10);
+
 /// Mock NPU Device
 pub struct MockNpuDevice {
     info: NpuDeviceInfo,
     capabilities: Arc<NpuCapabilities>,
+    health: HealthCache,
+    /// Simulated time every inference takes
+    base_latency: Duration,
+    /// Most extra time added at random to each inference
+    jitter: Duration,
+    rng: Mutex<StdRng>,
+    /// Inferences running now, and the most that ever ran at once
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
 }
 
 impl MockNpuDevice {
@@ -76,7 +117,87 @@ impl MockNpuDevice {
 
         let capabilities = Arc::new(NpuCapabilities::default());
 
-        Ok(Self { info, capabilities })
+        Ok(Self {
+            info,
+            capabilities,
+            health: HealthCache::default(),
+            base_latency: DEFAULT_MOCK_LATENCY,
+            jitter: Duration::ZERO,
+            rng: Mutex::new(StdRng::from_entropy()),
+            in_flight: AtomicUsize::new(0),
+            peak_in_flight: AtomicUsize::new(0),
+        })
+    }
+
+    /// Mock device matching a manifest entry
+    pub fn from_spec(index: usize, spec: &DeviceSpec) -> Self {
+        let info = NpuDeviceInfo::new(
+            NpuDeviceId::new(format!("mock-device-{}", index)),
+            spec.name.clone(),
+            spec.device_type.clone(),
+            NpuVendor::Unknown("MockVendor".to_string()),
+        );
+
+        Self {
+            info,
+            capabilities: Arc::new(spec.capabilities()),
+            health: HealthCache::default(),
+            base_latency: DEFAULT_MOCK_LATENCY,
+            jitter: Duration::ZERO,
+            rng: Mutex::new(StdRng::from_entropy()),
+            in_flight: AtomicUsize::new(0),
+            peak_in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    /// Replace the capabilities the device reports
+    pub fn with_capabilities(mut self, capabilities: NpuCapabilities) -> Self {
+        self.capabilities = Arc::new(capabilities);
+        self
+    }
+
+    /// Most inferences the device has run at the same time
+    pub fn peak_concurrency(&self) -> usize {
+        self.peak_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Make each inference take `base` plus up to `jitter` more, chosen
+    /// at random
+    pub fn with_latency(mut self, base: Duration, jitter: Duration) -> Self {
+        self.base_latency = base;
+        self.jitter = jitter;
+        self
+    }
+
+    /// Draw jitter from a generator seeded with `seed`, so runs repeat
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Mutex::new(StdRng::seed_from_u64(seed));
+        self
+    }
+
+    /// Simulated execution time of the next inference
+    fn next_latency(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return self.base_latency;
+        }
+        let jitter = self
+            .rng
+            .lock()
+            .unwrap()
+            .gen_range(Duration::ZERO..=self.jitter);
+        self.base_latency + jitter
+    }
+
+    fn read_health() -> DeviceHealth {
+        DeviceHealth {
+            is_healthy: true,
+            temperature_celsius: 35.0,
+            power_consumption_watts: 10.0,
+            memory_errors: 0,
+            compute_errors: 0,
+            last_check: std::time::SystemTime::now(),
+            status_message: "All systems nominal".to_string(),
+        }
     }
 }
 
@@ -106,18 +227,24 @@ impl NpuDevice for MockNpuDevice {
 
     async fn execute_inference(&self, request: InferenceRequest) -> Result<InferenceResponse> {
         log::info!("Executing inference on Mock NPU Device: {:?}", request);
+        let started = std::time::Instant::now();
+        let running = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_in_flight.fetch_max(running, Ordering::SeqCst);
+        tokio::time::sleep(self.next_latency()).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
         // Mock output
-        let outputs = vec![InferenceOutput {
-            data: request.inputs[0].data.clone(),
-            shape: request.inputs[0].shape.clone(),
-            data_type: request.inputs[0].data_type.clone(),
-        }];
+        let outputs: Vec<InferenceOutput> = vec![request.inputs[0].clone()];
+        let quantized = request.inputs[0].data_type().is_quantized();
         Ok(InferenceResponse {
             outputs,
-            execution_time: std::time::Duration::from_millis(10),
+            execution_time: started.elapsed(),
             device_id: self.id(),
             metadata: HashMap::new(),
-        })
+        }
+        .with_metadata(metadata_keys::DEVICE_TYPE, &self.info.device_type)
+        .with_metadata(metadata_keys::EXECUTION_PROVIDER, "mock")
+        .with_metadata(metadata_keys::QUANTIZED, quantized))
     }
 
     async fn load_model(&self, _model_path: &str) -> Result<ModelHandle> {
@@ -133,15 +260,17 @@ impl NpuDevice for MockNpuDevice {
     }
 
     async fn get_health(&self) -> Result<DeviceHealth> {
-        Ok(DeviceHealth {
-            is_healthy: true,
-            temperature_celsius: 35.0,
-            power_consumption_watts: 10.0,
-            memory_errors: 0,
-            compute_errors: 0,
-            last_check: std::time::SystemTime::now(),
-            status_message: "All systems nominal".to_string(),
-        })
+        self.health.get(|| async { Ok(Self::read_health()) }).await
+    }
+
+    fn get_cached_health(&self) -> Option<DeviceHealth> {
+        self.health.cached()
+    }
+
+    async fn refresh_health(&self) -> Result<DeviceHealth> {
+        self.health
+            .refresh(|| async { Ok(Self::read_health()) })
+            .await
     }
 
     async fn get_power_state(&self) -> Result<PowerState> {
@@ -181,3 +310,95 @@ impl NpuDevice for MockNpuDevice {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::npu::{DataType, TaskPriority, Tensor};
+
+    fn request(data_type: DataType) -> InferenceRequest {
+        InferenceRequest {
+            model_path: "model.onnx".to_string(),
+            inputs: vec![
+                Tensor::new(vec![1; 4 * data_type.size()], vec![1, 4], data_type).unwrap(),
+            ],
+            timeout: std::time::Duration::from_secs(1),
+            priority: TaskPriority::Normal,
+            agent_id: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_response_metadata() {
+        let device = MockNpuDevice::new().await.unwrap();
+
+        let response = device
+            .execute_inference(request(DataType::Float32))
+            .await
+            .unwrap();
+        assert_eq!(response.device_type(), Some(NpuDeviceType::Mock));
+        assert_eq!(response.execution_provider(), Some("mock"));
+        assert_eq!(response.quantized(), Some(false));
+        assert_eq!(response.queue_wait(), None);
+
+        let response = device
+            .execute_inference(request(DataType::Int8))
+            .await
+            .unwrap();
+        assert_eq!(response.quantized(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_mock_latency_and_jitter() {
+        let base = Duration::from_millis(200);
+        let jitter = Duration::from_millis(20);
+        let device = MockNpuDevice::new()
+            .await
+            .unwrap()
+            .with_latency(base, jitter)
+            .with_seed(42);
+
+        let response = device
+            .execute_inference(request(DataType::Float32))
+            .await
+            .unwrap();
+        assert!(response.execution_time >= base);
+        assert!(
+            response.execution_time < base + jitter + Duration::from_millis(100),
+            "took {:?}",
+            response.execution_time
+        );
+
+        // The same seed draws the same jitter, one inference behind
+        let replay = MockNpuDevice::new()
+            .await
+            .unwrap()
+            .with_latency(base, jitter)
+            .with_seed(42);
+        replay.next_latency();
+        let drawn: Vec<_> = (0..5).map(|_| device.next_latency()).collect();
+        let replayed: Vec<_> = (0..5).map(|_| replay.next_latency()).collect();
+        assert_eq!(drawn, replayed);
+        assert!(drawn
+            .iter()
+            .all(|latency| *latency >= base && *latency <= base + jitter));
+    }
+
+    #[tokio::test]
+    async fn test_mock_health_is_cached() {
+        let device = MockNpuDevice::new().await.unwrap();
+        assert!(device.get_cached_health().is_none());
+
+        let health = device.get_health().await.unwrap();
+        let cached = device.get_cached_health().unwrap();
+        assert_eq!(cached.last_check, health.last_check);
+
+        let refreshed = device.refresh_health().await.unwrap();
+        assert!(refreshed.last_check >= health.last_check);
+        assert_eq!(
+            device.get_cached_health().unwrap().last_check,
+            refreshed.last_check
+        );
+    }
+}
@@ -7,40 +7,97 @@ pub mod capabilities;
 pub mod device;
 pub mod drivers;
 pub mod hal;
+pub mod manifest;
 pub mod mock;
 pub mod scheduler;
 pub mod types;
 
 // Re-export commonly used types and traits
-pub use capabilities::{ComputeCapability, MemoryCapability, NpuCapabilities};
-pub use device::{NpuDevice, NpuDeviceInfo};
+pub use capabilities::{
+    CapabilityReport, ComputeCapability, ComputeUnitCount, MemoryCapability, NpuCapabilities,
+};
+pub use device::{HealthCache, NpuDevice, NpuDeviceInfo};
 pub use hal::{HalFeature, HalInfo, MemoryHandle, ModelHandle, NpuDriver, NpuHal};
-pub use scheduler::NpuScheduler;
+pub use manifest::{DeviceManifest, DeviceSpec};
+pub use scheduler::{AgingPolicy, NpuScheduler, ScheduledTask, SchedulerSnapshot};
 pub use types::*;
 
-use crate::types::TaskId;
+use crate::backend::{BackendCapabilities, InferenceBackend};
+use crate::ml;
+use crate::types::{AgentId, InferenceTelemetry, TaskId, TelemetryCallback};
 use anyhow::Result;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// Timeout applied to inferences run through the `InferenceBackend` interface
+pub const BACKEND_INFERENCE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Order `NpuManager` keeps its devices in, so the first device and
+/// round-robin selection don't depend on discovery order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeviceOrder {
+    /// Ascending device id
+    #[default]
+    Id,
+    /// Highest peak TOPS first, ties broken by ascending device id
+    PeakTops,
+}
+
+impl DeviceOrder {
+    fn sort(self, devices: &mut [Arc<dyn NpuDevice + Send + Sync>]) {
+        match self {
+            DeviceOrder::Id => devices.sort_by(|a, b| a.id().as_str().cmp(b.id().as_str())),
+            DeviceOrder::PeakTops => devices.sort_by(|a, b| {
+                b.capabilities()
+                    .performance
+                    .peak_tops
+                    .total_cmp(&a.capabilities().performance.peak_tops)
+                    .then_with(|| a.id().as_str().cmp(b.id().as_str()))
+            }),
+        }
+    }
+}
+
 /// Global NPU manager that coordinates all NPU devices and scheduling
 pub struct NpuManager {
     hal: Arc<dyn NpuHal + Send + Sync>,
     devices: Arc<RwLock<Vec<Arc<dyn NpuDevice + Send + Sync>>>>,
+    device_order: DeviceOrder,
     scheduler: Arc<dyn NpuScheduler + Send + Sync>,
+    /// Model paths by model id, for requests made through `InferenceBackend`
+    models: RwLock<HashMap<String, String>>,
+    telemetry: std::sync::RwLock<Option<TelemetryCallback>>,
 }
 
 impl NpuManager {
-    /// Create a new NPU manager with the specified HAL implementation
+    /// Create a new NPU manager with the specified HAL implementation,
+    /// ordering its devices by id
     pub async fn new(hal: Arc<dyn NpuHal + Send + Sync>) -> Result<Self> {
+        Self::with_device_order(hal, DeviceOrder::default()).await
+    }
+
+    /// Create a new NPU manager that keeps its devices in `device_order`
+    pub async fn with_device_order(
+        hal: Arc<dyn NpuHal + Send + Sync>,
+        device_order: DeviceOrder,
+    ) -> Result<Self> {
         let devices = Arc::new(RwLock::new(Vec::new()));
 
         // Discover available NPU devices
         let discovered_devices = hal.discover_devices().await?;
+        if discovered_devices.is_empty() {
+            log::warn!(
+                "{} discovered no NPU devices; inference tasks will be rejected",
+                hal.get_hal_info().name
+            );
+        }
         let mut device_list = devices.write().await;
         for device in discovered_devices {
             device_list.push(device);
         }
+        device_order.sort(&mut device_list);
         drop(device_list);
 
         // Create scheduler with discovered devices
@@ -49,7 +106,10 @@ impl NpuManager {
         Ok(NpuManager {
             hal,
             devices,
+            device_order,
             scheduler,
+            models: RwLock::new(HashMap::new()),
+            telemetry: std::sync::RwLock::new(None),
         })
     }
 
@@ -67,8 +127,56 @@ impl NpuManager {
         devices.iter().find(|d| d.id() == *device_id).cloned()
     }
 
-    /// Submit an inference task to the scheduler
+    /// Add a device from a third-party driver without writing a HAL
+    ///
+    /// The device is initialized first and rejected if that fails or its id
+    /// is already registered. Schedulers created by the HAL share the
+    /// manager's device list, so tasks can target the device right away.
+    pub async fn register_custom_device(
+        &self,
+        device: Arc<dyn NpuDevice + Send + Sync>,
+    ) -> Result<()> {
+        let device_id = device.id();
+        if self.get_device(&device_id).await.is_some() {
+            return Err(NpuError::ConfigError(format!(
+                "device {} is already registered",
+                device_id
+            ))
+            .into());
+        }
+
+        device.init().await.map_err(|e| {
+            NpuError::DriverError(format!("device {} failed to initialize: {}", device_id, e))
+        })?;
+
+        let mut devices = self.devices.write().await;
+        if devices.iter().any(|d| d.id() == device_id) {
+            return Err(NpuError::ConfigError(format!(
+                "device {} is already registered",
+                device_id
+            ))
+            .into());
+        }
+        devices.push(device);
+        self.device_order.sort(&mut devices);
+        log::info!("Registered custom NPU device {}", device_id);
+        Ok(())
+    }
+
+    /// Check whether any NPU devices are available to run tasks
+    pub async fn has_devices(&self) -> bool {
+        !self.devices.read().await.is_empty()
+    }
+
+    /// Submit an inference task to the scheduler, returning the
+    /// scheduler-assigned id used to query or cancel it
     pub async fn submit_task(&self, task: InferenceTask) -> Result<TaskId> {
+        if !self.has_devices().await {
+            return Err(NpuError::DeviceUnavailable(
+                "no NPU devices available to run the task".to_string(),
+            )
+            .into());
+        }
         self.scheduler.submit_task(task).await
     }
 
@@ -82,10 +190,190 @@ impl NpuManager {
         self.scheduler.get_task_status(task_id).await
     }
 
+    /// Cancel every queued or running task submitted by an agent, returning
+    /// how many were cancelled. Deregistering an agent from an
+    /// `AgentRuntime` does not reach the NPU manager, so whoever owns both
+    /// calls this after `deregister_agent`.
+    pub async fn cancel_agent_tasks(&self, agent_id: AgentId) -> Result<usize> {
+        let tasks = self
+            .scheduler
+            .list_tasks(TaskFilter::default().with_agent(agent_id))
+            .await;
+
+        let mut cancelled = 0;
+        for task in tasks {
+            if matches!(task.status, TaskStatus::Queued | TaskStatus::Running) {
+                self.scheduler.cancel_task(task.id).await?;
+                cancelled += 1;
+            }
+        }
+
+        if cancelled > 0 {
+            log::info!("Cancelled {} task(s) for agent {}", cancelled, agent_id);
+        }
+        Ok(cancelled)
+    }
+
+    /// Run the next queued task, returning its id and outcome, or `None`
+    /// if nothing is queued
+    pub async fn run_next_task(&self) -> Option<(TaskId, Result<InferenceResponse>)> {
+        self.scheduler.run_next().await
+    }
+
+    /// List scheduler tasks matching the filter
+    pub async fn list_tasks(&self, filter: TaskFilter) -> Vec<TaskSummary> {
+        self.scheduler.list_tasks(filter).await
+    }
+
+    /// Queued tasks that have waited past the scheduler's starvation
+    /// threshold, longest wait first
+    pub async fn starvation_report(&self) -> Vec<(TaskId, Duration)> {
+        self.scheduler.starvation_report().await
+    }
+
+    /// Capture the scheduler's tasks so they can survive a restart
+    pub async fn snapshot_scheduler(&self) -> SchedulerSnapshot {
+        self.scheduler.snapshot().await
+    }
+
+    /// Rehydrate the scheduler from a snapshot
+    pub async fn restore_scheduler(&self, snapshot: SchedulerSnapshot) -> Result<()> {
+        self.scheduler.restore(snapshot).await
+    }
+
     /// Get system-wide NPU usage statistics
     pub async fn get_usage_stats(&self) -> NpuUsageStats {
         self.scheduler.get_usage_stats().await
     }
+
+    /// Report every inference run through `InferenceBackend` to `callback`,
+    /// replacing any previous one
+    pub fn set_telemetry(&self, callback: TelemetryCallback) {
+        *self.telemetry.write().unwrap() = Some(callback);
+    }
+
+    fn report_telemetry(&self, telemetry: InferenceTelemetry) {
+        let callback = self.telemetry.read().unwrap().clone();
+        if let Some(callback) = callback {
+            callback(&telemetry);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl InferenceBackend for NpuManager {
+    fn name(&self) -> &str {
+        "npu"
+    }
+
+    async fn load(&self, config: ml::ModelConfig) -> Result<()> {
+        let devices = self.get_devices().await;
+        if devices.is_empty() {
+            return Err(NpuError::DeviceUnavailable(
+                "no NPU devices available to load the model".to_string(),
+            )
+            .into());
+        }
+        for device in devices {
+            device.load_model(&config.model_path).await?;
+        }
+        self.models
+            .write()
+            .await
+            .insert(config.model_id, config.model_path);
+        Ok(())
+    }
+
+    async fn infer(&self, request: ml::InferenceRequest) -> Result<ml::InferenceResponse> {
+        let model_path = self
+            .models
+            .read()
+            .await
+            .get(&request.model_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Model {} not loaded", request.model_id))?;
+
+        let mut device = None;
+        for candidate in self.get_devices().await {
+            if candidate.is_available().await {
+                device = Some(candidate);
+                break;
+            }
+        }
+        let device = device.ok_or_else(|| {
+            NpuError::DeviceUnavailable("no NPU device is ready for inference".to_string())
+        })?;
+
+        let input_data = request.input_data.load()?;
+        let input_bytes = input_data.len();
+        let start_time = std::time::Instant::now();
+        let result = device
+            .execute_inference(InferenceRequest {
+                model_path,
+                inputs: vec![Tensor::new(
+                    input_data,
+                    request.input_shape.iter().map(|&d| d as u64).collect(),
+                    DataType::Float32,
+                )?],
+                timeout: BACKEND_INFERENCE_TIMEOUT,
+                priority: request.priority.into(),
+                agent_id: None,
+                metadata: HashMap::new(),
+            })
+            .await;
+        self.report_telemetry(InferenceTelemetry {
+            model_id: request.model_id,
+            task_id: request.task_id,
+            latency: start_time.elapsed(),
+            device: device.id().to_string(),
+            success: result.is_ok(),
+            input_bytes,
+            output_bytes: result.as_ref().map_or(0, |response| {
+                response.outputs.iter().map(|o| o.data().len()).sum()
+            }),
+        });
+        let response = result?;
+
+        let outputs: Vec<ml::InferenceOutput> = response
+            .outputs
+            .into_iter()
+            .enumerate()
+            .map(|(index, output)| ml::InferenceOutput {
+                name: format!("output_{}", index),
+                data: output.data().to_vec(),
+                shape: output.shape().iter().map(|&d| d as usize).collect(),
+            })
+            .collect();
+        let (output_data, output_shape) = outputs
+            .first()
+            .map(|output| (output.data.clone(), output.shape.clone()))
+            .unwrap_or_default();
+
+        Ok(ml::InferenceResponse {
+            task_id: request.task_id,
+            success: true,
+            output_data,
+            output_shape,
+            outputs,
+            latency_ms: response.execution_time.as_millis() as u64,
+            error: None,
+        })
+    }
+
+    async fn capabilities(&self) -> BackendCapabilities {
+        let devices = self.get_devices().await;
+        BackendCapabilities {
+            hardware_accelerated: devices
+                .iter()
+                .any(|d| d.info().device_type != NpuDeviceType::Mock),
+            max_batch_size: devices
+                .iter()
+                .map(|d| d.capabilities().max_batch_size() as usize)
+                .max()
+                .unwrap_or(1),
+            loaded_models: self.models.read().await.keys().cloned().collect(),
+        }
+    }
 }
 
 /// Initialize the NPU subsystem with default (mock) implementation
@@ -109,12 +397,16 @@ pub async fn init_npu_subsystem() -> Result<NpuManager> {
     }
 }
 
-/// Initialize NPU subsystem with explicit mock implementation
+/// Initialize NPU subsystem with explicit mock implementation. Devices come
+/// from the manifest named by `NEBULA_NPU_MANIFEST` when it is set.
 pub async fn init_mock_npu_subsystem() -> Result<NpuManager> {
     #[cfg(feature = "npu")]
     {
         log::info!("Initializing mock NPU subsystem");
-        let mock_hal = Arc::new(mock::MockNpuHal::new().await?);
+        let mock_hal = match DeviceManifest::from_env()? {
+            Some(manifest) => Arc::new(mock::MockNpuHal::with_manifest(manifest)),
+            None => Arc::new(mock::MockNpuHal::new().await?),
+        };
         NpuManager::new(mock_hal).await
     }
 
@@ -180,3 +472,260 @@ async fn detect_nvidia_gpu() -> Result<Arc<dyn NpuHal + Send + Sync>> {
     // Stub implementation - would use CUDA
     anyhow::bail!("NVIDIA GPU detection not yet implemented")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    /// HAL that finds no hardware
+    struct EmptyHal;
+
+    #[async_trait]
+    impl NpuHal for EmptyHal {
+        async fn discover_devices(&self) -> Result<Vec<Arc<dyn NpuDevice + Send + Sync>>> {
+            Ok(Vec::new())
+        }
+
+        async fn create_scheduler(
+            &self,
+            devices: Arc<RwLock<Vec<Arc<dyn NpuDevice + Send + Sync>>>>,
+        ) -> Result<Arc<dyn NpuScheduler + Send + Sync>> {
+            Ok(Arc::new(scheduler::MockScheduler::new(devices)))
+        }
+
+        fn get_hal_info(&self) -> HalInfo {
+            HalInfo {
+                name: "Empty HAL".to_string(),
+                version: "1.0.0".to_string(),
+                supported_devices: vec![],
+                features: vec![],
+            }
+        }
+
+        async fn shutdown(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn test_task() -> InferenceTask {
+        InferenceTask {
+            id: 0,
+            request: InferenceRequest {
+                model_path: "model.onnx".to_string(),
+                inputs: vec![],
+                timeout: Duration::from_secs(1),
+                priority: TaskPriority::Normal,
+                agent_id: Some(1),
+                metadata: HashMap::new(),
+            },
+            priority: TaskPriority::Normal,
+            resource_requirements: ResourceAllocation {
+                device_id: NpuDeviceId::new("none"),
+                compute_units: vec![],
+                memory_bytes: 0,
+                power_budget_watts: 0.0,
+                timeout: Duration::from_secs(1),
+            },
+            scheduling_hints: SchedulingHints::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit_without_devices_fails() {
+        let manager = NpuManager::new(Arc::new(EmptyHal)).await.unwrap();
+        assert!(!manager.has_devices().await);
+
+        let err = manager.submit_task(test_task()).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<NpuError>(),
+            Some(NpuError::DeviceUnavailable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_submit_with_mock_devices_succeeds() {
+        let manager = init_mock_npu_subsystem().await.unwrap();
+        assert!(manager.has_devices().await);
+        assert!(manager.submit_task(test_task()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_usage_stats_without_devices_are_zero() {
+        let manager = NpuManager::new(Arc::new(EmptyHal)).await.unwrap();
+        let stats = manager.get_usage_stats().await;
+        assert_eq!(stats.total_devices, 0);
+        assert_eq!(stats.active_devices, 0);
+        assert_eq!(stats.compute_utilization, 0.0);
+        assert_eq!(stats.power_consumption_watts, 0.0);
+
+        let manager = init_mock_npu_subsystem().await.unwrap();
+        let stats = manager.get_usage_stats().await;
+        assert!(stats.active_devices > 0);
+        assert!((0.0..=1.0).contains(&stats.compute_utilization));
+        assert!(stats.power_consumption_watts.is_finite());
+    }
+
+    /// HAL that discovers the same devices in a different order each time
+    struct ShuffledHal;
+
+    #[async_trait]
+    impl NpuHal for ShuffledHal {
+        async fn discover_devices(&self) -> Result<Vec<Arc<dyn NpuDevice + Send + Sync>>> {
+            use rand::seq::SliceRandom;
+
+            let mut devices: Vec<Arc<dyn NpuDevice + Send + Sync>> = [2.0, 8.0, 4.0, 8.0, 1.0]
+                .iter()
+                .enumerate()
+                .map(|(index, &peak_tops)| {
+                    let spec = DeviceSpec {
+                        name: format!("NPU {}", index),
+                        device_type: NpuDeviceType::Mock,
+                        peak_tops,
+                        memory_mb: 512,
+                        precisions: vec![DataType::Float32],
+                    };
+                    Arc::new(mock::MockNpuDevice::from_spec(index, &spec))
+                        as Arc<dyn NpuDevice + Send + Sync>
+                })
+                .collect();
+            devices.shuffle(&mut rand::thread_rng());
+            Ok(devices)
+        }
+
+        async fn create_scheduler(
+            &self,
+            devices: Arc<RwLock<Vec<Arc<dyn NpuDevice + Send + Sync>>>>,
+        ) -> Result<Arc<dyn NpuScheduler + Send + Sync>> {
+            Ok(Arc::new(scheduler::MockScheduler::new(devices)))
+        }
+
+        fn get_hal_info(&self) -> HalInfo {
+            HalInfo {
+                name: "Shuffled HAL".to_string(),
+                version: "1.0.0".to_string(),
+                supported_devices: vec![NpuDeviceType::Mock],
+                features: vec![],
+            }
+        }
+
+        async fn shutdown(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn device_ids(manager: &NpuManager) -> Vec<String> {
+        let devices = manager.get_devices().await;
+        devices.iter().map(|d| d.id().to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_device_order_is_stable() {
+        for _ in 0..5 {
+            let manager = NpuManager::new(Arc::new(ShuffledHal)).await.unwrap();
+            assert_eq!(
+                device_ids(&manager).await,
+                vec![
+                    "mock-device-0",
+                    "mock-device-1",
+                    "mock-device-2",
+                    "mock-device-3",
+                    "mock-device-4"
+                ]
+            );
+
+            let manager =
+                NpuManager::with_device_order(Arc::new(ShuffledHal), DeviceOrder::PeakTops)
+                    .await
+                    .unwrap();
+            assert_eq!(
+                device_ids(&manager).await,
+                vec![
+                    "mock-device-1",
+                    "mock-device-3",
+                    "mock-device-2",
+                    "mock-device-0",
+                    "mock-device-4"
+                ]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_custom_device_receives_tasks() {
+        let manager = NpuManager::new(Arc::new(EmptyHal)).await.unwrap();
+        let spec = DeviceSpec {
+            name: "Third-party accelerator".to_string(),
+            device_type: NpuDeviceType::Mock,
+            peak_tops: 4.0,
+            memory_mb: 512,
+            precisions: vec![DataType::Float32],
+        };
+        let device = Arc::new(mock::MockNpuDevice::from_spec(7, &spec));
+        let device_id = device.id();
+
+        manager
+            .register_custom_device(device.clone())
+            .await
+            .unwrap();
+        let devices = manager.get_devices().await;
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].id(), device_id);
+        assert!(manager.register_custom_device(device).await.is_err());
+
+        let mut task = test_task();
+        task.request.inputs = vec![Tensor::from_f32(&[0.0], vec![1, 1]).unwrap()];
+        task.resource_requirements.device_id = device_id.clone();
+        let task_id = manager.submit_task(task).await.unwrap();
+
+        let (ran, result) = manager.run_next_task().await.unwrap();
+        assert_eq!(ran, task_id);
+        assert_eq!(result.unwrap().device_id, device_id);
+    }
+
+    #[tokio::test]
+    async fn test_telemetry_observes_each_inference() {
+        let manager = init_mock_npu_subsystem().await.unwrap();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        manager.set_telemetry(Arc::new(move |telemetry: &InferenceTelemetry| {
+            sink.lock().unwrap().push(telemetry.clone())
+        }));
+
+        manager
+            .load(ml::ModelConfig {
+                model_id: "echo".to_string(),
+                model_path: "/models/echo.onnx".to_string(),
+                ..ml::ModelConfig::default()
+            })
+            .await
+            .unwrap();
+        for task_id in 1..=2 {
+            manager
+                .infer(ml::InferenceRequest {
+                    task_id,
+                    model_id: "echo".to_string(),
+                    input_data: vec![0; 8 * task_id].into(),
+                    input_shape: vec![1, 2 * task_id],
+                    priority: crate::types::InferencePriority::Normal,
+                    named_inputs: Vec::new(),
+                    timeout: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        let device = manager.get_devices().await[0].id().to_string();
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        for (telemetry, task_id) in seen.iter().zip(1..) {
+            assert_eq!(telemetry.task_id, task_id);
+            assert_eq!(telemetry.model_id, "echo");
+            assert_eq!(telemetry.device, device);
+            assert!(telemetry.success);
+            // The mock device echoes its input
+            assert_eq!(telemetry.input_bytes, 8 * task_id);
+            assert_eq!(telemetry.output_bytes, 8 * task_id);
+        }
+    }
+}
@@ -1,65 +1,989 @@
 //! NPU Scheduler interface and implementation
 
 use crate::npu::{
-    InferenceResponse, InferenceTask, NpuDeviceId, NpuUsageStats, TaskPriority, TaskStatus,
+    metadata_keys, DataType, InferenceRequest, InferenceResponse, InferenceTask, NpuCapabilities,
+    NpuDevice, NpuDeviceId, NpuError, NpuUsageStats, PrecisionPreference, TaskFilter, TaskPriority,
+    TaskStatus, TaskSummary,
 };
 use crate::types::TaskId;
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::sync::RwLock;
 
+/// How long a task may stay queued before `starvation_report` lists it
+pub const DEFAULT_STARVATION_THRESHOLD: Duration = Duration::from_secs(5);
+
+/// How many finished tasks the scheduler keeps for status queries before
+/// evicting the oldest
+pub const DEFAULT_FINISHED_TASK_LIMIT: usize = 1024;
+
+/// Window `NpuUsageStats::tasks_completed_last_minute` counts over
+const COMPLETION_WINDOW: Duration = Duration::from_secs(60);
+
 /// NPU Task Scheduler trait
 #[async_trait]
 pub trait NpuScheduler: Send + Sync {
-    /// Submit a new inference task to the scheduler
+    /// Submit a new inference task to the scheduler, returning the
+    /// authoritative id every other call takes. The task's own `id` is
+    /// recorded as its external id and may repeat across submissions.
     async fn submit_task(&self, task: InferenceTask) -> Result<TaskId>;
 
-    /// Cancel a running or queued task
+    /// Cancel a running or queued task by its scheduler-assigned id
     async fn cancel_task(&self, task_id: TaskId) -> Result<()>;
 
-    /// Get the status of a task
+    /// Get the status of a task by its scheduler-assigned id
     async fn get_task_status(&self, task_id: TaskId) -> Option<TaskStatus>;
 
     /// Get system usage statistics
     async fn get_usage_stats(&self) -> NpuUsageStats;
+
+    /// List tasks known to the scheduler that match the filter
+    async fn list_tasks(&self, filter: TaskFilter) -> Vec<TaskSummary>;
+
+    /// Capture every known task and its status
+    async fn snapshot(&self) -> SchedulerSnapshot;
+
+    /// Replace the scheduler's tasks with a snapshot. Tasks that were
+    /// running when the snapshot was taken are queued again.
+    async fn restore(&self, snapshot: SchedulerSnapshot) -> Result<()>;
+
+    /// Run the highest-priority queued task on its device, returning its id
+    /// and outcome. Tasks whose device is already running its
+    /// `max_concurrent_inferences` stay queued; `None` means no queued task
+    /// can run now. Successful responses carry `metadata_keys::QUEUE_WAIT_MS`.
+    async fn run_next(&self) -> Option<(TaskId, Result<InferenceResponse>)>;
+
+    /// Queued tasks that have waited longer than the starvation threshold,
+    /// longest wait first
+    async fn starvation_report(&self) -> Vec<(TaskId, Duration)>;
+}
+
+/// Raises a queued task's effective priority one level for every
+/// `interval` it waits, up to `Critical`, so low-priority work cannot be
+/// starved indefinitely by a steady stream of higher-priority tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgingPolicy {
+    pub interval: Duration,
+}
+
+impl AgingPolicy {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+
+    /// Urgency rank after waiting `waited`, where 0 is `Critical`
+    fn rank(&self, priority: &TaskPriority, waited: Duration) -> u32 {
+        let levels = waited
+            .as_nanos()
+            .checked_div(self.interval.as_nanos())
+            .unwrap_or(0);
+        rank(priority).saturating_sub(levels.min(u32::MAX as u128) as u32)
+    }
+}
+
+/// Urgency rank of a priority, where 0 is `Critical`
+fn rank(priority: &TaskPriority) -> u32 {
+    priority.clone() as u32
+}
+
+/// A task together with its scheduler-side state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    pub task: InferenceTask,
+    pub summary: TaskSummary,
+}
+
+/// Serializable scheduler state, used to carry queued tasks across restarts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchedulerSnapshot {
+    /// Tasks ordered by id
+    pub tasks: Vec<ScheduledTask>,
 }
 
 /// Mock implementation of an NPU Scheduler
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct MockScheduler {
-    tasks: Arc<RwLock<HashMap<TaskId, TaskStatus>>>,
+    tasks: Arc<RwLock<HashMap<TaskId, ScheduledTask>>>,
+    /// Next id to assign, kept apart from the task map so evicted ids are
+    /// never reused
+    next_id: Arc<AtomicUsize>,
+    devices: Arc<RwLock<Vec<Arc<dyn NpuDevice + Send + Sync>>>>,
+    aging: Option<AgingPolicy>,
+    starvation_threshold: Duration,
+    finished_task_limit: usize,
+}
+
+impl MockScheduler {
+    /// Scheduler that runs tasks on the given devices
+    pub fn new(devices: Arc<RwLock<Vec<Arc<dyn NpuDevice + Send + Sync>>>>) -> Self {
+        Self {
+            tasks: Arc::default(),
+            next_id: Arc::default(),
+            devices,
+            aging: None,
+            starvation_threshold: DEFAULT_STARVATION_THRESHOLD,
+            finished_task_limit: DEFAULT_FINISHED_TASK_LIMIT,
+        }
+    }
+
+    /// Boost long-waiting tasks according to `policy` when picking the next task
+    pub fn with_aging(mut self, policy: AgingPolicy) -> Self {
+        self.aging = Some(policy);
+        self
+    }
+
+    /// Report queued tasks once they have waited longer than `threshold`
+    pub fn with_starvation_threshold(mut self, threshold: Duration) -> Self {
+        self.starvation_threshold = threshold;
+        self
+    }
+
+    /// Keep at most `limit` finished tasks, evicting the longest-finished
+    /// first
+    pub fn with_finished_task_limit(mut self, limit: usize) -> Self {
+        self.finished_task_limit = limit;
+        self
+    }
+
+    /// Move a task to a final status and evict finished tasks over the limit
+    fn finish(&self, tasks: &mut HashMap<TaskId, ScheduledTask>, id: TaskId, status: TaskStatus) {
+        if let Some(scheduled) = tasks.get_mut(&id) {
+            scheduled.summary.status = status;
+            scheduled.summary.finished_at = Some(SystemTime::now());
+        }
+
+        let mut finished: Vec<(Option<SystemTime>, TaskId)> = tasks
+            .values()
+            .filter(|scheduled| scheduled.summary.status.is_finished())
+            .map(|scheduled| (scheduled.summary.finished_at, scheduled.summary.id))
+            .collect();
+        if finished.len() <= self.finished_task_limit {
+            return;
+        }
+        finished.sort();
+        let excess = finished.len() - self.finished_task_limit;
+        for (_, id) in &finished[..excess] {
+            tasks.remove(id);
+        }
+    }
+
+    /// Urgency rank the scheduler picks by, where 0 is `Critical`
+    fn effective_rank(&self, summary: &TaskSummary, now: SystemTime) -> u32 {
+        match &self.aging {
+            Some(policy) => {
+                let waited = now.duration_since(summary.submitted_at).unwrap_or_default();
+                policy.rank(&summary.priority, waited)
+            }
+            None => rank(&summary.priority),
+        }
+    }
+}
+
+impl Default for MockScheduler {
+    fn default() -> Self {
+        Self::new(Arc::default())
+    }
 }
 
 #[async_trait]
 impl NpuScheduler for MockScheduler {
-    async fn submit_task(&self, task: InferenceTask) -> Result<TaskId> {
-        let id = self.tasks.read().await.len();
-        self.tasks.write().await.insert(id, TaskStatus::Queued);
+    async fn submit_task(&self, mut task: InferenceTask) -> Result<TaskId> {
+        let mut tasks = self.tasks.write().await;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let external_id = std::mem::replace(&mut task.id, id);
+        let summary = TaskSummary {
+            id,
+            external_id,
+            agent_id: task.request.agent_id,
+            device_id: task.resource_requirements.device_id.clone(),
+            priority: task.priority.clone(),
+            status: TaskStatus::Queued,
+            submitted_at: SystemTime::now(),
+            started_at: None,
+            finished_at: None,
+        };
+        tasks.insert(id, ScheduledTask { task, summary });
         Ok(id)
     }
 
     async fn cancel_task(&self, task_id: TaskId) -> Result<()> {
-        self.tasks.write().await.remove(&task_id);
+        let mut tasks = self.tasks.write().await;
+        let active = matches!(
+            tasks.get(&task_id),
+            Some(scheduled) if !scheduled.summary.status.is_finished()
+        );
+        if active {
+            self.finish(&mut tasks, task_id, TaskStatus::Cancelled);
+        }
         Ok(())
     }
 
     async fn get_task_status(&self, task_id: TaskId) -> Option<TaskStatus> {
-        self.tasks.read().await.get(&task_id).cloned()
+        self.tasks
+            .read()
+            .await
+            .get(&task_id)
+            .map(|scheduled| scheduled.summary.status.clone())
+    }
+
+    async fn list_tasks(&self, filter: TaskFilter) -> Vec<TaskSummary> {
+        let mut tasks: Vec<TaskSummary> = self
+            .tasks
+            .read()
+            .await
+            .values()
+            .map(|scheduled| &scheduled.summary)
+            .filter(|summary| filter.matches(summary))
+            .cloned()
+            .collect();
+        tasks.sort_by_key(|summary| summary.id);
+        tasks
+    }
+
+    async fn snapshot(&self) -> SchedulerSnapshot {
+        let mut tasks: Vec<ScheduledTask> = self.tasks.read().await.values().cloned().collect();
+        tasks.sort_by_key(|scheduled| scheduled.summary.id);
+        SchedulerSnapshot { tasks }
+    }
+
+    async fn restore(&self, snapshot: SchedulerSnapshot) -> Result<()> {
+        let mut tasks = self.tasks.write().await;
+        tasks.clear();
+        for mut scheduled in snapshot.tasks {
+            if scheduled.summary.status == TaskStatus::Running {
+                scheduled.summary.status = TaskStatus::Queued;
+                scheduled.summary.started_at = None;
+            }
+            self.next_id
+                .fetch_max(scheduled.summary.id + 1, Ordering::Relaxed);
+            tasks.insert(scheduled.summary.id, scheduled);
+        }
+        Ok(())
+    }
+
+    async fn run_next(&self) -> Option<(TaskId, Result<InferenceResponse>)> {
+        let limits: HashMap<NpuDeviceId, usize> = self
+            .devices
+            .read()
+            .await
+            .iter()
+            .map(|device| {
+                let limit = device.capabilities().max_concurrent_inferences();
+                (device.id(), limit as usize)
+            })
+            .collect();
+        let (id, task, queue_wait) = {
+            let mut tasks = self.tasks.write().await;
+            let mut running: HashMap<NpuDeviceId, usize> = HashMap::new();
+            for scheduled in tasks.values() {
+                if scheduled.summary.status == TaskStatus::Running {
+                    *running
+                        .entry(scheduled.summary.device_id.clone())
+                        .or_default() += 1;
+                }
+            }
+            let has_capacity = |device_id: &NpuDeviceId| {
+                limits.get(device_id).map_or(true, |&limit| {
+                    running.get(device_id).copied().unwrap_or(0) < limit
+                })
+            };
+            // Most urgent first, oldest first among equals
+            let now = SystemTime::now();
+            let scheduled = tasks
+                .values_mut()
+                .filter(|scheduled| scheduled.summary.status == TaskStatus::Queued)
+                .filter(|scheduled| has_capacity(&scheduled.summary.device_id))
+                .min_by_key(|scheduled| {
+                    (
+                        self.effective_rank(&scheduled.summary, now),
+                        scheduled.summary.id,
+                    )
+                })?;
+            let started_at = SystemTime::now();
+            scheduled.summary.status = TaskStatus::Running;
+            scheduled.summary.started_at = Some(started_at);
+            let queue_wait = started_at
+                .duration_since(scheduled.summary.submitted_at)
+                .unwrap_or_default();
+            (scheduled.summary.id, scheduled.task.clone(), queue_wait)
+        };
+
+        let device_id = &task.resource_requirements.device_id;
+        let device = self
+            .devices
+            .read()
+            .await
+            .iter()
+            .find(|device| device.id() == *device_id)
+            .cloned();
+        let result = match device {
+            Some(device) => {
+                let mut request = task.request;
+                let choice = match task.scheduling_hints.auto_precision {
+                    Some(preference) => {
+                        apply_auto_precision(&mut request, &device.capabilities(), preference)
+                    }
+                    None => Ok(None),
+                };
+                match choice {
+                    Ok(choice) => device.execute_inference(request).await.map(|response| {
+                        let response = response
+                            .with_metadata(metadata_keys::QUEUE_WAIT_MS, queue_wait.as_millis());
+                        match choice {
+                            Some(choice) => choice.annotate(response),
+                            None => response,
+                        }
+                    }),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            None => Err(NpuError::DeviceUnavailable(format!("no device {}", device_id)).into()),
+        };
+
+        // A task cancelled while it ran stays cancelled
+        let mut tasks = self.tasks.write().await;
+        let running = matches!(
+            tasks.get(&id),
+            Some(scheduled) if scheduled.summary.status == TaskStatus::Running
+        );
+        if running {
+            let status = match &result {
+                Ok(_) => TaskStatus::Completed,
+                Err(e) => TaskStatus::Failed(e.to_string()),
+            };
+            self.finish(&mut tasks, id, status);
+        }
+        Some((id, result))
+    }
+
+    async fn starvation_report(&self) -> Vec<(TaskId, Duration)> {
+        let now = SystemTime::now();
+        let mut starving: Vec<(TaskId, Duration)> = self
+            .tasks
+            .read()
+            .await
+            .values()
+            .filter(|scheduled| scheduled.summary.status == TaskStatus::Queued)
+            .map(|scheduled| {
+                let waited = now
+                    .duration_since(scheduled.summary.submitted_at)
+                    .unwrap_or_default();
+                (scheduled.summary.id, waited)
+            })
+            .filter(|(_, waited)| *waited > self.starvation_threshold)
+            .collect();
+        starving.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        starving
     }
 
     async fn get_usage_stats(&self) -> NpuUsageStats {
-        let tasks = self.tasks.read().await.len();
+        let now = SystemTime::now();
+        let mut queued_tasks = 0;
+        let mut task_times = Vec::new();
+        for scheduled in self.tasks.read().await.values() {
+            let summary = &scheduled.summary;
+            if !summary.status.is_finished() {
+                queued_tasks += 1;
+                continue;
+            }
+            if summary.status != TaskStatus::Completed {
+                continue;
+            }
+            let (Some(started_at), Some(finished_at)) = (summary.started_at, summary.finished_at)
+            else {
+                continue;
+            };
+            let age = now.duration_since(finished_at).unwrap_or_default();
+            if age <= COMPLETION_WINDOW {
+                task_times.push(finished_at.duration_since(started_at).unwrap_or_default());
+            }
+        }
+        let average_task_time = if task_times.is_empty() {
+            Duration::ZERO
+        } else {
+            task_times.iter().sum::<Duration>() / task_times.len() as u32
+        };
+
+        let devices = self.devices.read().await.clone();
+        let mut utilization = Vec::new();
+        let mut power = Vec::new();
+        for device in &devices {
+            if !device.is_available().await {
+                continue;
+            }
+            utilization.push(device.get_utilization().await);
+            if let Ok(health) = device.get_health().await {
+                power.push(health.power_consumption_watts as f64);
+            }
+        }
         NpuUsageStats {
-            total_devices: 1,
-            active_devices: 0,
-            compute_utilization: 0.0,
+            total_devices: devices.len(),
+            active_devices: utilization.len(),
+            compute_utilization: mean(&utilization).clamp(0.0, 1.0),
             memory_utilization: 0.0,
-            power_consumption_watts: 0.0,
-            tasks_completed_last_minute: tasks as u64,
-            average_task_time: std::time::Duration::from_secs(0),
-            queued_tasks: tasks as usize,
+            power_consumption_watts: mean(&power),
+            tasks_completed_last_minute: task_times.len() as u64,
+            average_task_time,
+            queued_tasks,
+        }
+    }
+}
+
+/// Precision automatic selection ran a request at
+struct PrecisionChoice {
+    data_type: DataType,
+    /// Int8 quantization scale of each input, in input order
+    quant_scales: Vec<Option<f32>>,
+}
+
+impl PrecisionChoice {
+    /// `metadata_keys::QUANT_SCALE` value, if any input was quantized
+    fn quant_scale_metadata(&self) -> Option<String> {
+        if self.quant_scales.iter().all(Option::is_none) {
+            return None;
+        }
+        let scales: Vec<String> = self
+            .quant_scales
+            .iter()
+            .map(|scale| scale.map(|s| s.to_string()).unwrap_or_default())
+            .collect();
+        Some(scales.join(","))
+    }
+
+    fn annotate(self, response: InferenceResponse) -> InferenceResponse {
+        let response =
+            response.with_metadata(metadata_keys::PRECISION, format!("{:?}", self.data_type));
+        match self.quant_scale_metadata() {
+            Some(scales) => response.with_metadata(metadata_keys::QUANT_SCALE, scales),
+            None => response,
+        }
+    }
+}
+
+/// Convert the request's Float32 inputs to the device's preferred
+/// precision. Inputs of other types are left as submitted, and nothing
+/// changes if the device supports no precision Float32 converts to. The
+/// int8 scales are recorded in the request's metadata for the device.
+fn apply_auto_precision(
+    request: &mut InferenceRequest,
+    capabilities: &NpuCapabilities,
+    preference: PrecisionPreference,
+) -> Result<Option<PrecisionChoice>, NpuError> {
+    let Some(data_type) = capabilities.preferred_precision(preference) else {
+        return Ok(None);
+    };
+
+    let mut quant_scales = Vec::with_capacity(request.inputs.len());
+    for input in &mut request.inputs {
+        if *input.data_type() != DataType::Float32 {
+            quant_scales.push(None);
+            continue;
+        }
+        let (converted, scale) = input.to_precision(data_type.clone())?;
+        *input = converted;
+        quant_scales.push(scale);
+    }
+    let choice = PrecisionChoice {
+        data_type,
+        quant_scales,
+    };
+    if let Some(scales) = choice.quant_scale_metadata() {
+        request
+            .metadata
+            .insert(metadata_keys::QUANT_SCALE.to_string(), scales);
+    }
+    Ok(Some(choice))
+}
+
+/// Mean of the finite values, or 0.0 if there are none
+fn mean(values: &[f64]) -> f64 {
+    let finite: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if finite.is_empty() {
+        0.0
+    } else {
+        finite.iter().sum::<f64>() / finite.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::npu::mock::MockNpuDevice;
+    use crate::npu::{
+        DataType, InferenceRequest, NpuCapabilities, ResourceAllocation, SchedulingHints, Tensor,
+    };
+    use crate::types::AgentId;
+
+    fn test_task(agent_id: AgentId) -> InferenceTask {
+        InferenceTask {
+            id: 0,
+            request: InferenceRequest {
+                model_path: "model.onnx".to_string(),
+                inputs: vec![],
+                timeout: Duration::from_secs(1),
+                priority: TaskPriority::Normal,
+                agent_id: Some(agent_id),
+                metadata: HashMap::new(),
+            },
+            priority: TaskPriority::Normal,
+            resource_requirements: ResourceAllocation {
+                device_id: NpuDeviceId::new("mock-device"),
+                compute_units: vec![],
+                memory_bytes: 0,
+                power_budget_watts: 0.0,
+                timeout: Duration::from_secs(1),
+            },
+            scheduling_hints: SchedulingHints::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_by_agent() {
+        let scheduler = MockScheduler::default();
+        scheduler.submit_task(test_task(1)).await.unwrap();
+        scheduler.submit_task(test_task(2)).await.unwrap();
+        scheduler.submit_task(test_task(1)).await.unwrap();
+
+        let all = scheduler.list_tasks(TaskFilter::default()).await;
+        assert_eq!(all.len(), 3);
+
+        let agent_one = scheduler
+            .list_tasks(TaskFilter::default().with_agent(1))
+            .await;
+        assert_eq!(agent_one.len(), 2);
+        assert!(agent_one.iter().all(|t| t.agent_id == Some(1)));
+
+        let queued_for_two = scheduler
+            .list_tasks(
+                TaskFilter::default()
+                    .with_agent(2)
+                    .with_status(TaskStatus::Queued),
+            )
+            .await;
+        assert_eq!(queued_for_two.len(), 1);
+
+        let other_device = scheduler
+            .list_tasks(TaskFilter::default().with_device(NpuDeviceId::new("other")))
+            .await;
+        assert!(other_device.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_external_ids_get_distinct_task_ids() {
+        let scheduler = MockScheduler::default();
+        let mut first = test_task(1);
+        first.id = 7;
+        let mut second = test_task(2);
+        second.id = 7;
+
+        let first_id = scheduler.submit_task(first).await.unwrap();
+        let second_id = scheduler.submit_task(second).await.unwrap();
+        assert_ne!(first_id, second_id);
+
+        scheduler.cancel_task(first_id).await.unwrap();
+        assert_eq!(
+            scheduler.get_task_status(first_id).await,
+            Some(TaskStatus::Cancelled)
+        );
+        assert_eq!(
+            scheduler.get_task_status(second_id).await,
+            Some(TaskStatus::Queued)
+        );
+        // The caller's id is not a handle to either task
+        assert_eq!(scheduler.get_task_status(7).await, None);
+
+        let summaries = scheduler.list_tasks(TaskFilter::default()).await;
+        assert!(summaries.iter().all(|summary| summary.external_id == 7));
+        let snapshot = scheduler.snapshot().await;
+        let stored: Vec<TaskId> = snapshot.tasks.iter().map(|s| s.task.id).collect();
+        assert_eq!(stored, vec![first_id, second_id]);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_agent_tasks() {
+        let manager = crate::npu::init_mock_npu_subsystem().await.unwrap();
+
+        let mut agent_tasks = Vec::new();
+        for _ in 0..3 {
+            agent_tasks.push(manager.submit_task(test_task(1)).await.unwrap());
+        }
+        let other_task = manager.submit_task(test_task(2)).await.unwrap();
+
+        assert_eq!(manager.cancel_agent_tasks(1).await.unwrap(), 3);
+        for task_id in agent_tasks {
+            assert_eq!(
+                manager.get_task_status(task_id).await,
+                Some(TaskStatus::Cancelled)
+            );
         }
+        assert_eq!(
+            manager.get_task_status(other_task).await,
+            Some(TaskStatus::Queued)
+        );
+
+        // Already-cancelled tasks are not counted again
+        assert_eq!(manager.cancel_agent_tasks(1).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restore_requeues_tasks() {
+        let scheduler = MockScheduler::default();
+        for agent_id in [1, 2, 3] {
+            scheduler.submit_task(test_task(agent_id)).await.unwrap();
+        }
+        scheduler.cancel_task(1).await.unwrap();
+        scheduler
+            .tasks
+            .write()
+            .await
+            .get_mut(&2)
+            .unwrap()
+            .summary
+            .status = TaskStatus::Running;
+
+        let json = serde_json::to_string(&scheduler.snapshot().await).unwrap();
+        let snapshot: SchedulerSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot.tasks.len(), 3);
+
+        let restored = MockScheduler::default();
+        restored.restore(snapshot).await.unwrap();
+
+        assert_eq!(restored.get_task_status(0).await, Some(TaskStatus::Queued));
+        assert_eq!(
+            restored.get_task_status(1).await,
+            Some(TaskStatus::Cancelled)
+        );
+        assert_eq!(restored.get_task_status(2).await, Some(TaskStatus::Queued));
+
+        let queued = restored
+            .list_tasks(TaskFilter::default().with_status(TaskStatus::Queued))
+            .await;
+        let agents: Vec<_> = queued.iter().map(|t| t.agent_id).collect();
+        assert_eq!(agents, vec![Some(1), Some(3)]);
+
+        let task = &restored.snapshot().await.tasks[2].task;
+        assert_eq!(task.request.model_path, "model.onnx");
+
+        // New submissions continue after the restored ids
+        assert_eq!(restored.submit_task(test_task(4)).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_usage_stats_count_live_and_recent_tasks() {
+        let device = Arc::new(MockNpuDevice::new().await.unwrap());
+        let devices: Vec<Arc<dyn NpuDevice + Send + Sync>> = vec![device.clone()];
+        let scheduler =
+            MockScheduler::new(Arc::new(RwLock::new(devices))).with_finished_task_limit(2);
+
+        let mut ids = Vec::new();
+        for agent_id in 0..5 {
+            let mut task = test_task(agent_id);
+            task.request.inputs = vec![Tensor::from_f32(&[0.0], vec![1, 1]).unwrap()];
+            task.resource_requirements.device_id = device.id();
+            ids.push(scheduler.submit_task(task).await.unwrap());
+        }
+        for _ in 0..3 {
+            let (_, result) = scheduler.run_next().await.unwrap();
+            assert!(result.is_ok());
+        }
+        scheduler.cancel_task(ids[3]).await.unwrap();
+
+        let stats = scheduler.get_usage_stats().await;
+        assert_eq!(stats.queued_tasks, 1);
+        // The oldest completion was evicted to stay within the limit
+        assert_eq!(stats.tasks_completed_last_minute, 1);
+        assert_eq!(scheduler.get_task_status(ids[0]).await, None);
+        assert_eq!(scheduler.get_task_status(ids[1]).await, None);
+        assert_eq!(
+            scheduler.get_task_status(ids[2]).await,
+            Some(TaskStatus::Completed)
+        );
+        assert_eq!(
+            scheduler.get_task_status(ids[3]).await,
+            Some(TaskStatus::Cancelled)
+        );
+
+        // Evicted ids are not handed out again
+        let next = scheduler.submit_task(test_task(9)).await.unwrap();
+        assert_eq!(next, ids[4] + 1);
+        // Finished tasks cannot be cancelled after the fact
+        scheduler.cancel_task(ids[2]).await.unwrap();
+        assert_eq!(
+            scheduler.get_task_status(ids[2]).await,
+            Some(TaskStatus::Completed)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_next_respects_device_concurrency_limit() {
+        let mut capabilities = NpuCapabilities::default();
+        capabilities.compute.max_concurrent_inferences = 2;
+        let device = Arc::new(
+            MockNpuDevice::new()
+                .await
+                .unwrap()
+                .with_capabilities(capabilities)
+                .with_latency(Duration::from_millis(50), Duration::ZERO),
+        );
+        let devices: Vec<Arc<dyn NpuDevice + Send + Sync>> = vec![device.clone()];
+        let scheduler = MockScheduler::new(Arc::new(RwLock::new(devices)));
+
+        for agent_id in 0..5 {
+            let mut task = test_task(agent_id);
+            task.request.inputs = vec![Tensor::from_f32(&[0.0], vec![1, 1]).unwrap()];
+            task.resource_requirements.device_id = device.id();
+            scheduler.submit_task(task).await.unwrap();
+        }
+
+        // Five workers at once: two get the device, the rest find it full
+        let workers: Vec<_> = (0..5)
+            .map(|_| {
+                let scheduler = scheduler.clone();
+                tokio::spawn(async move { scheduler.run_next().await })
+            })
+            .collect();
+        let mut dispatched = 0;
+        for worker in workers {
+            if let Some((_, result)) = worker.await.unwrap() {
+                assert!(result.is_ok());
+                dispatched += 1;
+            }
+        }
+        assert_eq!(dispatched, 2);
+        let queued = scheduler
+            .list_tasks(TaskFilter::default().with_status(TaskStatus::Queued))
+            .await;
+        assert_eq!(queued.len(), 3);
+
+        while let Some((_, result)) = scheduler.run_next().await {
+            assert!(result.is_ok());
+        }
+        let completed = scheduler
+            .list_tasks(TaskFilter::default().with_status(TaskStatus::Completed))
+            .await;
+        assert_eq!(completed.len(), 5);
+        assert_eq!(device.peak_concurrency(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_next_reports_queue_wait() {
+        let device = MockNpuDevice::new()
+            .await
+            .unwrap()
+            .with_latency(Duration::from_millis(50), Duration::ZERO);
+        let device_id = device.id();
+        let devices: Vec<Arc<dyn NpuDevice + Send + Sync>> = vec![Arc::new(device)];
+        let scheduler = MockScheduler::new(Arc::new(RwLock::new(devices)));
+
+        let mut ids = Vec::new();
+        for agent_id in [1, 2] {
+            let mut task = test_task(agent_id);
+            // The mock device echoes the first input
+            task.request.inputs = vec![Tensor::from_f32(&[0.0], vec![1, 1]).unwrap()];
+            task.resource_requirements.device_id = device_id.clone();
+            ids.push(scheduler.submit_task(task).await.unwrap());
+        }
+
+        let (first_id, first) = scheduler.run_next().await.unwrap();
+        let (second_id, second) = scheduler.run_next().await.unwrap();
+        assert_eq!(vec![first_id, second_id], ids);
+        assert!(scheduler.run_next().await.is_none());
+
+        // The second task waited while the first ran
+        assert!(first.unwrap().queue_wait().unwrap() < Duration::from_millis(25));
+        assert!(second.unwrap().queue_wait().unwrap() >= Duration::from_millis(50));
+        for id in ids {
+            assert_eq!(
+                scheduler.get_task_status(id).await,
+                Some(TaskStatus::Completed)
+            );
+        }
+        let started: Vec<_> = scheduler
+            .list_tasks(TaskFilter::default())
+            .await
+            .iter()
+            .map(|summary| summary.started_at.unwrap())
+            .collect();
+        assert!(started[0] < started[1]);
+    }
+
+    async fn stream_until_low_runs(scheduler: &MockScheduler, device_id: &NpuDeviceId) -> bool {
+        let submit = |priority: TaskPriority| {
+            let mut task = test_task(1);
+            task.priority = priority;
+            task.request.inputs = vec![Tensor::from_f32(&[0.0], vec![1, 1]).unwrap()];
+            task.resource_requirements.device_id = device_id.clone();
+            scheduler.submit_task(task)
+        };
+
+        // Keep a backlog of high-priority work topped up as tasks finish
+        for _ in 0..3 {
+            submit(TaskPriority::High).await.unwrap();
+        }
+        let low = submit(TaskPriority::Low).await.unwrap();
+        for _ in 0..30 {
+            submit(TaskPriority::High).await.unwrap();
+            let (id, result) = scheduler.run_next().await.unwrap();
+            assert!(result.is_ok());
+            if id == low {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_aging_prevents_starvation() {
+        let device = MockNpuDevice::new()
+            .await
+            .unwrap()
+            .with_latency(Duration::from_millis(5), Duration::ZERO);
+        let device_id = device.id();
+        let devices: Vec<Arc<dyn NpuDevice + Send + Sync>> = vec![Arc::new(device)];
+        let devices = Arc::new(RwLock::new(devices));
+
+        // Strict priority never gets to the low-priority task, which shows up
+        // in the report instead
+        let strict = MockScheduler::new(devices.clone())
+            .with_starvation_threshold(Duration::from_millis(50));
+        assert!(!stream_until_low_runs(&strict, &device_id).await);
+        let starving = strict.starvation_report().await;
+        assert_eq!(starving[0].0, 3);
+        assert!(starving[0].1 > Duration::from_millis(50));
+        assert!(starving.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        let aging =
+            MockScheduler::new(devices).with_aging(AgingPolicy::new(Duration::from_millis(20)));
+        assert!(stream_until_low_runs(&aging, &device_id).await);
+        assert!(aging.starvation_report().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_next_prefers_urgent_tasks() {
+        let device = MockNpuDevice::new().await.unwrap();
+        let device_id = device.id();
+        let devices: Vec<Arc<dyn NpuDevice + Send + Sync>> = vec![Arc::new(device)];
+        let scheduler = MockScheduler::new(Arc::new(RwLock::new(devices)));
+
+        for priority in [
+            TaskPriority::Background,
+            TaskPriority::Normal,
+            TaskPriority::Critical,
+            TaskPriority::Normal,
+        ] {
+            let mut task = test_task(1);
+            task.priority = priority;
+            task.request.inputs = vec![Tensor::from_f32(&[0.0], vec![1, 1]).unwrap()];
+            task.resource_requirements.device_id = device_id.clone();
+            scheduler.submit_task(task).await.unwrap();
+        }
+
+        let mut order = Vec::new();
+        while let Some((id, _)) = scheduler.run_next().await {
+            order.push(id);
+        }
+        assert_eq!(order, vec![2, 1, 3, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_auto_precision_quantizes_for_int8_device() {
+        let mut capabilities = NpuCapabilities::default();
+        capabilities.compute.supported_data_types = vec![DataType::Int8];
+        let device = MockNpuDevice::new()
+            .await
+            .unwrap()
+            .with_capabilities(capabilities);
+        let device_id = device.id();
+        let devices: Vec<Arc<dyn NpuDevice + Send + Sync>> = vec![Arc::new(device)];
+        let scheduler = MockScheduler::new(Arc::new(RwLock::new(devices)));
+
+        for preference in [PrecisionPreference::Speed, PrecisionPreference::Accuracy] {
+            let mut task = test_task(1);
+            task.request.inputs =
+                vec![Tensor::from_f32(&[63.0, -127.0, 32.0], vec![1, 3]).unwrap()];
+            task.resource_requirements.device_id = device_id.clone();
+            task.scheduling_hints.auto_precision = Some(preference);
+            scheduler.submit_task(task).await.unwrap();
+
+            // Int8 is the only option whichever way the preference leans
+            let (_, result) = scheduler.run_next().await.unwrap();
+            let response = result.unwrap();
+            assert_eq!(response.precision(), Some("Int8"));
+            assert_eq!(response.quantized(), Some(true));
+            // The mock device echoes the converted input
+            let output = &response.outputs[0];
+            assert_eq!(*output.data_type(), DataType::Int8);
+            assert_eq!(output.view::<i8>().unwrap(), vec![63, -127, 32]);
+            assert_eq!(response.metadata[metadata_keys::QUANT_SCALE], "1");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auto_precision_records_scale_per_input() {
+        let mut capabilities = NpuCapabilities::default();
+        capabilities.compute.supported_data_types = vec![DataType::Int8];
+        let device = MockNpuDevice::new()
+            .await
+            .unwrap()
+            .with_capabilities(capabilities);
+        let device_id = device.id();
+        let devices: Vec<Arc<dyn NpuDevice + Send + Sync>> = vec![Arc::new(device)];
+        let scheduler = MockScheduler::new(Arc::new(RwLock::new(devices)));
+
+        let mut task = test_task(1);
+        task.request.inputs = vec![
+            Tensor::from_f32(&[63.0, -127.0], vec![1, 2]).unwrap(),
+            Tensor::from_f32(&[254.0, -127.0], vec![1, 2]).unwrap(),
+        ];
+        task.resource_requirements.device_id = device_id;
+        task.scheduling_hints.auto_precision = Some(PrecisionPreference::Speed);
+        scheduler.submit_task(task).await.unwrap();
+
+        let (_, result) = scheduler.run_next().await.unwrap();
+        let response = result.unwrap();
+        assert_eq!(response.quant_scales(), Some(vec![Some(1.0), Some(2.0)]));
+        assert_eq!(response.outputs[1].view::<i8>().unwrap(), vec![127, -64]);
+
+        // Inputs left as submitted keep their place without a scale
+        let mut request = test_task(1).request;
+        request.inputs = vec![
+            Tensor::new(vec![5], vec![1], DataType::Int8).unwrap(),
+            Tensor::from_f32(&[-254.0], vec![1]).unwrap(),
+        ];
+        let choice = apply_auto_precision(
+            &mut request,
+            &NpuCapabilities::default(),
+            PrecisionPreference::Speed,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(choice.quant_scales, vec![None, Some(2.0)]);
+    }
+
+    #[test]
+    fn test_preferred_precision_follows_preference() {
+        let capabilities = NpuCapabilities::default();
+        assert_eq!(
+            capabilities.preferred_precision(PrecisionPreference::Speed),
+            Some(DataType::Int8)
+        );
+        assert_eq!(
+            capabilities.preferred_precision(PrecisionPreference::Accuracy),
+            Some(DataType::Float32)
+        );
+
+        let mut request = test_task(1).request;
+        request.inputs = vec![Tensor::from_f32(&[1.5], vec![1]).unwrap()];
+        let choice =
+            apply_auto_precision(&mut request, &capabilities, PrecisionPreference::Accuracy)
+                .unwrap()
+                .unwrap();
+        assert_eq!(choice.data_type, DataType::Float32);
+        assert_eq!(request.inputs[0].view::<f32>().unwrap(), vec![1.5]);
     }
 }
@@ -7,7 +7,8 @@ use std::time::{Duration, SystemTime};
 #[cfg(feature = "npu")]
 use uuid::Uuid;
 
-use crate::types::{AgentId, TaskId};
+use crate::precision::{self, QuantParams};
+use crate::types::{AgentId, InferencePriority, Precision, TaskId};
 use serde::{Deserialize, Serialize};
 
 /// Unique identifier for NPU devices
@@ -58,6 +59,41 @@ pub enum NpuDeviceType {
     Unknown(String),
 }
 
+impl std::fmt::Display for NpuDeviceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NpuDeviceType::AppleNeuralEngine => write!(f, "AppleNeuralEngine"),
+            NpuDeviceType::IntelNpu => write!(f, "IntelNpu"),
+            NpuDeviceType::NvidiaGpu => write!(f, "NvidiaGpu"),
+            NpuDeviceType::AmdGpu => write!(f, "AmdGpu"),
+            NpuDeviceType::QualcommHexagon => write!(f, "QualcommHexagon"),
+            NpuDeviceType::GoogleEdgeTpu => write!(f, "GoogleEdgeTpu"),
+            NpuDeviceType::CpuFallback => write!(f, "CpuFallback"),
+            NpuDeviceType::Mock => write!(f, "Mock"),
+            NpuDeviceType::Unknown(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl std::str::FromStr for NpuDeviceType {
+    type Err = std::convert::Infallible;
+
+    /// Parse a name produced by `Display`; unrecognized names become `Unknown`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "AppleNeuralEngine" => NpuDeviceType::AppleNeuralEngine,
+            "IntelNpu" => NpuDeviceType::IntelNpu,
+            "NvidiaGpu" => NpuDeviceType::NvidiaGpu,
+            "AmdGpu" => NpuDeviceType::AmdGpu,
+            "QualcommHexagon" => NpuDeviceType::QualcommHexagon,
+            "GoogleEdgeTpu" => NpuDeviceType::GoogleEdgeTpu,
+            "CpuFallback" => NpuDeviceType::CpuFallback,
+            "Mock" => NpuDeviceType::Mock,
+            other => NpuDeviceType::Unknown(other.to_string()),
+        })
+    }
+}
+
 /// NPU device vendor
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NpuVendor {
@@ -166,6 +202,31 @@ impl Default for TaskPriority {
     }
 }
 
+/// Maps SDK priorities onto NPU priorities: `Realtime` becomes `Critical`,
+/// the others keep their name. No SDK priority maps to `Background`.
+impl From<InferencePriority> for TaskPriority {
+    fn from(priority: InferencePriority) -> Self {
+        match priority {
+            InferencePriority::Realtime => TaskPriority::Critical,
+            InferencePriority::High => TaskPriority::High,
+            InferencePriority::Normal => TaskPriority::Normal,
+            InferencePriority::Low => TaskPriority::Low,
+        }
+    }
+}
+
+/// Inverse of the SDK mapping; `Background` folds into `Low`.
+impl From<TaskPriority> for InferencePriority {
+    fn from(priority: TaskPriority) -> Self {
+        match priority {
+            TaskPriority::Critical => InferencePriority::Realtime,
+            TaskPriority::High => InferencePriority::High,
+            TaskPriority::Normal => InferencePriority::Normal,
+            TaskPriority::Low | TaskPriority::Background => InferencePriority::Low,
+        }
+    }
+}
+
 /// Task execution state
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TaskStatus {
@@ -183,6 +244,13 @@ pub enum TaskStatus {
     TimedOut,
 }
 
+impl TaskStatus {
+    /// Whether the task has reached a final state and will not run again
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, TaskStatus::Queued | TaskStatus::Running)
+    }
+}
+
 /// Resource allocation for a task
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceAllocation {
@@ -222,24 +290,214 @@ pub struct ModelInfo {
     pub model_size_bytes: u64,
 }
 
-/// Inference input data
-#[derive(Debug, Clone)]
-pub struct InferenceInput {
-    pub data: Vec<u8>,
-    pub shape: Vec<u64>,
-    pub data_type: DataType,
+/// Tensor data whose length always matches its shape and element type
+///
+/// Elements are stored little-endian. The data is shared so cloning a
+/// request doesn't copy the tensor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TensorParts", into = "TensorParts")]
+pub struct Tensor {
+    data: Arc<[u8]>,
+    shape: Vec<u64>,
+    data_type: DataType,
+}
+
+/// Unchecked serialized form of a `Tensor`
+#[derive(Serialize, Deserialize)]
+struct TensorParts {
+    data: Arc<[u8]>,
+    shape: Vec<u64>,
+    data_type: DataType,
+}
+
+impl Tensor {
+    /// Bundle `data` with its shape and element type, failing unless the
+    /// data holds exactly `product(shape)` elements
+    pub fn new(
+        data: impl Into<Arc<[u8]>>,
+        shape: Vec<u64>,
+        data_type: DataType,
+    ) -> Result<Self, NpuError> {
+        let data = data.into();
+        let expected = shape
+            .iter()
+            .try_fold(data_type.size() as u64, |len, &dim| len.checked_mul(dim));
+        if expected != Some(data.len() as u64) {
+            return Err(NpuError::InvalidTensor(format!(
+                "{} bytes do not hold a {:?} tensor of shape {:?}",
+                data.len(),
+                data_type,
+                shape
+            )));
+        }
+        Ok(Self {
+            data,
+            shape,
+            data_type,
+        })
+    }
+
+    /// Float32 tensor of `values` with the given shape
+    pub fn from_f32(values: &[f32], shape: Vec<u64>) -> Result<Self, NpuError> {
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        Self::new(data, shape, DataType::Float32)
+    }
+
+    /// Raw little-endian element bytes
+    pub fn data(&self) -> &Arc<[u8]> {
+        &self.data
+    }
+
+    pub fn shape(&self) -> &[u64] {
+        &self.shape
+    }
+
+    pub fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    /// Number of elements
+    pub fn len(&self) -> usize {
+        self.data.len() / self.data_type.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Convert a Float32 tensor to Float16 or Int8. Int8 uses symmetric
+    /// quantization scaled to the largest magnitude, returned alongside.
+    pub fn to_precision(&self, data_type: DataType) -> Result<(Self, Option<f32>), NpuError> {
+        if data_type == self.data_type {
+            return Ok((self.clone(), None));
+        }
+        let values: Vec<f32> = self.view()?;
+        match data_type {
+            DataType::Float16 => {
+                let data: Vec<u8> = precision::f32_to_f16(&values)
+                    .iter()
+                    .flat_map(|bits| bits.to_le_bytes())
+                    .collect();
+                Ok((Self::new(data, self.shape.clone(), data_type)?, None))
+            }
+            DataType::Int8 => {
+                let max = values
+                    .iter()
+                    .fold(0.0f32, |max, value| max.max(value.abs()));
+                let scale = if max > 0.0 { max / i8::MAX as f32 } else { 1.0 };
+                let data: Vec<u8> = precision::f32_to_int8(&values, QuantParams::new(scale, 0))
+                    .into_iter()
+                    .map(|q| q as u8)
+                    .collect();
+                Ok((Self::new(data, self.shape.clone(), data_type)?, Some(scale)))
+            }
+            other => Err(NpuError::InvalidTensor(format!(
+                "cannot convert {:?} tensor to {:?}",
+                self.data_type, other
+            ))),
+        }
+    }
+
+    /// Decode the elements as `f32` whatever the tensor's precision. Int8
+    /// values are multiplied by `scale`, or left as their integer values if
+    /// there is none.
+    pub fn dequantize(&self, scale: Option<f32>) -> Result<Vec<f32>, NpuError> {
+        match self.data_type {
+            DataType::Float32 => self.view(),
+            DataType::Float16 => {
+                let bits: Vec<u16> = self
+                    .data
+                    .chunks_exact(2)
+                    .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                    .collect();
+                Ok(precision::f16_to_f32(&bits))
+            }
+            DataType::Int8 => {
+                let params = QuantParams::new(scale.unwrap_or(1.0), 0);
+                Ok(precision::int8_to_f32(&self.view::<i8>()?, params))
+            }
+            ref other => Err(NpuError::InvalidTensor(format!(
+                "cannot dequantize {:?} tensor",
+                other
+            ))),
+        }
+    }
+
+    /// Decode the elements as `T`, failing if `T` is not the tensor's type
+    pub fn view<T: TensorElement>(&self) -> Result<Vec<T>, NpuError> {
+        if self.data_type != T::DATA_TYPE {
+            return Err(NpuError::InvalidTensor(format!(
+                "cannot view {:?} tensor as {:?}",
+                self.data_type,
+                T::DATA_TYPE
+            )));
+        }
+        Ok(self
+            .data
+            .chunks_exact(self.data_type.size())
+            .map(T::from_le_bytes)
+            .collect())
+    }
+}
+
+impl TryFrom<TensorParts> for Tensor {
+    type Error = NpuError;
+
+    fn try_from(parts: TensorParts) -> Result<Self, NpuError> {
+        Self::new(parts.data, parts.shape, parts.data_type)
+    }
+}
+
+impl From<Tensor> for TensorParts {
+    fn from(tensor: Tensor) -> Self {
+        Self {
+            data: tensor.data,
+            shape: tensor.shape,
+            data_type: tensor.data_type,
+        }
+    }
+}
+
+/// Rust types a `Tensor` can be viewed as
+pub trait TensorElement: Sized {
+    const DATA_TYPE: DataType;
+
+    /// Decode one element from its `DataType::size()` little-endian bytes
+    fn from_le_bytes(bytes: &[u8]) -> Self;
 }
 
-/// Inference output data
-#[derive(Debug, Clone)]
-pub struct InferenceOutput {
-    pub data: Vec<u8>,
-    pub shape: Vec<u64>,
-    pub data_type: DataType,
+macro_rules! tensor_element {
+    ($($ty:ty => $data_type:ident),* $(,)?) => {
+        $(
+            impl TensorElement for $ty {
+                const DATA_TYPE: DataType = DataType::$data_type;
+
+                fn from_le_bytes(bytes: &[u8]) -> Self {
+                    <$ty>::from_le_bytes(bytes.try_into().unwrap())
+                }
+            }
+        )*
+    };
 }
 
+tensor_element! {
+    f32 => Float32,
+    i8 => Int8,
+    i16 => Int16,
+    i32 => Int32,
+    u8 => UInt8,
+    u16 => UInt16,
+    u32 => UInt32,
+}
+
+/// Inference input tensor
+pub type InferenceInput = Tensor;
+
+/// Inference output tensor
+pub type InferenceOutput = Tensor;
+
 /// Inference request
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InferenceRequest {
     /// Model to use for inference
     pub model_path: String,
@@ -256,7 +514,7 @@ pub struct InferenceRequest {
 }
 
 /// Inference response
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InferenceResponse {
     /// Output tensors
     pub outputs: Vec<InferenceOutput>,
@@ -264,10 +522,135 @@ pub struct InferenceResponse {
     pub execution_time: Duration,
     /// Device used for inference
     pub device_id: NpuDeviceId,
-    /// Additional metadata
+    /// Additional metadata; see `metadata_keys` for the standard keys
     pub metadata: HashMap<String, String>,
 }
 
+/// Standard keys for `InferenceResponse::metadata`
+///
+/// Devices set the keys that apply to them; `queue_wait_ms` is left to
+/// schedulers, which know how long a task waited before dispatch.
+pub mod metadata_keys {
+    /// `NpuDeviceType` of the executing device, in its `Display` form
+    pub const DEVICE_TYPE: &str = "device_type";
+    /// Backend that ran the model, e.g. "CoreML" or "CPU"
+    pub const EXECUTION_PROVIDER: &str = "execution_provider";
+    /// "true" if the inference ran on quantized (integer) data
+    pub const QUANTIZED: &str = "quantized";
+    /// "true" if the model was already loaded on the device
+    pub const CACHE_HIT: &str = "cache_hit";
+    /// Milliseconds the task waited in a queue before execution
+    pub const QUEUE_WAIT_MS: &str = "queue_wait_ms";
+    /// `DataType` automatic precision selection ran the inputs at
+    pub const PRECISION: &str = "precision";
+    /// Scales of the symmetric int8 quantization applied to each input,
+    /// comma-separated in input order; empty for inputs not quantized.
+    /// Set on both the request handed to the device and its response.
+    pub const QUANT_SCALE: &str = "quant_scale";
+}
+
+/// Parse a `metadata_keys::QUANT_SCALE` entry
+fn parse_quant_scales(metadata: &HashMap<String, String>) -> Option<Vec<Option<f32>>> {
+    let scales = metadata.get(metadata_keys::QUANT_SCALE)?;
+    Some(scales.split(',').map(|scale| scale.parse().ok()).collect())
+}
+
+impl InferenceRequest {
+    /// Int8 quantization scale of each input, `None` for inputs that were
+    /// not quantized
+    pub fn quant_scales(&self) -> Option<Vec<Option<f32>>> {
+        parse_quant_scales(&self.metadata)
+    }
+}
+
+impl InferenceResponse {
+    /// Set a metadata entry
+    pub fn with_metadata(mut self, key: &str, value: impl ToString) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Type of the device that produced this response
+    pub fn device_type(&self) -> Option<NpuDeviceType> {
+        self.metadata
+            .get(metadata_keys::DEVICE_TYPE)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// Backend that ran the model
+    pub fn execution_provider(&self) -> Option<&str> {
+        self.metadata
+            .get(metadata_keys::EXECUTION_PROVIDER)
+            .map(String::as_str)
+    }
+
+    /// Whether the inference ran on quantized data
+    pub fn quantized(&self) -> Option<bool> {
+        self.metadata
+            .get(metadata_keys::QUANTIZED)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// Whether the model was already loaded on the device
+    pub fn cache_hit(&self) -> Option<bool> {
+        self.metadata
+            .get(metadata_keys::CACHE_HIT)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// Time the task spent queued before execution
+    pub fn queue_wait(&self) -> Option<Duration> {
+        self.metadata
+            .get(metadata_keys::QUEUE_WAIT_MS)
+            .and_then(|value| value.parse().ok())
+            .map(Duration::from_millis)
+    }
+
+    /// Precision chosen by automatic precision selection, e.g. "Int8"
+    pub fn precision(&self) -> Option<&str> {
+        self.metadata
+            .get(metadata_keys::PRECISION)
+            .map(String::as_str)
+    }
+
+    /// Int8 quantization scale of each input, `None` for inputs that were
+    /// not quantized
+    pub fn quant_scales(&self) -> Option<Vec<Option<f32>>> {
+        parse_quant_scales(&self.metadata)
+    }
+}
+
+impl DataType {
+    /// Bytes per element
+    pub fn size(&self) -> usize {
+        match self {
+            DataType::Int8 | DataType::UInt8 | DataType::Bool => 1,
+            DataType::Float16 | DataType::BFloat16 | DataType::Int16 | DataType::UInt16 => 2,
+            DataType::Float32 | DataType::Int32 | DataType::UInt32 => 4,
+        }
+    }
+
+    /// Whether this is an integer type used for quantized models
+    pub fn is_quantized(&self) -> bool {
+        matches!(
+            self,
+            DataType::Int8 | DataType::UInt8 | DataType::Int16 | DataType::UInt16
+        )
+    }
+}
+
+/// Tensor element type for a model precision. INT4 values are held
+/// unpacked, one per `Int8` element.
+impl From<Precision> for DataType {
+    fn from(precision: Precision) -> Self {
+        match precision {
+            Precision::FP32 => DataType::Float32,
+            Precision::FP16 => DataType::Float16,
+            Precision::INT8 | Precision::INT4 => DataType::Int8,
+        }
+    }
+}
+
 /// NPU usage statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NpuUsageStats {
@@ -285,7 +668,7 @@ pub struct NpuUsageStats {
     pub tasks_completed_last_minute: u64,
     /// Average task execution time
     pub average_task_time: Duration,
-    /// Number of queued tasks
+    /// Number of tasks queued or running
     pub queued_tasks: usize,
 }
 
@@ -338,12 +721,18 @@ pub enum NpuError {
 
     #[error("Configuration error: {0}")]
     ConfigError(String),
+
+    #[error("Invalid tensor: {0}")]
+    InvalidTensor(String),
 }
 
 /// Inference task that can be submitted to the scheduler
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InferenceTask {
-    /// Unique task identifier
+    /// Caller's reference for the task. The scheduler owns task ids: it
+    /// assigns the authoritative `TaskId` returned by `submit_task`, keeps
+    /// this value only as `TaskSummary::external_id`, and never looks tasks
+    /// up by it.
     pub id: TaskId,
     /// Inference request
     pub request: InferenceRequest,
@@ -368,4 +757,158 @@ pub struct SchedulingHints {
     pub min_tops: Option<f64>,
     /// Maximum acceptable latency
     pub max_latency: Option<Duration>,
+    /// Convert Float32 inputs to the device's preferred precision before
+    /// running; `None` leaves inputs as submitted
+    #[serde(default)]
+    pub auto_precision: Option<PrecisionPreference>,
+}
+
+/// Trade-off used when picking a precision automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrecisionPreference {
+    /// The most exact precision the device supports
+    Accuracy,
+    /// The cheapest precision the device supports
+    Speed,
+}
+
+/// Selects tasks when listing the scheduler queue. Unset fields match any task.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskFilter {
+    /// Only tasks in this state
+    pub status: Option<TaskStatus>,
+    /// Only tasks submitted by this agent
+    pub agent_id: Option<AgentId>,
+    /// Only tasks targeting this device
+    pub device_id: Option<NpuDeviceId>,
+}
+
+impl TaskFilter {
+    pub fn with_status(mut self, status: TaskStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn with_agent(mut self, agent_id: AgentId) -> Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+
+    pub fn with_device(mut self, device_id: NpuDeviceId) -> Self {
+        self.device_id = Some(device_id);
+        self
+    }
+
+    /// Check whether a task summary satisfies this filter
+    pub fn matches(&self, summary: &TaskSummary) -> bool {
+        self.status.as_ref().map_or(true, |s| *s == summary.status)
+            && self.agent_id.map_or(true, |a| summary.agent_id == Some(a))
+            && self.device_id.as_ref().map_or(true, |d| *d == summary.device_id)
+    }
+}
+
+/// Scheduler-side view of a submitted task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskSummary {
+    /// Authoritative id assigned by the scheduler
+    pub id: TaskId,
+    /// Id the caller supplied in `InferenceTask::id`, which need not be unique
+    pub external_id: TaskId,
+    pub agent_id: Option<AgentId>,
+    pub device_id: NpuDeviceId,
+    pub priority: TaskPriority,
+    pub status: TaskStatus,
+    /// When the task was queued
+    pub submitted_at: SystemTime,
+    /// When the scheduler dispatched the task to its device
+    pub started_at: Option<SystemTime>,
+    /// When the task reached a final status
+    #[serde(default)]
+    pub finished_at: Option<SystemTime>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inference_request_json_round_trip() {
+        let request = InferenceRequest {
+            model_path: "models/classifier.onnx".to_string(),
+            inputs: vec![Tensor::new(vec![0, 0, 128, 63], vec![1, 1], DataType::Float32).unwrap()],
+            timeout: Duration::from_millis(1500),
+            priority: TaskPriority::High,
+            agent_id: Some(7),
+            metadata: HashMap::from([("trace_id".to_string(), "abc".to_string())]),
+        };
+
+        let json = serde_json::to_string(&request).unwrap();
+        let decoded: InferenceRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, request);
+    }
+
+    #[test]
+    fn test_precision_to_data_type() {
+        assert_eq!(DataType::from(Precision::FP32), DataType::Float32);
+        assert_eq!(DataType::from(Precision::FP16), DataType::Float16);
+        assert_eq!(DataType::from(Precision::INT8), DataType::Int8);
+        assert!(DataType::from(Precision::INT4).is_quantized());
+    }
+
+    #[test]
+    fn test_tensor_checks_length_against_shape() {
+        let tensor = Tensor::from_f32(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], vec![2, 3]).unwrap();
+        assert_eq!(tensor.shape(), &[2, 3]);
+        assert_eq!(tensor.len(), 6);
+        assert_eq!(
+            tensor.view::<f32>().unwrap(),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+        assert!(tensor.view::<i32>().is_err());
+
+        let int8 = Tensor::new(vec![1, 255], vec![2], DataType::Int8).unwrap();
+        assert_eq!(int8.view::<i8>().unwrap(), vec![1, -1]);
+
+        // Six floats are 24 bytes, not enough for 2x4 or right for 2x3 int16
+        let data: Vec<u8> = tensor.data().to_vec();
+        assert!(matches!(
+            Tensor::new(data.clone(), vec![2, 4], DataType::Float32),
+            Err(NpuError::InvalidTensor(_))
+        ));
+        assert!(Tensor::new(data, vec![2, 3], DataType::Int16).is_err());
+        assert!(Tensor::new(vec![0u8; 4], vec![u64::MAX, 2], DataType::Float32).is_err());
+
+        // Deserializing re-checks the invariant
+        let json = serde_json::to_string(&tensor).unwrap();
+        assert_eq!(serde_json::from_str::<Tensor>(&json).unwrap(), tensor);
+        let corrupt = json.replace("[2,3]", "[3,3]");
+        assert!(serde_json::from_str::<Tensor>(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_priority_mapping_round_trips() {
+        let sdk = [
+            InferencePriority::Low,
+            InferencePriority::Normal,
+            InferencePriority::High,
+            InferencePriority::Realtime,
+        ];
+        for priority in sdk.clone() {
+            let npu = TaskPriority::from(priority.clone());
+            assert_eq!(InferencePriority::from(npu), priority);
+        }
+
+        // Ordering is preserved: higher SDK priority never maps to a lower NPU one
+        let mapped: Vec<TaskPriority> = sdk.into_iter().map(TaskPriority::from).collect();
+        assert!(mapped.windows(2).all(|w| w[0] > w[1]));
+
+        assert_eq!(
+            TaskPriority::from(InferencePriority::Realtime),
+            TaskPriority::Critical
+        );
+        assert_eq!(
+            InferencePriority::from(TaskPriority::Background),
+            InferencePriority::Low
+        );
+    }
 }
@@ -0,0 +1,163 @@
+//! Precision conversion module for the NebulaOS Agent SDK
+//!
+//! Converts tensor data between `f32` and the reduced precisions listed in
+//! `Precision`, as a shared base for quantization code. Half-precision
+//! values are carried as their IEEE 754 binary16 bit patterns.
+
+use serde::{Deserialize, Serialize};
+
+/// Affine int8 quantization parameters: `real = (q - zero_point) * scale`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuantParams {
+    pub scale: f32,
+    pub zero_point: i8,
+}
+
+impl QuantParams {
+    pub fn new(scale: f32, zero_point: i8) -> Self {
+        Self { scale, zero_point }
+    }
+}
+
+/// Convert `f32` values to half-precision bit patterns, rounding to nearest even
+pub fn f32_to_f16(values: &[f32]) -> Vec<u16> {
+    values.iter().map(|&value| f32_to_f16_bits(value)).collect()
+}
+
+/// Convert half-precision bit patterns back to `f32`
+pub fn f16_to_f32(values: &[u16]) -> Vec<f32> {
+    values.iter().map(|&bits| f16_bits_to_f32(bits)).collect()
+}
+
+/// Quantize `f32` values to int8, saturating values outside the
+/// representable range
+pub fn f32_to_int8(values: &[f32], params: QuantParams) -> Vec<i8> {
+    values
+        .iter()
+        .map(|&value| {
+            let q = (value / params.scale).round() + f32::from(params.zero_point);
+            // `as` saturates and maps NaN to zero
+            q.clamp(i8::MIN as f32, i8::MAX as f32) as i8
+        })
+        .collect()
+}
+
+/// Dequantize int8 values back to `f32`
+pub fn int8_to_f32(values: &[i8], params: QuantParams) -> Vec<f32> {
+    values
+        .iter()
+        .map(|&q| (i32::from(q) - i32::from(params.zero_point)) as f32 * params.scale)
+        .collect()
+}
+
+fn f32_to_f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // Infinity and NaN, keeping NaNs quiet
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    if half_exponent <= 0 {
+        // Too small even for a subnormal half
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        let half = round_shift(mantissa, shift);
+        return sign | half as u16;
+    }
+
+    // A mantissa carry rolls over into the exponent, up to infinity
+    let half = ((half_exponent as u32) << 10) + round_shift(mantissa, 13);
+    sign | half as u16
+}
+
+/// Shift `value` right by `shift` bits, rounding half to even
+fn round_shift(value: u32, shift: u32) -> u32 {
+    let kept = value >> shift;
+    let remainder = value & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    if remainder > halfway || (remainder == halfway && kept & 1 == 1) {
+        kept + 1
+    } else {
+        kept
+    }
+}
+
+fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exponent = u32::from((bits >> 10) & 0x1f);
+    let mantissa = u32::from(bits & 0x3ff);
+
+    match exponent {
+        0 => {
+            // Zero or subnormal: mantissa * 2^-24
+            let magnitude = mantissa as f32 / (1u32 << 24) as f32;
+            if sign == 0 {
+                magnitude
+            } else {
+                -magnitude
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 127 - 15) << 23) | (mantissa << 13)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f16_round_trip_within_tolerance() {
+        let values = [0.0, 1.0, -2.5, 0.1, 3.3, 1000.0, -65504.0, 1e-3, 6e-5];
+        let round_trip = f16_to_f32(&f32_to_f16(&values));
+
+        for (value, converted) in values.iter().zip(&round_trip) {
+            // binary16 keeps 11 significant bits
+            let tolerance = value.abs() / 1024.0 + 1e-7;
+            assert!(
+                (value - converted).abs() <= tolerance,
+                "{} became {}",
+                value,
+                converted
+            );
+        }
+        assert_eq!(f32_to_f16(&[1.0]), vec![0x3c00]);
+        assert_eq!(f16_to_f32(&[0x7c00])[0], f32::INFINITY);
+        assert_eq!(f16_to_f32(&f32_to_f16(&[1e6]))[0], f32::INFINITY);
+        assert!(f16_to_f32(&f32_to_f16(&[f32::NAN]))[0].is_nan());
+    }
+
+    #[test]
+    fn test_int8_quantization_within_error() {
+        let params = QuantParams::new(0.05, 10);
+        let values = [-6.9, -1.0, 0.0, 0.026, 0.5, 3.0, 5.85];
+        let quantized = f32_to_int8(&values, params);
+        let dequantized = int8_to_f32(&quantized, params);
+
+        for (value, converted) in values.iter().zip(&dequantized) {
+            assert!(
+                (value - converted).abs() <= params.scale / 2.0 + 1e-6,
+                "{} became {}",
+                value,
+                converted
+            );
+        }
+        assert_eq!(quantized[2], 10);
+
+        // Out of range values saturate
+        assert_eq!(f32_to_int8(&[100.0, -100.0], params), vec![127, -128]);
+    }
+}
@@ -3,34 +3,127 @@
 //! Manages the execution of multiple agents, providing lifecycle
 //! management, resource allocation, and coordination between agents.
 
+use crate::agent::lock_or_recover;
+use crate::backend::{
+    InferenceBackend, InferenceResultBus, MlWorkerPool, PooledBackend, PublishingBackend,
+};
 use crate::ml::MLHandler;
 use crate::prelude::*;
-use crate::types::AgentContext;
+use crate::types::{AgentContext, AgentQuery};
 use anyhow::Result;
 use std::sync::{Arc, Mutex};
 
 pub struct NebulaRuntime {
     agents: Vec<Agent>,
-    ml_handler: Arc<tokio::sync::Mutex<MLHandler>>,
+    ml_handler: Arc<MLHandler>,
+    backend: Arc<dyn InferenceBackend>,
+    ml_pool: MlWorkerPool,
+    default_capabilities: AgentCapabilities,
+    results: InferenceResultBus,
 }
 
 impl NebulaRuntime {
     /// Create a new Nebula runtime
+    ///
+    /// ML work is capped at the machine's available parallelism.
     pub async fn new() -> Result<Self> {
-        let ml_handler = Arc::new(tokio::sync::Mutex::new(MLHandler::new()?));
-        ml_handler.lock().await.initialize().await?;
+        Self::with_ml_pool(MlWorkerPool::with_available_parallelism()).await
+    }
+
+    /// Create a runtime that runs at most `pool.size()` model loads and
+    /// inferences at once
+    pub async fn with_ml_pool(pool: MlWorkerPool) -> Result<Self> {
+        let mut ml_handler = MLHandler::new()?;
+        ml_handler.initialize().await?;
+        let ml_handler = Arc::new(ml_handler);
 
+        let results = InferenceResultBus::default();
+        let pooled = Arc::new(PooledBackend::new(ml_handler.clone(), pool.clone()));
         Ok(Self {
             agents: Vec::new(),
+            backend: Arc::new(PublishingBackend::new(pooled, results.clone())),
             ml_handler,
+            ml_pool: pool,
+            default_capabilities: AgentCapabilities::default(),
+            results,
         })
     }
 
+    /// Set the capabilities given to agents registered without their own
+    pub fn set_default_capabilities(&mut self, capabilities: AgentCapabilities) {
+        self.default_capabilities = capabilities;
+    }
+
     /// Register a new agent with the runtime
-    pub fn register_agent(&mut self, agent: Agent) {
+    ///
+    /// Agents without their own capabilities get the runtime's defaults,
+    /// written into the shared context so every clone of the agent sees
+    /// them.
+    pub fn register_agent(&mut self, mut agent: Agent) {
+        if agent.config.capabilities.is_none() {
+            lock_or_recover(&agent.context()).capabilities = self.default_capabilities.clone();
+            agent.config.capabilities = Some(self.default_capabilities.clone());
+        }
         self.agents.push(agent);
     }
 
+    /// Find the registered agents matching a query
+    pub fn query_agents(&self, query: &AgentQuery) -> Vec<AgentId> {
+        self.agents
+            .iter()
+            .filter(|agent| {
+                let capabilities = lock_or_recover(&agent.context()).capabilities.clone();
+                query.matches(&agent.status(), &capabilities, &agent.config.tags)
+            })
+            .map(|agent| agent.id)
+            .collect()
+    }
+
+    /// Run inference for agent `from` on another registered agent that
+    /// can run inference and supports the request's model
+    ///
+    /// The inference runs on the delegate's own backend and is accounted to
+    /// the delegate's resource usage. Backends bound their own concurrency,
+    /// as [`NebulaRuntime::inference_backend`] does with the ML pool. Only
+    /// running agents with a backend are picked. Fails if `from` is not
+    /// registered or no other agent is capable.
+    pub async fn delegate_inference(
+        &self,
+        from: AgentId,
+        request: InferenceRequest,
+    ) -> Result<InferenceResponse> {
+        if !self.agents.iter().any(|agent| agent.id == from) {
+            return Err(anyhow::anyhow!("Agent {} is not registered", from));
+        }
+        let query = AgentQuery::default()
+            .with_can_inference(true)
+            .supports_model(&request.model_id);
+        let (delegate, backend) = self
+            .query_agents(&query)
+            .into_iter()
+            .filter(|id| *id != from)
+            .filter_map(|id| self.agents.iter().find(|agent| agent.id == id))
+            .filter(|agent| agent.status().is_running())
+            .find_map(|agent| Some((agent, agent.inference_backend()?)))
+            .ok_or_else(|| {
+                anyhow::anyhow!("No agent can run inference on model {}", request.model_id)
+            })?;
+
+        debug!(
+            "Agent {} delegating inference on {} to agent {}",
+            from, request.model_id, delegate.id
+        );
+        let started = std::time::Instant::now();
+        let response = backend.infer(request).await?;
+
+        let context = delegate.context();
+        let mut ctx = lock_or_recover(&context);
+        ctx.resource_usage.inference_count.increment();
+        ctx.resource_usage.total_inference_time += started.elapsed();
+        ctx.update_activity();
+        Ok(response)
+    }
+
     /// Start all registered agents
     pub async fn start_agents(&self) -> Result<()> {
         for agent in &self.agents {
@@ -48,10 +141,98 @@ impl NebulaRuntime {
         Ok(())
     }
 
+    /// Drain and shut agents down one at a time
+    ///
+    /// Every agent first stops accepting messages from outside the runtime;
+    /// registered agents can still message each other, so work already
+    /// queued is passed along. Agents listed in `order` then drain first, in
+    /// that order, and the rest follow in registration order. List producers
+    /// before the consumers they feed so a consumer drains only once nothing
+    /// more can reach it. Each drain waits at most `timeout` before dropping
+    /// the messages left.
+    pub async fn shutdown_ordered(
+        &self,
+        order: Vec<AgentId>,
+        timeout: std::time::Duration,
+    ) -> Result<()> {
+        let mut sequence = Vec::with_capacity(self.agents.len());
+        for id in &order {
+            let agent = self
+                .agents
+                .iter()
+                .find(|agent| agent.id == *id)
+                .ok_or_else(|| anyhow::anyhow!("Agent {} is not registered", id))?;
+            sequence.push(agent);
+        }
+        sequence.extend(
+            self.agents
+                .iter()
+                .filter(|agent| !order.contains(&agent.id)),
+        );
+
+        let peers: Vec<AgentId> = self.agents.iter().map(|agent| agent.id).collect();
+        for agent in &self.agents {
+            agent.stop_accepting(peers.iter().copied());
+        }
+        for agent in sequence {
+            agent.drain(Some(timeout)).await?;
+        }
+        Ok(())
+    }
+
+    /// Agents whose heartbeats show them stalled
+    ///
+    /// An agent is stalled if its last heartbeat found it busy on one
+    /// message for longer than `threshold`, or if it has sent none for
+    /// longer than `threshold` without shutting down. Agents without
+    /// heartbeats are never reported.
+    pub fn stalled_agents(&self, threshold: std::time::Duration) -> Vec<AgentId> {
+        self.agents
+            .iter()
+            .filter(|agent| {
+                agent.last_heartbeat().map_or(false, |beat| {
+                    beat.is_stalled(threshold)
+                        || (beat.sent_at.elapsed() > threshold
+                            && agent.status() != AgentStatus::Shutdown)
+                })
+            })
+            .map(|agent| agent.id)
+            .collect()
+    }
+
     /// Get the ML handler for inference operations
-    pub fn get_ml_handler(&self) -> Arc<tokio::sync::Mutex<MLHandler>> {
+    pub fn get_ml_handler(&self) -> Arc<MLHandler> {
         self.ml_handler.clone()
     }
+
+    /// Replace the backend agents run inference through. Defaults to the
+    /// runtime's ML handler. Calls still go through the runtime's ML pool
+    /// and publish their results to inference subscribers.
+    pub fn set_inference_backend(&mut self, backend: Arc<dyn InferenceBackend>) {
+        info!("Using {} inference backend", backend.name());
+        let pooled = Arc::new(PooledBackend::new(backend, self.ml_pool.clone()));
+        self.backend = Arc::new(PublishingBackend::new(pooled, self.results.clone()));
+    }
+
+    /// Receive every result of inferences run on `model_id` through the
+    /// runtime's backend
+    pub fn subscribe_inference(
+        &self,
+        model_id: &str,
+    ) -> tokio::sync::broadcast::Receiver<InferenceResponse> {
+        self.results.subscribe(model_id)
+    }
+
+    /// Get the pool bounding the runtime's ML work, for agents that run
+    /// their own blocking model code
+    pub fn ml_pool(&self) -> MlWorkerPool {
+        self.ml_pool.clone()
+    }
+
+    /// Get the backend agents should run inference through
+    pub fn inference_backend(&self) -> Arc<dyn InferenceBackend> {
+        self.backend.clone()
+    }
 }
 
 /// Temporary test handler for agent execution
@@ -69,3 +250,626 @@ impl AgentHandler for TestHandler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::SendError;
+    use crate::backend::BackendCapabilities;
+    use crate::types::InferencePriority;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Records how many inferences run at the same time
+    #[derive(Default)]
+    struct ConcurrencyProbe {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl InferenceBackend for ConcurrencyProbe {
+        fn name(&self) -> &str {
+            "probe"
+        }
+
+        async fn load(&self, _config: ModelConfig) -> Result<()> {
+            Ok(())
+        }
+
+        async fn infer(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(InferenceResponse {
+                task_id: request.task_id,
+                success: true,
+                output_data: vec![],
+                output_shape: vec![],
+                outputs: vec![],
+                latency_ms: 20,
+                error: None,
+            })
+        }
+
+        async fn capabilities(&self) -> BackendCapabilities {
+            BackendCapabilities {
+                hardware_accelerated: false,
+                max_batch_size: 1,
+                loaded_models: vec![],
+            }
+        }
+    }
+
+    /// Handles messages slowly, counting them
+    #[derive(Clone, Default)]
+    struct SlowConsumer {
+        handled: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentHandler for SlowConsumer {
+        async fn handle_message(
+            &self,
+            _context: Arc<Mutex<AgentContext>>,
+            _message: Message,
+        ) -> Result<()> {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            self.handled.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Takes a fixed time over every message
+    struct Sleeper(std::time::Duration);
+
+    #[async_trait::async_trait]
+    impl AgentHandler for Sleeper {
+        async fn handle_message(
+            &self,
+            _context: Arc<Mutex<AgentContext>>,
+            _message: Message,
+        ) -> Result<()> {
+            tokio::time::sleep(self.0).await;
+            Ok(())
+        }
+    }
+
+    /// Forwards each message to a consumer once `release` lets it through
+    struct Producer {
+        id: AgentId,
+        consumer: Agent,
+        release: Arc<tokio::sync::Semaphore>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentHandler for Producer {
+        async fn handle_message(
+            &self,
+            _context: Arc<Mutex<AgentContext>>,
+            message: Message,
+        ) -> Result<()> {
+            self.release.acquire().await?.forget();
+            self.consumer.send_message(Message {
+                from: self.id,
+                to: self.consumer.id,
+                ..message
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_ordered_drains_producer_before_consumer() {
+        let mut runtime = NebulaRuntime::with_ml_pool(MlWorkerPool::new(1))
+            .await
+            .unwrap();
+        let producer = AgentBuilder::new("producer").build(1);
+        let consumer = AgentBuilder::new("consumer").build(2);
+        runtime.register_agent(consumer.clone());
+        runtime.register_agent(producer.clone());
+
+        let handler = SlowConsumer::default();
+        let release = Arc::new(tokio::sync::Semaphore::new(0));
+        consumer.initialize().await.unwrap();
+        consumer.start(handler.clone()).await.unwrap();
+        producer.initialize().await.unwrap();
+        producer
+            .start(Producer {
+                id: 1,
+                consumer: consumer.clone(),
+                release: release.clone(),
+            })
+            .await
+            .unwrap();
+
+        let message = |to| Message {
+            from: 0,
+            to,
+            message_type: MessageType::Data,
+            payload: vec![],
+            timestamp: 0,
+            id: None,
+        };
+        for _ in 0..5 {
+            producer.send_message(message(1)).unwrap();
+        }
+
+        let runtime = Arc::new(runtime);
+        let shutdown = tokio::spawn({
+            let runtime = runtime.clone();
+            async move {
+                runtime
+                    .shutdown_ordered(vec![1, 2], std::time::Duration::from_secs(5))
+                    .await
+            }
+        });
+        while producer.is_accepting() || consumer.is_accepting() {
+            tokio::task::yield_now().await;
+        }
+        let err = consumer.send_message(message(2)).unwrap_err();
+        assert_eq!(err.downcast_ref::<SendError>(), Some(&SendError::Draining));
+
+        // Nothing has been forwarded yet; all of it must still arrive
+        assert_eq!(consumer.pending_messages(), 0);
+        release.add_permits(5);
+        shutdown.await.unwrap().unwrap();
+
+        assert_eq!(handler.handled.load(Ordering::SeqCst), 5);
+        assert_eq!(consumer.status(), AgentStatus::Shutdown);
+        assert_eq!(producer.status(), AgentStatus::Shutdown);
+        assert!(runtime
+            .shutdown_ordered(vec![9], std::time::Duration::from_secs(1))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_agents_reports_long_busy_agent() {
+        let mut runtime = NebulaRuntime::new().await.unwrap();
+        let heartbeat = std::time::Duration::from_millis(10);
+        let stuck = AgentBuilder::new("stuck")
+            .with_heartbeat(heartbeat)
+            .build(1);
+        let idle = AgentBuilder::new("idle").with_heartbeat(heartbeat).build(2);
+        runtime.register_agent(stuck.clone());
+        runtime.register_agent(idle.clone());
+
+        for agent in [&stuck, &idle] {
+            agent.initialize().await.unwrap();
+        }
+        stuck
+            .start(Sleeper(std::time::Duration::from_millis(500)))
+            .await
+            .unwrap();
+        idle.start(TestHandler).await.unwrap();
+        stuck
+            .send_message(Message {
+                from: 0,
+                to: 1,
+                message_type: MessageType::Data,
+                payload: vec![],
+                timestamp: 0,
+                id: None,
+            })
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+
+        // Still reads Busy, but the heartbeat shows it stuck on one message
+        assert_eq!(stuck.status(), AgentStatus::Busy);
+        let threshold = std::time::Duration::from_millis(100);
+        assert_eq!(runtime.stalled_agents(threshold), vec![1]);
+
+        runtime.shutdown_agents().await.unwrap();
+        tokio::time::sleep(threshold * 2).await;
+        assert!(runtime.stalled_agents(threshold).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ml_pool_caps_concurrent_inference() {
+        let mut runtime = NebulaRuntime::with_ml_pool(MlWorkerPool::new(2))
+            .await
+            .unwrap();
+        let probe = Arc::new(ConcurrencyProbe::default());
+        runtime.set_inference_backend(probe.clone());
+
+        let backend = runtime.inference_backend();
+        let tasks: Vec<_> = (0..6)
+            .map(|task_id| {
+                let backend = backend.clone();
+                tokio::spawn(async move {
+                    backend
+                        .infer(InferenceRequest {
+                            task_id,
+                            model_id: "probe".to_string(),
+                            input_data: vec![0u8; 4].into(),
+                            input_shape: vec![],
+                            priority: InferencePriority::Normal,
+                            named_inputs: Vec::new(),
+                            timeout: None,
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert!(task.await.unwrap().unwrap().success);
+        }
+
+        assert_eq!(probe.peak.load(Ordering::SeqCst), 2);
+        assert_eq!(runtime.ml_pool().available(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_ml_handler_runs_pooled_inferences_in_parallel() {
+        let runtime = NebulaRuntime::with_ml_pool(MlWorkerPool::new(2))
+            .await
+            .unwrap();
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        // Telemetry runs inside the inference, so a blocking callback stands
+        // in for a slow CPU-bound model
+        runtime.get_ml_handler().set_telemetry(Arc::new({
+            let running = running.clone();
+            let peak = peak.clone();
+            move |_telemetry: &crate::ml::InferenceTelemetry| {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+            }
+        }));
+
+        let backend = runtime.inference_backend();
+        backend
+            .load(ModelConfig {
+                model_id: "multi".to_string(),
+                model_path: concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/multi_output.onnx")
+                    .to_string(),
+                input_shape: vec![1, 4],
+                output_shape: vec![1, 4],
+                ..ModelConfig::default()
+            })
+            .await
+            .unwrap();
+        let tasks: Vec<_> = (0..4)
+            .map(|task_id| {
+                let backend = backend.clone();
+                tokio::spawn(async move {
+                    backend
+                        .infer(InferenceRequest {
+                            task_id,
+                            model_id: "multi".to_string(),
+                            input_data: [0.5f32; 4].iter().flat_map(|x| x.to_ne_bytes()).collect(),
+                            input_shape: vec![1, 4],
+                            priority: InferencePriority::Normal,
+                            named_inputs: Vec::new(),
+                            timeout: None,
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert!(task.await.unwrap().unwrap().success);
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_inference_results_fan_out_to_subscribers() {
+        let mut runtime = NebulaRuntime::new().await.unwrap();
+        runtime.set_inference_backend(Arc::new(ConcurrencyProbe::default()));
+        let mut first = runtime.subscribe_inference("sensor");
+        let mut second = runtime.subscribe_inference("sensor");
+        let mut other = runtime.subscribe_inference("other");
+
+        let backend = runtime.inference_backend();
+        for task_id in 1..=3 {
+            backend
+                .infer(InferenceRequest {
+                    task_id,
+                    model_id: "sensor".to_string(),
+                    input_data: vec![0u8; 4].into(),
+                    input_shape: vec![],
+                    priority: InferencePriority::Normal,
+                    named_inputs: Vec::new(),
+                    timeout: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        for subscriber in [&mut first, &mut second] {
+            for task_id in 1..=3 {
+                assert_eq!(subscriber.recv().await.unwrap().task_id, task_id);
+            }
+            assert!(subscriber.try_recv().is_err());
+        }
+        assert!(other.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ml_pool_serializes_blocking_work() {
+        let pool = MlWorkerPool::new(1);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let pool = pool.clone();
+                let running = running.clone();
+                let peak = peak.clone();
+                tokio::spawn(async move {
+                    pool.spawn_blocking(move || {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(std::time::Duration::from_millis(10));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                    })
+                    .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_agents() {
+        let mut runtime = NebulaRuntime::new().await.unwrap();
+        runtime.register_agent(
+            AgentBuilder::new("vision")
+                .with_ai_inference()
+                .with_models(vec!["resnet".to_string()])
+                .with_tag("camera")
+                .build(1),
+        );
+        runtime.register_agent(
+            AgentBuilder::new("speech")
+                .with_ai_inference()
+                .with_models(vec!["whisper".to_string()])
+                .build(2),
+        );
+        runtime.register_agent(AgentBuilder::new("logger").with_tag("camera").build(3));
+        runtime.agents[2].context().lock().unwrap().status = AgentStatus::Ready;
+
+        let query = |query: AgentQuery| runtime.query_agents(&query);
+        assert_eq!(query(AgentQuery::default()), vec![1, 2, 3]);
+        assert_eq!(
+            query(AgentQuery::default().with_can_inference(true)),
+            vec![1, 2]
+        );
+        assert_eq!(
+            query(AgentQuery::default().with_can_inference(false)),
+            vec![3]
+        );
+        assert_eq!(
+            query(AgentQuery::default().supports_model("whisper")),
+            vec![2]
+        );
+        assert_eq!(query(AgentQuery::default().with_tag("camera")), vec![1, 3]);
+        assert_eq!(
+            query(AgentQuery::default().with_status(AgentStatus::Ready)),
+            vec![3]
+        );
+        assert_eq!(
+            query(
+                AgentQuery::default()
+                    .with_can_inference(true)
+                    .with_tag("camera")
+            ),
+            vec![1]
+        );
+        assert!(query(AgentQuery::default().supports_model("llama")).is_empty());
+    }
+
+    /// Returns every request's input as its output
+    struct EchoBackend;
+
+    #[async_trait::async_trait]
+    impl InferenceBackend for EchoBackend {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        async fn load(&self, _config: ModelConfig) -> Result<()> {
+            Ok(())
+        }
+
+        async fn infer(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+            Ok(InferenceResponse {
+                task_id: request.task_id,
+                success: true,
+                output_data: request.input_data.load()?.to_vec(),
+                output_shape: request.input_shape,
+                outputs: vec![],
+                latency_ms: 0,
+                error: None,
+            })
+        }
+
+        async fn capabilities(&self) -> BackendCapabilities {
+            BackendCapabilities {
+                hardware_accelerated: false,
+                max_batch_size: 1,
+                loaded_models: vec!["echo".to_string()],
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_capabilities_survive_initialize_on_clone() {
+        let mut runtime = NebulaRuntime::new().await.unwrap();
+        runtime.set_default_capabilities(AgentCapabilities {
+            can_inference: true,
+            supported_models: vec!["default_model".to_string()],
+            ..AgentCapabilities::default()
+        });
+        let agent = AgentBuilder::new("plain").build(1);
+        runtime.register_agent(agent.clone());
+        // Initializing the caller's clone must not reset the defaults
+        agent.initialize().await.unwrap();
+
+        let caps = agent.snapshot_context().capabilities;
+        assert!(caps.can_inference);
+        assert_eq!(caps.supported_models, vec!["default_model".to_string()]);
+        assert_eq!(
+            runtime.query_agents(&AgentQuery::default().supports_model("default_model")),
+            vec![1]
+        );
+        assert_eq!(
+            runtime.query_agents(&AgentQuery::default().with_status(AgentStatus::Ready)),
+            vec![1]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delegate_inference_to_capable_agent() {
+        let mut runtime = NebulaRuntime::new().await.unwrap();
+        // Delegated work must not fall back to the runtime's own backend
+        runtime.set_inference_backend(Arc::new(ConcurrencyProbe::default()));
+        let agents = [
+            AgentBuilder::new("sensor").build(1),
+            AgentBuilder::new("vision")
+                .with_ai_inference()
+                .with_models(vec!["resnet".to_string()])
+                .build(2),
+            AgentBuilder::new("speech")
+                .with_ai_inference()
+                .with_models(vec!["echo".to_string()])
+                .build(3),
+        ];
+        for agent in agents {
+            agent.initialize().await.unwrap();
+            agent.set_inference_backend(Arc::new(EchoBackend));
+            runtime.register_agent(agent);
+        }
+
+        let request = |model_id: &str| InferenceRequest {
+            task_id: 9,
+            model_id: model_id.to_string(),
+            input_data: vec![1, 2, 3, 4].into(),
+            input_shape: vec![1, 4],
+            priority: InferencePriority::Normal,
+            named_inputs: Vec::new(),
+            timeout: None,
+        };
+        let response = runtime
+            .delegate_inference(1, request("echo"))
+            .await
+            .unwrap();
+        assert!(response.success);
+        assert_eq!(response.task_id, 9);
+        assert_eq!(response.output_data, vec![1, 2, 3, 4]);
+
+        // Accounted to the delegate, not the caller
+        let usage = |index: usize| runtime.agents[index].resource_usage().inference_count.get();
+        assert_eq!((usage(0), usage(1), usage(2)), (0, 0, 1));
+
+        assert!(runtime
+            .delegate_inference(1, request("llama"))
+            .await
+            .is_err());
+        // An agent cannot delegate to itself
+        assert!(runtime
+            .delegate_inference(3, request("echo"))
+            .await
+            .is_err());
+        assert!(runtime
+            .delegate_inference(7, request("echo"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delegate_inference_through_pooled_backend_on_single_slot() {
+        let mut runtime = NebulaRuntime::with_ml_pool(MlWorkerPool::new(1))
+            .await
+            .unwrap();
+        runtime.set_inference_backend(Arc::new(EchoBackend));
+        let caller = AgentBuilder::new("caller").build(1);
+        let delegate = AgentBuilder::new("delegate")
+            .with_ai_inference()
+            .with_models(vec!["echo".to_string()])
+            .build(2);
+        // The runtime's own backend already takes a slot of the pool
+        delegate.set_inference_backend(runtime.inference_backend());
+        for agent in [&caller, &delegate] {
+            agent.initialize().await.unwrap();
+            runtime.register_agent(agent.clone());
+        }
+
+        for task_id in 0..3 {
+            let request = InferenceRequest {
+                task_id,
+                model_id: "echo".to_string(),
+                input_data: vec![1, 2].into(),
+                input_shape: vec![1, 2],
+                priority: InferencePriority::Normal,
+                named_inputs: Vec::new(),
+                timeout: None,
+            };
+            let response = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                runtime.delegate_inference(1, request),
+            )
+            .await
+            .expect("delegation deadlocked on the ML pool")
+            .unwrap();
+            assert_eq!(response.output_data, vec![1, 2]);
+        }
+        assert_eq!(delegate.resource_usage().inference_count.get(), 3);
+        assert_eq!(runtime.ml_pool().available(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_delegate_inference_skips_agents_not_running() {
+        let mut runtime = NebulaRuntime::new().await.unwrap();
+        let capable = |name: &str, id| {
+            let agent = AgentBuilder::new(name)
+                .with_ai_inference()
+                .with_models(vec!["echo".to_string()])
+                .build(id);
+            agent.set_inference_backend(Arc::new(EchoBackend));
+            agent
+        };
+        let caller = AgentBuilder::new("caller").build(1);
+        let stopped = capable("stopped", 2);
+        let idle = capable("idle", 3);
+        let running = capable("running", 4);
+        caller.initialize().await.unwrap();
+        stopped.initialize().await.unwrap();
+        stopped.shutdown().await.unwrap();
+        running.initialize().await.unwrap();
+        for agent in [&caller, &stopped, &idle, &running] {
+            runtime.register_agent(agent.clone());
+        }
+
+        let response = runtime
+            .delegate_inference(
+                1,
+                InferenceRequest {
+                    task_id: 1,
+                    model_id: "echo".to_string(),
+                    input_data: vec![7].into(),
+                    input_shape: vec![1],
+                    priority: InferencePriority::Normal,
+                    named_inputs: Vec::new(),
+                    timeout: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.output_data, vec![7]);
+        // Neither the shut down nor the never initialized agent was picked
+        assert_eq!(stopped.resource_usage().inference_count.get(), 0);
+        assert_eq!(idle.resource_usage().inference_count.get(), 0);
+        assert_eq!(running.resource_usage().inference_count.get(), 1);
+    }
+}
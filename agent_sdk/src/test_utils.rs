@@ -0,0 +1,130 @@
+//! Test support module for the NebulaOS Agent SDK
+//!
+//! Helpers for asserting on inference output without decoding bytes by
+//! hand. Enabled for the SDK's own tests and, through the `test-utils`
+//! feature, for downstream agent crates.
+
+use crate::ml::{helpers, InferenceOutput, InferenceResponse};
+use crate::types::TaskId;
+
+/// Values of the response's first output
+///
+/// Panics if the inference failed or the output is not whole f32 values.
+#[track_caller]
+pub fn decode_f32_output(response: &InferenceResponse) -> Vec<f32> {
+    assert!(
+        response.success,
+        "inference task {} failed: {}",
+        response.task_id,
+        response.error.as_deref().unwrap_or("no error message")
+    );
+    decode_f32(&response.output_data)
+}
+
+/// Values of the named output, panicking if the response has no such output
+#[track_caller]
+pub fn decode_named_output(response: &InferenceResponse, name: &str) -> Vec<f32> {
+    let Some(output) = response.outputs.iter().find(|output| output.name == name) else {
+        let names: Vec<&str> = response.outputs.iter().map(|o| o.name.as_str()).collect();
+        panic!("no output named {:?}, outputs are {:?}", name, names);
+    };
+    decode_f32(&output.data)
+}
+
+/// Assert the response's first output is within `tolerance` of `expected`
+/// element-wise
+#[track_caller]
+pub fn assert_output_close(response: &InferenceResponse, expected: &[f32], tolerance: f32) {
+    assert_close(&decode_f32_output(response), expected, tolerance);
+}
+
+/// Assert two value slices have the same length and differ by at most
+/// `tolerance` element-wise, naming the first element that does not
+#[track_caller]
+pub fn assert_close(actual: &[f32], expected: &[f32], tolerance: f32) {
+    assert_eq!(
+        actual.len(),
+        expected.len(),
+        "output has {} values, expected {}",
+        actual.len(),
+        expected.len()
+    );
+    for (index, (a, e)) in actual.iter().zip(expected).enumerate() {
+        let difference = (a - e).abs();
+        if difference.is_nan() || difference > tolerance {
+            panic!(
+                "output[{}] = {} differs from expected {} by {} (tolerance {})",
+                index, a, e, difference, tolerance
+            );
+        }
+    }
+}
+
+/// Successful single-output response carrying `values`, for stubbing a
+/// backend in agent tests
+pub fn f32_response(task_id: TaskId, values: &[f32], shape: Vec<usize>) -> InferenceResponse {
+    let data = helpers::f32_to_bytes(values);
+    InferenceResponse {
+        task_id,
+        success: true,
+        output_data: data.clone(),
+        output_shape: shape.clone(),
+        outputs: vec![InferenceOutput {
+            name: "output_0".to_string(),
+            data,
+            shape,
+        }],
+        latency_ms: 0,
+        error: None,
+    }
+}
+
+#[track_caller]
+fn decode_f32(data: &[u8]) -> Vec<f32> {
+    assert!(
+        data.len().is_multiple_of(4),
+        "{} output bytes are not whole f32 values",
+        data.len()
+    );
+    helpers::bytes_to_f32(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_and_assert_close() {
+        let response = f32_response(1, &[0.5, -1.25, 3.0], vec![1, 3]);
+        assert_eq!(decode_f32_output(&response), vec![0.5, -1.25, 3.0]);
+        assert_eq!(
+            decode_named_output(&response, "output_0"),
+            vec![0.5, -1.25, 3.0]
+        );
+        assert_output_close(&response, &[0.501, -1.249, 3.0], 0.01);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "output[1] = -1.25 differs from expected -1.5 by 0.25 (tolerance 0.1)"
+    )]
+    fn test_assert_output_close_names_mismatch() {
+        let response = f32_response(1, &[0.5, -1.25, 3.0], vec![1, 3]);
+        assert_output_close(&response, &[0.5, -1.5, 3.0], 0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "output has 3 values, expected 2")]
+    fn test_assert_close_checks_length() {
+        assert_close(&[1.0, 2.0, 3.0], &[1.0, 2.0], 0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "inference task 4 failed: model exploded")]
+    fn test_decode_rejects_failed_response() {
+        let mut response = f32_response(4, &[1.0], vec![1]);
+        response.success = false;
+        response.error = Some("model exploded".to_string());
+        decode_f32_output(&response);
+    }
+}
@@ -2,8 +2,10 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
 /// Result type for SDK operations
 pub type Result<T> = anyhow::Result<T>;
@@ -32,9 +34,11 @@ pub struct AgentContext {
     pub status: AgentStatus,
     pub capabilities: AgentCapabilities,
     pub resource_usage: ResourceUsage,
-    pub message_queue: VecDeque<crate::message::Message>,
     pub created_at: Instant,
     pub last_activity: Instant,
+    /// Cancelled when the agent shuts down, so long-running handlers can
+    /// stop early
+    pub cancellation: CancellationToken,
 }
 
 impl AgentContext {
@@ -47,15 +51,74 @@ impl AgentContext {
             status: AgentStatus::Initializing,
             capabilities: AgentCapabilities::default(),
             resource_usage: ResourceUsage::default(),
-            message_queue: VecDeque::new(),
             created_at: now,
             last_activity: now,
+            cancellation: CancellationToken::new(),
         }
     }
 
     pub fn update_activity(&mut self) {
         self.last_activity = Instant::now();
     }
+
+    /// Time since the agent was created
+    pub fn uptime(&self) -> Duration {
+        self.created_at.elapsed()
+    }
+
+    /// Time since the agent's last recorded activity
+    pub fn idle_time(&self) -> Duration {
+        self.last_activity.elapsed()
+    }
+
+    /// Copy the fields monitors read into a lock-free snapshot
+    pub fn view(&self) -> AgentContextView {
+        AgentContextView {
+            id: self.id,
+            name: self.name.clone(),
+            status: self.status.clone(),
+            capabilities: self.capabilities.clone(),
+            resource_usage: self.resource_usage.view(),
+        }
+    }
+}
+
+/// Point-in-time copy of an agent's context for read-only use
+#[derive(Debug, Clone)]
+pub struct AgentContextView {
+    pub id: AgentId,
+    pub name: String,
+    pub status: AgentStatus,
+    pub capabilities: AgentCapabilities,
+    pub resource_usage: ResourceUsageView,
+}
+
+/// Point-in-time copy of an agent's resource usage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceUsageView {
+    pub memory_mb: u64,
+    pub npu_utilization: f32,
+    pub inference_count: u64,
+    pub total_inference_time: Duration,
+    pub message_count: u64,
+}
+
+/// Periodic liveness signal from a running agent
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    pub agent_id: AgentId,
+    pub status: AgentStatus,
+    pub last_activity: Instant,
+    pub sent_at: Instant,
+}
+
+impl Heartbeat {
+    /// Whether the agent had been busy on one message for longer than
+    /// `threshold` when the heartbeat was sent
+    pub fn is_stalled(&self, threshold: Duration) -> bool {
+        self.status == AgentStatus::Busy
+            && self.sent_at.duration_since(self.last_activity) > threshold
+    }
 }
 
 /// Current status of an agent
@@ -68,6 +131,13 @@ pub enum AgentStatus {
     Shutdown,
 }
 
+impl AgentStatus {
+    /// Whether the agent is up and able to take work
+    pub fn is_running(&self) -> bool {
+        matches!(self, AgentStatus::Ready | AgentStatus::Busy)
+    }
+}
+
 /// What capabilities does this agent have?
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AgentCapabilities {
@@ -78,14 +148,132 @@ pub struct AgentCapabilities {
     pub preferred_npu: Option<NPUId>,
 }
 
+/// Selects registered agents. Unset fields match any agent.
+#[derive(Debug, Clone, Default)]
+pub struct AgentQuery {
+    /// Only agents in this state
+    pub status: Option<AgentStatus>,
+    /// Only agents that can (or cannot) run inference
+    pub can_inference: Option<bool>,
+    /// Only agents that list this model as supported
+    pub model: Option<String>,
+    /// Only agents carrying every one of these tags
+    pub tags: Vec<String>,
+}
+
+impl AgentQuery {
+    pub fn with_status(mut self, status: AgentStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn with_can_inference(mut self, can_inference: bool) -> Self {
+        self.can_inference = Some(can_inference);
+        self
+    }
+
+    pub fn supports_model(mut self, model: &str) -> Self {
+        self.model = Some(model.to_string());
+        self
+    }
+
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Check whether an agent satisfies this query
+    pub fn matches(
+        &self,
+        status: &AgentStatus,
+        capabilities: &AgentCapabilities,
+        tags: &[String],
+    ) -> bool {
+        self.status.as_ref().map_or(true, |s| s == status)
+            && self
+                .can_inference
+                .map_or(true, |c| c == capabilities.can_inference)
+            && self
+                .model
+                .as_ref()
+                .map_or(true, |m| capabilities.supported_models.contains(m))
+            && self.tags.iter().all(|tag| tags.contains(tag))
+    }
+}
+
 /// Resource usage tracking
 #[derive(Debug, Clone, Default)]
 pub struct ResourceUsage {
     pub memory_mb: u64,
     pub npu_utilization: f32,
-    pub inference_count: u64,
+    pub inference_count: Counter,
     pub total_inference_time: Duration,
-    pub message_count: u64, // Added for testing
+    pub message_count: Counter, // Added for testing
+}
+
+impl ResourceUsage {
+    /// Copy the current counter values
+    pub fn view(&self) -> ResourceUsageView {
+        ResourceUsageView {
+            memory_mb: self.memory_mb,
+            npu_utilization: self.npu_utilization,
+            inference_count: self.inference_count.get(),
+            total_inference_time: self.total_inference_time,
+            message_count: self.message_count.get(),
+        }
+    }
+
+    /// Usage whose counters are shared with this one, so later increments
+    /// show through without locking the context again
+    pub fn share(&self) -> ResourceUsage {
+        ResourceUsage {
+            memory_mb: self.memory_mb,
+            npu_utilization: self.npu_utilization,
+            inference_count: self.inference_count.share(),
+            total_inference_time: self.total_inference_time,
+            message_count: self.message_count.share(),
+        }
+    }
+}
+
+/// Lock-free counter for usage metrics
+///
+/// Cloning copies the current value. Use [`Counter::share`] for a handle
+/// that sees later increments, e.g. for a metrics exporter that reads it
+/// without holding the agent context lock.
+#[derive(Debug, Default)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Clone for Counter {
+    fn clone(&self) -> Self {
+        Self::new(self.get())
+    }
+}
+
+impl Counter {
+    pub fn new(value: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(value)))
+    }
+
+    /// Handle to the same value
+    pub fn share(&self) -> Counter {
+        Counter(self.0.clone())
+    }
+
+    /// Increment by one, returning the new value
+    pub fn increment(&self) -> u64 {
+        self.add(1)
+    }
+
+    /// Increment by `n`, returning the new value
+    pub fn add(&self, n: u64) -> u64 {
+        self.0.fetch_add(n, Ordering::Relaxed) + n
+    }
+
+    /// Current value
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
 }
 
 /// Inference task submitted to the scheduler
@@ -119,6 +307,22 @@ pub struct InferenceResult {
     pub error: Option<String>,
 }
 
+/// What one inference looked like, as reported to a telemetry callback
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferenceTelemetry {
+    pub model_id: ModelId,
+    pub task_id: TaskId,
+    pub latency: Duration,
+    /// Where the inference ran, e.g. `cpu` or an NPU device id
+    pub device: String,
+    pub success: bool,
+    pub input_bytes: usize,
+    pub output_bytes: usize,
+}
+
+/// Callback invoked after each inference
+pub type TelemetryCallback = Arc<dyn Fn(&InferenceTelemetry) + Send + Sync>;
+
 /// NPU device capabilities and status
 #[derive(Debug, Clone)]
 pub struct NPUDevice {
@@ -217,6 +421,39 @@ mod tests {
         assert_eq!(ctx.status, AgentStatus::Initializing);
     }
 
+    #[test]
+    fn test_counter_clones_copy_and_shares_alias() {
+        let counter = Counter::new(2);
+        let copy = counter.clone();
+        let shared = counter.share();
+
+        counter.increment();
+        shared.add(3);
+        assert_eq!(counter.get(), 6);
+        assert_eq!(shared.get(), 6);
+        assert_eq!(copy.get(), 2);
+
+        // Cloning a context no longer aliases its counters
+        let ctx = AgentContext::new(1, "test_agent".to_string());
+        let cloned = ctx.clone();
+        ctx.resource_usage.message_count.increment();
+        assert_eq!(cloned.resource_usage.message_count.get(), 0);
+    }
+
+    #[test]
+    fn test_uptime_and_idle_time() {
+        let mut ctx = AgentContext::new(1, "test_agent".to_string());
+        let initial_uptime = ctx.uptime();
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(ctx.uptime() > initial_uptime);
+        assert!(ctx.idle_time() >= Duration::from_millis(20));
+
+        ctx.update_activity();
+        assert!(ctx.idle_time() < Duration::from_millis(20));
+        assert!(ctx.uptime() >= Duration::from_millis(20));
+    }
+
     #[test]
     fn test_npu_pool_allocation() {
         let mut pool = NPUPool::new();
@@ -10,6 +10,11 @@ use {
     redox_syscall::{error::*, flag::*, schemev2::NewFdFlags, Error},
 };
 
+#[cfg(feature = "mock")]
+use crate::inference::EchoBackend;
+#[cfg(feature = "redox")]
+use crate::inference::OnnxBackend;
+use crate::inference::InferenceBackend;
 use crate::types::*;
 
 /// Handle for open agent scheme resources
@@ -18,6 +23,7 @@ use crate::types::*;
 pub struct AgentHandle {
     pub handle_type: HandleType,
     pub buffer: Vec<u8>,
+    pub responses: ResponseQueue,
     pub agent_id: Option<AgentId>,
     pub flags: usize,
 }
@@ -30,6 +36,7 @@ pub enum HandleType {
     Agent { agent_id: AgentId },
     Inference,
     Status,
+    Metrics,
 }
 
 #[cfg(feature = "redox")]
@@ -48,6 +55,8 @@ pub struct AgentScheme<'socket> {
     next_agent_id: AtomicUsize,
     next_handle_id: AtomicUsize,
     next_task_id: AtomicUsize,
+    backend: Box<dyn InferenceBackend>,
+    name_policy: NamePolicy,
     socket: &'socket Socket,
 }
 
@@ -58,6 +67,9 @@ pub struct AgentScheme {
     npu_pool: NPUPool,
     next_agent_id: AtomicUsize,
     next_task_id: AtomicUsize,
+    next_request_id: AtomicUsize,
+    backend: Box<dyn InferenceBackend>,
+    name_policy: NamePolicy,
 }
 
 // Common trait for both implementations
@@ -71,7 +83,8 @@ pub trait AgentSchemeImpl {
 // Redox implementation
 #[cfg(feature = "redox")]
 impl<'socket> AgentScheme<'socket> {
-    pub fn new(socket: &'socket Socket) -> Self {
+    /// Create the scheme, running inference tasks through ONNX Runtime
+    pub fn new(socket: &'socket Socket) -> Result<Self, String> {
         let mut scheme = Self {
             agents: HashMap::new(),
             handles: HashMap::new(),
@@ -79,28 +92,22 @@ impl<'socket> AgentScheme<'socket> {
             next_agent_id: AtomicUsize::new(1),
             next_handle_id: AtomicUsize::new(1),
             next_task_id: AtomicUsize::new(1),
+            backend: Box::new(OnnxBackend::new()?),
+            name_policy: NamePolicy::default(),
             socket,
         };
-        scheme.init_mock_npus();
-        info!("AgentScheme initialized with {} NPU devices", scheme.npu_pool.devices.len());
-        scheme
-    }
-
-    fn init_mock_npus(&mut self) {
-        let npu1 = NPUDevice {
-            id: 0,
-            name: "MockNPU-0".to_string(),
-            capabilities: NPUCapabilities {
-                max_memory_mb: 8192,
-                supported_precision: vec![Precision::FP32, Precision::FP16],
-                max_batch_size: 32,
-                ops_per_second: 1000000,
+        match std::env::var_os(NPU_MANIFEST_ENV) {
+            Some(path) => match NPUManifest::load(path) {
+                Ok(manifest) => scheme.init_npus_from_manifest(&manifest),
+                Err(e) => {
+                    warn!("{}; using a single mock NPU", e);
+                    scheme.init_mock_npus(1);
+                },
             },
-            status: NPUStatus::Idle,
-            current_model: None,
-            allocated_to: None,
-        };
-        self.npu_pool.add_device(npu1);
+            None => scheme.init_mock_npus(1),
+        }
+        info!("AgentScheme initialized with {} NPU devices", scheme.npu_pool.devices.len());
+        Ok(scheme)
     }
 }
 
@@ -108,31 +115,29 @@ impl<'socket> AgentScheme<'socket> {
 #[cfg(feature = "mock")]
 impl AgentScheme {
     pub fn mock_new() -> Self {
+        Self::mock_new_with_npus(1)
+    }
+
+    /// Create a mock scheme backed by `count` mock NPU devices
+    pub fn mock_new_with_npus(count: usize) -> Self {
         let mut scheme = Self {
             agents: HashMap::new(),
             npu_pool: NPUPool::new(),
             next_agent_id: AtomicUsize::new(1),
             next_task_id: AtomicUsize::new(1),
+            next_request_id: AtomicUsize::new(1),
+            backend: Box::new(EchoBackend),
+            name_policy: NamePolicy::default(),
         };
-        scheme.init_mock_npus();
+        scheme.init_mock_npus(count);
         scheme
     }
 
-    fn init_mock_npus(&mut self) {
-        let npu1 = NPUDevice {
-            id: 0,
-            name: "MockNPU-0".to_string(),
-            capabilities: NPUCapabilities {
-                max_memory_mb: 8192,
-                supported_precision: vec![Precision::FP32, Precision::FP16],
-                max_batch_size: 32,
-                ops_per_second: 1000000,
-            },
-            status: NPUStatus::Idle,
-            current_model: None,
-            allocated_to: None,
-        };
-        self.npu_pool.add_device(npu1);
+    /// Create a mock scheme with the NPU devices described by `manifest`
+    pub fn mock_new_with_manifest(manifest: &NPUManifest) -> Self {
+        let mut scheme = Self::mock_new_with_npus(0);
+        scheme.init_npus_from_manifest(manifest);
+        scheme
     }
 
     pub fn mock_register_agent(&mut self, name: String) -> Result<AgentId, String> {
@@ -142,8 +147,12 @@ impl AgentScheme {
             supported_models: vec!["test_model".to_string()],
             max_tensor_size: 1024 * 1024,
             preferred_npu: None,
+            supports_compression: false,
         };
-        self.register_agent(name, capabilities)
+        match self.mock_command(AgentCommand::Register { name, capabilities })? {
+            AgentResponse::Registered { agent_id } => Ok(agent_id),
+            other => Err(format!("Unexpected response to Register: {:?}", other)),
+        }
     }
 
     pub fn mock_send_message(&mut self, from: AgentId, to: AgentId, payload: &[u8]) -> Result<(), String> {
@@ -156,24 +165,70 @@ impl AgentScheme {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs(),
+            compressed: false,
         };
-        self.send_message(message)
+        match self.mock_command(AgentCommand::SendMessage { to, message })? {
+            AgentResponse::Success => Ok(()),
+            other => Err(format!("Unexpected response to SendMessage: {:?}", other)),
+        }
     }
 
+    /// Take an agent's next message through its wire encoding, as a read
+    /// from its agent handle would
     pub fn mock_receive_message(&mut self, agent_id: AgentId) -> Result<Vec<u8>, String> {
+        let compress = self.negotiated_compression(agent_id);
         if let Some(message) = self.receive_message(agent_id) {
-            Ok(message.payload)
+            Ok(AgentMessage::from_wire(&message.to_wire(compress)?)?.payload)
         } else {
             Err("No messages available".to_string())
         }
     }
+
+    /// Handle a bincode `CommandEnvelope` written by `caller` the way the
+    /// Redox scheme handles a write to its `register` handle, returning the
+    /// bincode `ResponseEnvelope` the caller would read back. `handle_agent`
+    /// is the agent registered through that handle, if any.
+    pub fn mock_write_command(&mut self, buf: &[u8], caller: &Caller, handle_agent: Option<AgentId>) -> Result<Vec<u8>, String> {
+        let envelope: CommandEnvelope = bincode::deserialize(buf)
+            .map_err(|e| format!("Failed to deserialize command: {}", e))?;
+        self.authorize_command(caller, handle_agent, &envelope.command)?;
+
+        let reply = self.execute_command(envelope, caller);
+        self.process_inference_tasks();
+        bincode::serialize(&reply).map_err(|e| format!("Failed to serialize response: {}", e))
+    }
+
+    /// Send `command` over the wire as this process with root privileges,
+    /// turning an `Error` reply into `Err`
+    fn mock_command(&mut self, command: AgentCommand) -> Result<AgentResponse, String> {
+        let caller = Caller::new(std::process::id(), 0);
+        let request_id = self.next_request_id.fetch_add(1, Ordering::SeqCst) as u64;
+        let envelope = CommandEnvelope { request_id, command };
+        let buf = bincode::serialize(&envelope)
+            .map_err(|e| format!("Failed to serialize command: {}", e))?;
+
+        let reply: ResponseEnvelope = bincode::deserialize(&self.mock_write_command(&buf, &caller, None)?)
+            .map_err(|e| format!("Failed to deserialize response: {}", e))?;
+        if reply.request_id != request_id {
+            return Err(format!("Reply to request {} answered request {}", request_id, reply.request_id));
+        }
+        match reply.response {
+            AgentResponse::Error { message } => Err(message),
+            response => Ok(response),
+        }
+    }
 }
 
 // Common implementation for both
 macro_rules! impl_agent_scheme_common {
     ($type:ty) => {
         impl $type {
+            /// Register an agent. Under `NamePolicy::Strict` this fails if an
+            /// agent with the same name is already registered.
             pub fn register_agent(&mut self, name: String, capabilities: AgentCapabilities) -> Result<AgentId, String> {
+                if self.name_policy == NamePolicy::Strict && self.get_agent_by_name(&name).is_some() {
+                    return Err(format!("An agent named {} is already registered", name));
+                }
                 let agent_id = self.next_agent_id.fetch_add(1, Ordering::SeqCst);
                 let mut agent = AgentContext::new(agent_id, name.clone());
                 agent.capabilities = capabilities;
@@ -192,11 +247,290 @@ macro_rules! impl_agent_scheme_common {
                 Ok(agent_id)
             }
 
+            /// Find the earliest registered agent with the given name
+            pub fn get_agent_by_name(&self, name: &str) -> Option<AgentId> {
+                self.agents.values()
+                    .filter(|agent| agent.name == name)
+                    .map(|agent| agent.id)
+                    .min()
+            }
+
+            /// Set whether registered agents must have unique names
+            pub fn set_name_policy(&mut self, name_policy: NamePolicy) {
+                self.name_policy = name_policy;
+            }
+
+            /// Add `count` identical mock NPU devices, numbered from 0
+            fn init_mock_npus(&mut self, count: usize) {
+                for id in 0..count {
+                    self.npu_pool.add_device(NPUDevice {
+                        id,
+                        name: format!("MockNPU-{}", id),
+                        capabilities: NPUCapabilities {
+                            max_memory_mb: 8192,
+                            supported_precision: vec![Precision::FP32, Precision::FP16],
+                            max_batch_size: 32,
+                            ops_per_second: 1000000,
+                        },
+                        status: NPUStatus::Idle,
+                        current_model: None,
+                        allocated_to: None,
+                    });
+                }
+            }
+
+            /// Add the devices described by an NPU manifest
+            fn init_npus_from_manifest(&mut self, manifest: &NPUManifest) {
+                for device in manifest.to_devices() {
+                    self.npu_pool.add_device(device);
+                }
+            }
+
+            /// Limit how many inference tasks may wait in the queue
+            pub fn set_max_queued_tasks(&mut self, max_queued_tasks: usize) {
+                self.npu_pool.max_queued_tasks = max_queued_tasks;
+            }
+
+            /// Choose how queued inference tasks are ordered for dispatch
+            pub fn set_scheduling_mode(&mut self, mode: SchedulingMode) {
+                self.npu_pool.scheduling_mode = mode;
+            }
+
+            /// Configure how many messages may wait for `agent_id` and what
+            /// happens to further messages once that many are queued
+            pub fn set_message_queue_limit(&mut self, agent_id: AgentId, max_size: usize, overflow: QueueOverflow) -> Result<(), String> {
+                let agent = self.agents.get_mut(&agent_id)
+                    .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+                agent.max_message_queue_size = max_size;
+                agent.queue_overflow = overflow;
+                Ok(())
+            }
+
+            /// Replace the backend used to execute inference tasks
+            pub fn set_inference_backend(&mut self, backend: Box<dyn InferenceBackend>) {
+                info!("Using {} inference backend", backend.name());
+                self.backend = backend;
+            }
+
+            /// Render scheme statistics in the Prometheus text format
+            pub fn metrics_text(&self) -> String {
+                use std::fmt::Write;
+
+                let mut by_status: std::collections::BTreeMap<&str, usize> = ["initializing", "ready", "busy", "error", "shutdown"]
+                    .into_iter()
+                    .map(|status| (status, 0))
+                    .collect();
+                let mut inferences = 0;
+                for agent in self.agents.values() {
+                    let status = match agent.status {
+                        AgentStatus::Initializing => "initializing",
+                        AgentStatus::Ready => "ready",
+                        AgentStatus::Busy => "busy",
+                        AgentStatus::Error(_) => "error",
+                        AgentStatus::Shutdown => "shutdown",
+                    };
+                    *by_status.entry(status).or_default() += 1;
+                    inferences += agent.resource_usage.inference_count.get();
+                }
+
+                let devices = self.npu_pool.devices.len();
+                let allocated = self.npu_pool.allocation_map.len();
+                let utilization = if devices == 0 { 0.0 } else { allocated as f64 / devices as f64 };
+
+                let mut out = String::new();
+                let _ = writeln!(out, "# HELP agentd_agents Registered agents by status");
+                let _ = writeln!(out, "# TYPE agentd_agents gauge");
+                for (status, count) in &by_status {
+                    let _ = writeln!(out, "agentd_agents{{status=\"{}\"}} {}", status, count);
+                }
+                let _ = writeln!(out, "# HELP agentd_inferences_total Inference tasks completed successfully");
+                let _ = writeln!(out, "# TYPE agentd_inferences_total counter");
+                let _ = writeln!(out, "agentd_inferences_total {}", inferences);
+                let _ = writeln!(out, "# HELP agentd_inference_queue_depth Inference tasks waiting to run");
+                let _ = writeln!(out, "# TYPE agentd_inference_queue_depth gauge");
+                let _ = writeln!(out, "agentd_inference_queue_depth {}", self.npu_pool.task_queue.len());
+                let _ = writeln!(out, "# HELP agentd_npu_devices NPU devices in the pool");
+                let _ = writeln!(out, "# TYPE agentd_npu_devices gauge");
+                let _ = writeln!(out, "agentd_npu_devices {}", devices);
+                let _ = writeln!(out, "# HELP agentd_npu_utilization Fraction of NPU devices allocated to agents");
+                let _ = writeln!(out, "# TYPE agentd_npu_utilization gauge");
+                let _ = writeln!(out, "agentd_npu_utilization {}", utilization);
+                out
+            }
+
+            /// Describe agents and NPU devices as the JSON served by the
+            /// status handle
+            pub fn status_json(&self) -> serde_json::Value {
+                let mut devices: Vec<&NPUDevice> = self.npu_pool.devices.values().collect();
+                devices.sort_by_key(|device| device.id);
+                serde_json::json!({
+                    "agents": self.agents.len(),
+                    "npus": self.npu_pool.devices.len(),
+                    "pending_tasks": self.npu_pool.task_queue.len(),
+                    "agent_list": self.agents.iter().map(|(id, agent)| {
+                        serde_json::json!({
+                            "id": id,
+                            "name": agent.name,
+                            "status": format!("{:?}", agent.status),
+                            "inference_count": agent.resource_usage.inference_count.get(),
+                            "total_inference_ms": agent.resource_usage.total_inference_time.as_secs_f64() * 1000.0,
+                            "avg_inference_ms": agent.resource_usage.average_inference_time()
+                                .map(|avg| avg.as_secs_f64() * 1000.0),
+                            "uptime_secs": agent.uptime().as_secs_f64(),
+                            "idle_secs": agent.idle_time().as_secs_f64(),
+                        })
+                    }).collect::<Vec<_>>(),
+                    "devices": devices.iter().map(|device| {
+                        serde_json::json!({
+                            "id": device.id,
+                            "name": device.name,
+                            "status": format!("{:?}", device.status),
+                            "allocated_to": device.allocated_to,
+                            "utilization": if device.is_active() { 1.0 } else { 0.0 },
+                            "health": device.health(),
+                        })
+                    }).collect::<Vec<_>>(),
+                    "usage": self.npu_pool.usage_stats(),
+                })
+            }
+
+            /// Run a control command and build its tagged reply
+            pub fn execute_command(&mut self, envelope: CommandEnvelope, caller: &Caller) -> ResponseEnvelope {
+                let response = match envelope.command {
+                    AgentCommand::Register { name, capabilities } => {
+                        match self.register_agent_as(name, capabilities, caller) {
+                            Ok(agent_id) => AgentResponse::Registered { agent_id },
+                            Err(msg) => AgentResponse::Error { message: msg },
+                        }
+                    },
+                    AgentCommand::SendMessage { to: _, message } => {
+                        match self.send_message(message) {
+                            Ok(()) => AgentResponse::Success,
+                            Err(msg) => AgentResponse::Error { message: msg },
+                        }
+                    },
+                    AgentCommand::SubmitInference { task } => {
+                        match self.npu_pool.submit_task(task) {
+                            Ok(()) => AgentResponse::Success,
+                            Err(msg) => {
+                                warn!("Rejecting inference task: {}", msg);
+                                AgentResponse::Error { message: msg }
+                            },
+                        }
+                    },
+                    AgentCommand::GetStatus { agent_id } => {
+                        let statuses = if let Some(id) = agent_id {
+                            if let Some(agent) = self.agents.get(&id) {
+                                vec![agent.status.clone()]
+                            } else {
+                                vec![]
+                            }
+                        } else {
+                            self.agents.values().map(|a| a.status.clone()).collect()
+                        };
+                        AgentResponse::Status { agents: statuses }
+                    },
+                    AgentCommand::LoadModel { model_id, model_data } => {
+                        let size = model_data.len();
+                        match self.npu_pool.load_model(model_id.clone(), model_data) {
+                            Ok(()) => {
+                                self.backend.model_loaded(&model_id);
+                                info!("Loaded model {} ({} bytes)", model_id, size);
+                                AgentResponse::Success
+                            },
+                            Err(msg) => AgentResponse::Error { message: msg },
+                        }
+                    },
+                    AgentCommand::Unregister { agent_id } => {
+                        match self.unregister_agent(agent_id) {
+                            Ok(_) => AgentResponse::Success,
+                            Err(msg) => AgentResponse::Error { message: msg },
+                        }
+                    },
+                };
+
+                ResponseEnvelope {
+                    request_id: envelope.request_id,
+                    response,
+                }
+            }
+
+            /// Register an agent owned by the calling process
+            pub fn register_agent_as(&mut self, name: String, capabilities: AgentCapabilities, caller: &Caller) -> Result<AgentId, String> {
+                let agent_id = self.register_agent(name, capabilities)?;
+                if let Some(agent) = self.agents.get_mut(&agent_id) {
+                    agent.process_id = Some(caller.pid);
+                }
+                Ok(agent_id)
+            }
+
+            /// Check that `caller` may issue `command` on a handle through which
+            /// `handle_agent` was registered, if any. Unprivileged callers can
+            /// only unregister, or send messages as, agents they registered, and
+            /// can only load models or submit inference for the handle's agent.
+            /// Tasks naming another agent than the handle's are always refused.
+            pub fn authorize_command(&self, caller: &Caller, handle_agent: Option<AgentId>, command: &AgentCommand) -> Result<(), String> {
+                if let (AgentCommand::SubmitInference { task }, Some(agent_id)) = (command, handle_agent) {
+                    if task.agent_id != agent_id {
+                        warn!("Handle of agent {} submitted a task for agent {}", agent_id, task.agent_id);
+                        return Err(format!("Task for agent {} submitted through the handle of agent {}", task.agent_id, agent_id));
+                    }
+                }
+                if caller.is_privileged() {
+                    return Ok(());
+                }
+                if matches!(command, AgentCommand::SubmitInference { .. } | AgentCommand::LoadModel { .. }) && handle_agent.is_none() {
+                    return Err(format!("Process {} has no agent registered through this handle", caller.pid));
+                }
+
+                let acting_as = match command {
+                    AgentCommand::Unregister { agent_id } => Some(*agent_id),
+                    AgentCommand::SendMessage { message, .. } => Some(message.from),
+                    _ => None,
+                };
+
+                if let Some(agent_id) = acting_as {
+                    let owner = self.agents.get(&agent_id).and_then(|agent| agent.process_id);
+                    if owner != Some(caller.pid) {
+                        warn!("Process {} is not allowed to act as agent {}", caller.pid, agent_id);
+                        return Err(format!("Process {} does not own agent {}", caller.pid, agent_id));
+                    }
+                }
+
+                Ok(())
+            }
+
+            pub fn unregister_agent(&mut self, agent_id: AgentId) -> Result<usize, String> {
+                self.agents.remove(&agent_id)
+                    .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+                if let Some(npu_id) = self.npu_pool.deallocate_npu(agent_id) {
+                    info!("Released NPU {} from agent {}", npu_id, agent_id);
+                }
+
+                // Drop queued inference work so no results go to a gone agent
+                let queued = self.npu_pool.task_queue.len();
+                self.npu_pool.task_queue.retain(|task| task.agent_id != agent_id);
+                let cancelled = queued - self.npu_pool.task_queue.len();
+
+                info!("Unregistered agent {} ({} queued task(s) cancelled)", agent_id, cancelled);
+                Ok(cancelled)
+            }
+
+            /// Whether `agent_id` registered as able to handle compressed payloads
+            pub fn negotiated_compression(&self, agent_id: AgentId) -> bool {
+                self.agents.get(&agent_id)
+                    .map_or(false, |agent| agent.capabilities.supports_compression)
+            }
+
             pub fn send_message(&mut self, message: AgentMessage) -> Result<(), String> {
+                if message.compressed && !self.negotiated_compression(message.from) {
+                    return Err(format!("Agent {} did not negotiate compressed payloads", message.from));
+                }
+
                 let target_agent = self.agents.get_mut(&message.to)
                     .ok_or_else(|| format!("Target agent {} not found", message.to))?;
 
-                target_agent.message_queue.push_back(message.clone());
+                target_agent.enqueue_message(message.clone())?;
                 target_agent.update_activity();
 
                 debug!("Sent message from agent {} to agent {}", message.from, message.to);
@@ -206,39 +540,74 @@ macro_rules! impl_agent_scheme_common {
             pub fn receive_message(&mut self, agent_id: AgentId) -> Option<AgentMessage> {
                 if let Some(agent) = self.agents.get_mut(&agent_id) {
                     agent.update_activity();
-                    agent.message_queue.pop_front()
+                    let mut message = agent.message_queue.pop_front()?;
+                    if let Err(e) = message.decompress_payload() {
+                        warn!("Dropping message for agent {}: {}", agent_id, e);
+                        return None;
+                    }
+                    Some(message)
                 } else {
                     None
                 }
             }
 
             pub fn process_inference_tasks(&mut self) {
-                if let Some(task) = self.npu_pool.task_queue.pop_front() {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default();
+                for task in self.npu_pool.take_expired_tasks(now) {
+                    warn!("Dropping inference task {}: deadline passed before dispatch", task.task_id);
                     if let Some(agent) = self.agents.get_mut(&task.agent_id) {
                         let result = InferenceResult {
                             task_id: task.task_id,
-                            success: true,
-                            output_data: b"mock_inference_result".to_vec(),
-                            latency: std::time::Duration::from_millis(50),
-                            error: None,
+                            success: false,
+                            output_data: Vec::new(),
+                            latency: std::time::Duration::ZERO,
+                            error: Some("Timed out: deadline passed before dispatch".to_string()),
                         };
+                        agent.message_queue.push_back(AgentMessage::inference_result(task.agent_id, &result));
+                    }
+                }
 
-                        let message = AgentMessage {
-                            from: 0,
-                            to: task.agent_id,
-                            message_type: MessageType::InferenceResponse,
-                            payload: bincode::serialize(&result).unwrap_or_default(),
-                            timestamp: std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap_or_default()
-                                .as_secs(),
+                if let Some(task) = self.npu_pool.next_task() {
+                    if !self.agents.contains_key(&task.agent_id) {
+                        return;
+                    }
+
+                    let started = std::time::Instant::now();
+                    let outcome = match self.npu_pool.models.get(&task.model_id) {
+                        Some(model_data) => self.backend.run(&task.model_id, model_data, &task.input_data),
+                        None => Err(format!("Model {} is not loaded", task.model_id)),
+                    };
+                    let latency = started.elapsed();
+
+                    if let Some(agent) = self.agents.get_mut(&task.agent_id) {
+                        let result = match outcome {
+                            Ok(output_data) => InferenceResult {
+                                task_id: task.task_id,
+                                success: true,
+                                output_data,
+                                latency,
+                                error: None,
+                            },
+                            Err(e) => {
+                                warn!("Inference task {} failed: {}", task.task_id, e);
+                                InferenceResult {
+                                    task_id: task.task_id,
+                                    success: false,
+                                    output_data: Vec::new(),
+                                    latency,
+                                    error: Some(e),
+                                }
+                            },
                         };
 
-                        agent.message_queue.push_back(message);
+                        agent.message_queue.push_back(AgentMessage::inference_result(task.agent_id, &result));
                         agent.status = AgentStatus::Ready;
-                        agent.resource_usage.inference_count += 1;
-
-                        debug!("Completed inference task {} for agent {}", task.task_id, task.agent_id);
+                        if result.success {
+                            agent.resource_usage.record_inference(latency);
+                            debug!("Completed inference task {} for agent {}", task.task_id, task.agent_id);
+                        }
                     }
                 }
             }
@@ -269,6 +638,12 @@ impl<'socket> SchemeSync for AgentScheme<'socket> {
                 handle.handle_type = HandleType::Status;
                 info!("Opened status handle {}", handle_id);
             },
+            "metrics" => {
+                // Snapshot at open so the text can be read in several chunks
+                handle.handle_type = HandleType::Metrics;
+                handle.buffer = self.metrics_text().into_bytes();
+                info!("Opened metrics handle {}", handle_id);
+            },
             _ => return Err(Error::new(EINVAL))
         }
 
@@ -280,82 +655,64 @@ impl<'socket> SchemeSync for AgentScheme<'socket> {
         })
     }
 
-    fn write(&mut self, id: usize, buf: &[u8], _offset: u64, _flags: u32, _ctx: &CallerCtx) -> Result<usize> {
-        let handle = self.handles.get_mut(&id).ok_or(Error::new(EBADF))?;
+    fn write(&mut self, id: usize, buf: &[u8], _offset: u64, _flags: u32, ctx: &CallerCtx) -> Result<usize> {
+        let caller = Caller::from(ctx);
+        let handle_type = self.handles.get(&id).ok_or(Error::new(EBADF))?.handle_type.clone();
         
-        match handle.handle_type {
+        match handle_type {
             HandleType::Control => {
                 // Parse incoming command
-                let command: AgentCommand = bincode::deserialize(buf)
+                let envelope: CommandEnvelope = bincode::deserialize(buf)
                     .map_err(|_| Error::new(EINVAL))?;
                 
-                let response = match command {
-                    AgentCommand::Register { name, capabilities } => {
-                        match self.register_agent(name, capabilities) {
-                            Ok(agent_id) => AgentResponse::Registered { agent_id },
-                            Err(msg) => AgentResponse::Error { message: msg },
-                        }
-                    },
-                    AgentCommand::SendMessage { to, message } => {
-                        match self.send_message(message) {
-                            Ok(()) => AgentResponse::Success,
-                            Err(msg) => AgentResponse::Error { message: msg },
-                        }
-                    },
-                    AgentCommand::SubmitInference { task } => {
-                        self.npu_pool.task_queue.push_back(task);
-                        AgentResponse::Success
-                    },
-                    AgentCommand::GetStatus { agent_id } => {
-                        let statuses = if let Some(id) = agent_id {
-                            if let Some(agent) = self.agents.get(&id) {
-                                vec![agent.status.clone()]
-                            } else {
-                                vec![]
-                            }
-                        } else {
-                            self.agents.values().map(|a| a.status.clone()).collect()
-                        };
-                        AgentResponse::Status { agents: statuses }
-                    },
-                    AgentCommand::LoadModel { model_id, model_data } => {
-                        // For now, just acknowledge model loading
-                        debug!("Model {} loaded ({} bytes)", model_id, model_data.len());
-                        AgentResponse::Success
-                    },
-                    AgentCommand::Unregister { agent_id } => {
-                        if let Some(_agent) = self.agents.remove(&agent_id) {
-                            self.npu_pool.deallocate_npu(agent_id);
-                            info!("Unregistered agent {}", agent_id);
-                            AgentResponse::Success
-                        } else {
-                            AgentResponse::Error { message: format!("Agent {} not found", agent_id) }
-                        }
-                    },
-                };
+                let handle_agent = self.handles.get(&id).ok_or(Error::new(EBADF))?.agent_id;
+                self.authorize_command(&caller, handle_agent, &envelope.command)
+                    .map_err(|_| Error::new(EPERM))?;
                 
-                // Store response in handle buffer for reading
-                handle.buffer = bincode::serialize(&response)
+                let reply = self.execute_command(envelope, &caller);
+                
+                // Queue the reply for reading, preserving pipelined order; the
+                // first agent registered through a handle acts for it from now on
+                let handle = self.handles.get_mut(&id).ok_or(Error::new(EBADF))?;
+                if let AgentResponse::Registered { agent_id } = &reply.response {
+                    handle.agent_id.get_or_insert(*agent_id);
+                }
+                handle.responses.push(&reply)
                     .map_err(|_| Error::new(EINVAL))?;
             },
             HandleType::Agent { agent_id } => {
-                // Direct agent communication - add to message queue
-                if let Some(agent) = self.agents.get_mut(&agent_id) {
-                    // For now, just add raw data to buffer for the agent to read
-                    agent.message_queue.extend(buf.iter().cloned());
-                    agent.update_activity();
-                } else {
-                    return Err(Error::new(ENOENT));
+                // Direct agent communication - a bincode AgentMessage queued
+                // for this agent under its overflow policy
+                let message: AgentMessage = bincode::deserialize(buf)
+                    .map_err(|_| Error::new(EINVAL))?;
+                if message.to != agent_id {
+                    return Err(Error::new(EINVAL));
+                }
+                if message.compressed && !self.negotiated_compression(message.from) {
+                    return Err(Error::new(EINVAL));
                 }
+
+                let agent = self.agents.get_mut(&agent_id).ok_or(Error::new(ENOENT))?;
+                agent.enqueue_message(message).map_err(|e| {
+                    warn!("Rejecting write to agent {}: {}", agent_id, e);
+                    Error::new(EAGAIN)
+                })?;
+                agent.update_activity();
             },
             HandleType::Inference => {
-                // Parse inference request
+                // Parse inference request, which must be for the handle's agent
                 let task: InferenceTask = bincode::deserialize(buf)
                     .map_err(|_| Error::new(EINVAL))?;
-                self.npu_pool.task_queue.push_back(task);
+                let handle_agent = self.handles.get(&id).ok_or(Error::new(EBADF))?.agent_id;
+                if handle_agent != Some(task.agent_id) {
+                    warn!("Inference handle {} submitted a task for agent {}", id, task.agent_id);
+                    return Err(Error::new(EPERM));
+                }
+                self.npu_pool.submit_task(task)
+                    .map_err(|_| Error::new(EAGAIN))?;
             },
-            HandleType::Status => {
-                // Status endpoint is read-only
+            HandleType::Status | HandleType::Metrics => {
+                // Status endpoints are read-only
                 return Err(Error::new(EPERM));
             },
         }
@@ -371,11 +728,8 @@ impl<'socket> SchemeSync for AgentScheme<'socket> {
         
         match handle.handle_type {
             HandleType::Control => {
-                // Return response from buffer
-                if !handle.buffer.is_empty() {
-                    let len = std::cmp::min(buf.len(), handle.buffer.len());
-                    buf[..len].copy_from_slice(&handle.buffer[..len]);
-                    handle.buffer.drain(..len);
+                // Return the oldest queued response
+                if let Some(len) = handle.responses.read(buf) {
                     Ok(len)
                 } else if (flags as usize) & O_NONBLOCK == O_NONBLOCK {
                     Err(Error::new(EAGAIN))
@@ -387,7 +741,7 @@ impl<'socket> SchemeSync for AgentScheme<'socket> {
                 // Read messages for this agent
                 if let Some(agent) = self.agents.get_mut(&agent_id) {
                     if let Some(message) = agent.message_queue.pop_front() {
-                        let serialized = bincode::serialize(&message)
+                        let serialized = message.to_wire(agent.capabilities.supports_compression)
                             .map_err(|_| Error::new(EINVAL))?;
                         let len = std::cmp::min(buf.len(), serialized.len());
                         buf[..len].copy_from_slice(&serialized[..len]);
@@ -416,27 +770,20 @@ impl<'socket> SchemeSync for AgentScheme<'socket> {
                 }
             },
             HandleType::Status => {
-                // Return JSON status of all agents
-                let status_info = serde_json::json!({
-                    "agents": self.agents.len(),
-                    "npus": self.npu_pool.devices.len(),
-                    "pending_tasks": self.npu_pool.task_queue.len(),
-                    "agent_list": self.agents.iter().map(|(id, agent)| {
-                        serde_json::json!({
-                            "id": id,
-                            "name": agent.name,
-                            "status": format!("{:?}", agent.status),
-                            "inference_count": agent.resource_usage.inference_count,
-                        })
-                    }).collect::<Vec<_>>()
-                });
-                
-                let status_str = status_info.to_string();
+                // Return JSON status of all agents and NPU devices
+                let status_str = self.status_json().to_string();
                 let status_bytes = status_str.as_bytes();
                 let len = std::cmp::min(buf.len(), status_bytes.len());
                 buf[..len].copy_from_slice(&status_bytes[..len]);
                 Ok(len)
             },
+            HandleType::Metrics => {
+                // Drain the snapshot taken at open; 0 signals end of file
+                let len = std::cmp::min(buf.len(), handle.buffer.len());
+                buf[..len].copy_from_slice(&handle.buffer[..len]);
+                handle.buffer.drain(..len);
+                Ok(len)
+            },
         }
     }
 
@@ -459,6 +806,7 @@ mod tests {
             supported_models: vec!["test_model".to_string()],
             max_tensor_size: 1024 * 1024,
             preferred_npu: None,
+            supports_compression: false,
         }
     }
 
@@ -487,6 +835,28 @@ mod tests {
         assert!(agent.capabilities.can_inference);
     }
 
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_name_policy() {
+        let mut scheme = AgentScheme::mock_new();
+        scheme.set_name_policy(NamePolicy::Strict);
+        let first = scheme.mock_register_agent("Camera".to_string()).unwrap();
+        assert!(scheme.mock_register_agent("Camera".to_string()).is_err());
+        assert_eq!(scheme.agents.len(), 1);
+        assert_eq!(scheme.get_agent_by_name("Camera"), Some(first));
+
+        // The register command goes through the same check
+        let caller = Caller::new(42, 1000);
+        assert!(scheme.register_agent_as("Camera".to_string(), AgentCapabilities::default(), &caller).is_err());
+
+        let mut scheme = AgentScheme::mock_new();
+        let first = scheme.mock_register_agent("Camera".to_string()).unwrap();
+        let second = scheme.mock_register_agent("Camera".to_string()).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(scheme.get_agent_by_name("Camera"), Some(first));
+        assert_eq!(scheme.get_agent_by_name("Microphone"), None);
+    }
+
     #[test]
     #[cfg(feature = "mock")]
     fn test_register_multiple_agents() {
@@ -633,6 +1003,7 @@ mod tests {
             supported_models: vec!["custom_model".to_string(), "another_model".to_string()],
             max_tensor_size: 2048 * 1024,
             preferred_npu: Some(0),
+            supports_compression: false,
         };
         
         let agent_id = scheme.register_agent(
@@ -662,6 +1033,7 @@ mod tests {
             message_type: MessageType::InferenceRequest,
             payload: b"test payload".to_vec(),
             timestamp: 123456789,
+            compressed: false,
         };
         
         scheme.send_message(message.clone()).unwrap();
@@ -673,4 +1045,680 @@ mod tests {
         assert_eq!(received_message.to, agent_id2);
         assert_eq!(received_message.payload, b"test payload");
     }
+
+    #[test]
+    fn test_inference_result_round_trip() {
+        let result = InferenceResult {
+            task_id: 7,
+            success: true,
+            output_data: vec![1, 2, 3],
+            latency: std::time::Duration::from_millis(12),
+            error: None,
+        };
+
+        let message = AgentMessage::inference_result(3, &result);
+        assert_eq!(message.to, 3);
+        assert_eq!(message.message_type, MessageType::InferenceResponse);
+
+        let wire = message.to_wire(true).unwrap();
+        let decoded = AgentMessage::from_wire(&wire).unwrap().as_inference_result().unwrap();
+        assert_eq!(decoded.task_id, 7);
+        assert!(decoded.success);
+        assert_eq!(decoded.output_data, vec![1, 2, 3]);
+        assert_eq!(decoded.latency, std::time::Duration::from_millis(12));
+
+        // Other message types are not mistaken for results
+        let mut data = message.clone();
+        data.message_type = MessageType::Data;
+        assert!(data.as_inference_result().is_err());
+    }
+
+    #[test]
+    fn test_large_payload_compressed_on_wire() {
+        let payload = vec![7u8; 64 * 1024];
+        let message = AgentMessage {
+            from: 1,
+            to: 2,
+            message_type: MessageType::Data,
+            payload: payload.clone(),
+            timestamp: 123456789,
+            compressed: false,
+        };
+
+        let wire = message.to_wire(true).unwrap();
+        assert!(wire.len() < payload.len() / 10);
+
+        let decoded = AgentMessage::from_wire(&wire).unwrap();
+        assert!(!decoded.compressed);
+        assert_eq!(decoded.payload, payload);
+
+        // Readers that did not negotiate compression get the payload as is
+        let plain = AgentMessage::from_wire(&message.to_wire(false).unwrap()).unwrap();
+        assert!(!plain.compressed);
+        assert_eq!(plain.payload, payload);
+    }
+
+    #[test]
+    fn test_oversized_decompression_prefix_rejected() {
+        let mut message = AgentMessage {
+            from: 1,
+            to: 2,
+            message_type: MessageType::Data,
+            payload: lz4_flex::compress_prepend_size(&[7u8; 8192]),
+            timestamp: 123456789,
+            compressed: true,
+        };
+        // Claim far more than the payload could hold
+        message.payload[..4].copy_from_slice(&((MAX_PAYLOAD_SIZE + 1) as u32).to_le_bytes());
+        let err = message.clone().decompress_payload().unwrap_err();
+        assert!(err.contains("more than"));
+
+        message.payload.truncate(3);
+        assert!(message.decompress_payload().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_compressed_message_requires_negotiation() {
+        let mut scheme = AgentScheme::mock_new();
+        let plain = scheme.mock_register_agent("Plain".to_string()).unwrap();
+        let receiver = scheme.mock_register_agent("Receiver".to_string()).unwrap();
+        let message = |from| AgentMessage {
+            from,
+            to: receiver,
+            message_type: MessageType::Data,
+            payload: lz4_flex::compress_prepend_size(&[7u8; 8192]),
+            timestamp: 123456789,
+            compressed: true,
+        };
+        assert!(scheme.send_message(message(plain)).is_err());
+
+        let mut capabilities = test_capabilities();
+        capabilities.supports_compression = true;
+        let caller = Caller::new(std::process::id(), 0);
+        let compressing = scheme.register_agent_as("Compressing".to_string(), capabilities, &caller).unwrap();
+        scheme.send_message(message(compressing)).unwrap();
+        assert_eq!(scheme.mock_receive_message(receiver).unwrap(), vec![7u8; 8192]);
+    }
+
+    #[test]
+    fn test_small_payload_not_compressed() {
+        let mut message = AgentMessage {
+            from: 1,
+            to: 2,
+            message_type: MessageType::Data,
+            payload: b"small".to_vec(),
+            timestamp: 123456789,
+            compressed: false,
+        };
+
+        message.compress_payload();
+        assert!(!message.compressed);
+        assert_eq!(message.payload, b"small");
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_unregister_cancels_queued_tasks() {
+        let mut scheme = AgentScheme::mock_new();
+        let agent_id = scheme.mock_register_agent("Agent 1".to_string()).unwrap();
+        let other_id = scheme.mock_register_agent("Agent 2".to_string()).unwrap();
+
+        for (task_id, owner) in [(1, agent_id), (2, agent_id), (3, other_id)] {
+            scheme.npu_pool.task_queue.push_back(InferenceTask {
+                task_id,
+                agent_id: owner,
+                model_id: "test_model".to_string(),
+                input_data: vec![],
+                priority: InferencePriority::Normal,
+                max_latency: None,
+                submitted_at: 0,
+            });
+        }
+
+        assert_eq!(scheme.unregister_agent(agent_id).unwrap(), 2);
+        assert!(!scheme.agents.contains_key(&agent_id));
+        assert_eq!(scheme.npu_pool.task_queue.len(), 1);
+        assert_eq!(scheme.npu_pool.task_queue[0].agent_id, other_id);
+
+        assert!(scheme.unregister_agent(agent_id).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_agent_cannot_unregister_other_agent() {
+        let mut scheme = AgentScheme::mock_new();
+        let alice = Caller::new(100, 1000);
+        let bob = Caller::new(200, 1001);
+        let root = Caller::new(1, 0);
+
+        let alice_agent = scheme.register_agent_as("Alice".to_string(), test_capabilities(), &alice).unwrap();
+        let bob_agent = scheme.register_agent_as("Bob".to_string(), test_capabilities(), &bob).unwrap();
+
+        let unregister_bob = AgentCommand::Unregister { agent_id: bob_agent };
+        assert!(scheme.authorize_command(&alice, None, &unregister_bob).is_err());
+        assert!(scheme.authorize_command(&bob, None, &unregister_bob).is_ok());
+        assert!(scheme.authorize_command(&root, None, &unregister_bob).is_ok());
+
+        // Alice cannot send messages impersonating Bob's agent
+        let spoofed = AgentCommand::SendMessage {
+            to: alice_agent,
+            message: AgentMessage {
+                from: bob_agent,
+                to: alice_agent,
+                message_type: MessageType::Data,
+                payload: vec![],
+                timestamp: 0,
+                compressed: false,
+            },
+        };
+        assert!(scheme.authorize_command(&alice, None, &spoofed).is_err());
+
+        // Registration is open to everyone
+        let register = AgentCommand::Register {
+            name: "Carol".to_string(),
+            capabilities: test_capabilities(),
+        };
+        assert!(scheme.authorize_command(&alice, None, &register).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_inference_commands_act_for_handle_agent() {
+        let mut scheme = AgentScheme::mock_new();
+        let alice = Caller::new(100, 1000);
+        let root = Caller::new(1, 0);
+        let alice_agent = scheme.register_agent_as("Alice".to_string(), test_capabilities(), &alice).unwrap();
+        let bob_agent = scheme.register_agent_as("Bob".to_string(), test_capabilities(), &Caller::new(200, 1001)).unwrap();
+
+        let submit = |agent_id| AgentCommand::SubmitInference {
+            task: inference_task(1, agent_id, "test_model", b"input"),
+        };
+        let load = AgentCommand::LoadModel {
+            model_id: "test_model".to_string(),
+            model_data: vec![1],
+        };
+        assert!(scheme.authorize_command(&alice, Some(alice_agent), &submit(alice_agent)).is_ok());
+        assert!(scheme.authorize_command(&alice, Some(alice_agent), &load).is_ok());
+
+        // The task's agent id must match the handle's, whoever the caller is
+        assert!(scheme.authorize_command(&alice, Some(alice_agent), &submit(bob_agent)).is_err());
+        assert!(scheme.authorize_command(&root, Some(alice_agent), &submit(bob_agent)).is_err());
+
+        // Without an agent behind the handle only privileged callers may
+        assert!(scheme.authorize_command(&alice, None, &submit(alice_agent)).is_err());
+        assert!(scheme.authorize_command(&alice, None, &load).is_err());
+        assert!(scheme.authorize_command(&root, None, &submit(bob_agent)).is_ok());
+
+        // The same checks apply to commands written over the wire
+        let envelope = CommandEnvelope { request_id: 1, command: submit(bob_agent) };
+        let buf = bincode::serialize(&envelope).unwrap();
+        assert!(scheme.mock_write_command(&buf, &alice, Some(alice_agent)).is_err());
+        assert!(scheme.npu_pool.task_queue.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_pipelined_replies_carry_request_ids() {
+        let mut scheme = AgentScheme::mock_new();
+        let caller = Caller::new(100, 1000);
+        let mut responses = ResponseQueue::default();
+
+        // Two commands written back to back before any reply is read
+        for (request_id, name) in [(41, "Agent A"), (42, "Agent B")] {
+            let envelope = CommandEnvelope {
+                request_id,
+                command: AgentCommand::Register {
+                    name: name.to_string(),
+                    capabilities: test_capabilities(),
+                },
+            };
+            let reply = scheme.execute_command(envelope, &caller);
+            responses.push(&reply).unwrap();
+        }
+        assert_eq!(responses.len(), 2);
+
+        let mut buf = [0u8; 256];
+        let mut replies = Vec::new();
+        while let Some(len) = responses.read(&mut buf) {
+            let reply: ResponseEnvelope = bincode::deserialize(&buf[..len]).unwrap();
+            replies.push(reply);
+        }
+
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0].request_id, 41);
+        assert!(matches!(replies[0].response, AgentResponse::Registered { agent_id: 1 }));
+        assert_eq!(replies[1].request_id, 42);
+        assert!(matches!(replies[1].response, AgentResponse::Registered { agent_id: 2 }));
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_mock_register_over_wire() {
+        let mut scheme = AgentScheme::mock_new();
+        let caller = Caller::new(100, 1000);
+
+        let command = CommandEnvelope {
+            request_id: 7,
+            command: AgentCommand::Register {
+                name: "Wire Agent".to_string(),
+                capabilities: test_capabilities(),
+            },
+        };
+        let buf = bincode::serialize(&command).unwrap();
+        let reply: ResponseEnvelope = bincode::deserialize(&scheme.mock_write_command(&buf, &caller, None).unwrap()).unwrap();
+
+        assert_eq!(reply.request_id, 7);
+        let agent_id = match reply.response {
+            AgentResponse::Registered { agent_id } => agent_id,
+            other => panic!("Expected Registered, got {:?}", other),
+        };
+        assert_eq!(scheme.get_agent_by_name("Wire Agent"), Some(agent_id));
+
+        // The registering process now owns the agent, and no other does
+        let unregister = CommandEnvelope {
+            request_id: 8,
+            command: AgentCommand::Unregister { agent_id },
+        };
+        let buf = bincode::serialize(&unregister).unwrap();
+        assert!(scheme.mock_write_command(&buf, &Caller::new(200, 1001), None).is_err());
+        let reply: ResponseEnvelope = bincode::deserialize(&scheme.mock_write_command(&buf, &caller, None).unwrap()).unwrap();
+        assert!(matches!(reply.response, AgentResponse::Success));
+
+        assert!(scheme.mock_write_command(b"not a command", &caller, None).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_npu_allocation_exhausts_devices() {
+        let mut scheme = AgentScheme::mock_new_with_npus(3);
+        assert_eq!(scheme.npu_pool.devices.len(), 3);
+
+        let agent_ids: Vec<AgentId> = (0..5)
+            .map(|i| scheme.mock_register_agent(format!("Agent {}", i)).unwrap())
+            .collect();
+
+        // Every agent registers, but only the first three get a device
+        assert_eq!(scheme.agents.len(), 5);
+        assert_eq!(scheme.npu_pool.allocation_map.len(), 3);
+        for agent_id in &agent_ids[..3] {
+            assert!(scheme.npu_pool.allocation_map.contains_key(agent_id));
+        }
+        for agent_id in &agent_ids[3..] {
+            assert!(!scheme.npu_pool.allocation_map.contains_key(agent_id));
+        }
+
+        // Each device is held by exactly one agent
+        let mut holders: Vec<AgentId> = scheme.npu_pool.devices.values()
+            .filter_map(|device| device.allocated_to)
+            .collect();
+        holders.sort();
+        assert_eq!(holders, agent_ids[..3].to_vec());
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_unregister_frees_npu() {
+        let mut scheme = AgentScheme::mock_new();
+        let agent_id = scheme.mock_register_agent("Inference Agent".to_string()).unwrap();
+
+        let npu_id = *scheme.npu_pool.allocation_map.get(&agent_id).unwrap();
+        {
+            let device = scheme.npu_pool.devices.get_mut(&npu_id).unwrap();
+            assert_eq!(device.allocated_to, Some(agent_id));
+            device.current_model = Some("test_model".to_string());
+            device.status = NPUStatus::Running;
+        }
+
+        scheme.unregister_agent(agent_id).unwrap();
+
+        let device = &scheme.npu_pool.devices[&npu_id];
+        assert_eq!(device.status, NPUStatus::Idle);
+        assert_eq!(device.allocated_to, None);
+        assert_eq!(device.current_model, None);
+        assert!(!scheme.npu_pool.allocation_map.contains_key(&agent_id));
+
+        // The freed device can be handed to a new agent
+        let next_id = scheme.mock_register_agent("Next Agent".to_string()).unwrap();
+        assert_eq!(scheme.npu_pool.allocation_map.get(&next_id), Some(&npu_id));
+    }
+
+    #[cfg(feature = "mock")]
+    fn inference_task(task_id: TaskId, agent_id: AgentId, model_id: &str, input_data: &[u8]) -> InferenceTask {
+        InferenceTask {
+            task_id,
+            agent_id,
+            model_id: model_id.to_string(),
+            input_data: input_data.to_vec(),
+            priority: InferencePriority::Normal,
+            max_latency: None,
+            submitted_at: 0,
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    fn next_inference_result(scheme: &mut AgentScheme, agent_id: AgentId) -> InferenceResult {
+        let message = scheme.receive_message(agent_id).expect("no inference result");
+        assert_eq!(message.message_type, MessageType::InferenceResponse);
+        message.as_inference_result().unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_inference_requires_loaded_model() {
+        let mut scheme = AgentScheme::mock_new();
+        let caller = Caller::new(100, 0);
+        let agent_id = scheme.mock_register_agent("Agent 1".to_string()).unwrap();
+
+        let load = CommandEnvelope {
+            request_id: 1,
+            command: AgentCommand::LoadModel {
+                model_id: "test_model".to_string(),
+                model_data: vec![1, 2, 3, 4],
+            },
+        };
+        assert!(matches!(scheme.execute_command(load, &caller).response, AgentResponse::Success));
+        assert!(scheme.npu_pool.is_model_loaded("test_model"));
+
+        for (task_id, model_id) in [(1, "test_model"), (2, "missing_model")] {
+            let submit = CommandEnvelope {
+                request_id: task_id as u64 + 1,
+                command: AgentCommand::SubmitInference {
+                    task: inference_task(task_id, agent_id, model_id, b"input"),
+                },
+            };
+            scheme.execute_command(submit, &caller);
+            scheme.process_inference_tasks();
+        }
+
+        let loaded = next_inference_result(&mut scheme, agent_id);
+        assert_eq!(loaded.task_id, 1);
+        assert!(loaded.success);
+
+        let missing = next_inference_result(&mut scheme, agent_id);
+        assert_eq!(missing.task_id, 2);
+        assert!(!missing.success);
+        assert!(missing.error.unwrap().contains("missing_model"));
+
+        assert_eq!(scheme.agents[&agent_id].resource_usage.inference_count.get(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_inference_output_depends_on_input() {
+        let mut scheme = AgentScheme::mock_new();
+        let agent_id = scheme.mock_register_agent("Agent 1".to_string()).unwrap();
+        scheme.npu_pool.load_model("test_model".to_string(), vec![0xAB; 16]).unwrap();
+
+        for (task_id, input) in [(1, &b"first input"[..]), (2, &b"second"[..])] {
+            scheme.npu_pool.task_queue.push_back(inference_task(task_id, agent_id, "test_model", input));
+            scheme.process_inference_tasks();
+
+            let result = next_inference_result(&mut scheme, agent_id);
+            assert!(result.success);
+            assert_eq!(result.task_id, task_id);
+            assert_eq!(result.output_data, input);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_edf_dispatches_nearest_deadline_and_drops_expired() {
+        let mut scheme = AgentScheme::mock_new();
+        let agent_id = scheme.mock_register_agent("Agent 1".to_string()).unwrap();
+        scheme.npu_pool.load_model("test_model".to_string(), vec![1]).unwrap();
+        scheme.set_scheduling_mode(SchedulingMode::EarliestDeadlineFirst);
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let with_deadline = |task_id: TaskId, submitted_at: u64, max_latency: Option<u64>| InferenceTask {
+            submitted_at,
+            max_latency: max_latency.map(std::time::Duration::from_secs),
+            ..inference_task(task_id, agent_id, "test_model", b"input")
+        };
+        for task in [
+            with_deadline(1, now, None),
+            with_deadline(2, now, Some(600)),
+            with_deadline(3, now, Some(60)),
+            with_deadline(4, now - 120, Some(1)),
+        ] {
+            scheme.npu_pool.submit_task(task).unwrap();
+        }
+
+        scheme.process_inference_tasks();
+        let expired = next_inference_result(&mut scheme, agent_id);
+        assert_eq!(expired.task_id, 4);
+        assert!(!expired.success);
+        assert!(expired.error.unwrap().contains("Timed out"));
+
+        let mut order = vec![next_inference_result(&mut scheme, agent_id).task_id];
+        while !scheme.npu_pool.task_queue.is_empty() {
+            scheme.process_inference_tasks();
+            order.push(next_inference_result(&mut scheme, agent_id).task_id);
+        }
+        assert_eq!(order, vec![3, 2, 1]);
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_submit_inference_rejected_when_queue_full() {
+        let mut scheme = AgentScheme::mock_new();
+        let caller = Caller::new(100, 0);
+        let agent_id = scheme.mock_register_agent("Agent 1".to_string()).unwrap();
+        scheme.npu_pool.load_model("test_model".to_string(), vec![1]).unwrap();
+        scheme.set_max_queued_tasks(2);
+
+        let submit = |scheme: &mut AgentScheme, task_id: TaskId| {
+            let envelope = CommandEnvelope {
+                request_id: task_id as u64,
+                command: AgentCommand::SubmitInference {
+                    task: inference_task(task_id, agent_id, "test_model", b"input"),
+                },
+            };
+            scheme.execute_command(envelope, &caller).response
+        };
+
+        assert!(matches!(submit(&mut scheme, 1), AgentResponse::Success));
+        assert!(matches!(submit(&mut scheme, 2), AgentResponse::Success));
+        match submit(&mut scheme, 3) {
+            AgentResponse::Error { message } => assert_eq!(message, "inference queue full"),
+            other => panic!("expected queue full error, got {:?}", other),
+        }
+        assert_eq!(scheme.npu_pool.task_queue.len(), 2);
+
+        // Processing a task frees a slot
+        scheme.process_inference_tasks();
+        assert!(matches!(submit(&mut scheme, 4), AgentResponse::Success));
+        assert_eq!(scheme.npu_pool.task_queue.len(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_message_queue_limit_rejects() {
+        let mut scheme = AgentScheme::mock_new();
+        let sender = scheme.mock_register_agent("Sender".to_string()).unwrap();
+        let receiver = scheme.mock_register_agent("Receiver".to_string()).unwrap();
+        scheme.set_message_queue_limit(receiver, 3, QueueOverflow::Reject).unwrap();
+
+        for i in 0..3u8 {
+            scheme.mock_send_message(sender, receiver, &[i]).unwrap();
+        }
+        let err = scheme.mock_send_message(sender, receiver, &[3]).unwrap_err();
+        assert!(err.contains("queue full"));
+
+        // The queued messages are the first three, untouched
+        for i in 0..3u8 {
+            assert_eq!(scheme.mock_receive_message(receiver).unwrap(), vec![i]);
+        }
+        assert!(scheme.mock_receive_message(receiver).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_message_queue_limit_drops_oldest() {
+        let mut scheme = AgentScheme::mock_new();
+        let sender = scheme.mock_register_agent("Sender".to_string()).unwrap();
+        let receiver = scheme.mock_register_agent("Receiver".to_string()).unwrap();
+        scheme.set_message_queue_limit(receiver, 3, QueueOverflow::DropOldest).unwrap();
+
+        for i in 0..5u8 {
+            scheme.mock_send_message(sender, receiver, &[i]).unwrap();
+        }
+        assert_eq!(scheme.agents[&receiver].dropped_messages.get(), 2);
+
+        for i in 2..5u8 {
+            assert_eq!(scheme.mock_receive_message(receiver).unwrap(), vec![i]);
+        }
+    }
+
+    /// Echo backend that takes a fixed time per task
+    #[cfg(feature = "mock")]
+    struct SlowBackend(std::time::Duration);
+
+    #[cfg(feature = "mock")]
+    impl InferenceBackend for SlowBackend {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn run(&mut self, _model_id: &ModelId, _model_data: &[u8], input: &[u8]) -> Result<Vec<u8>, String> {
+            std::thread::sleep(self.0);
+            Ok(input.to_vec())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_inference_time_tracked() {
+        let delay = std::time::Duration::from_millis(5);
+        let mut scheme = AgentScheme::mock_new();
+        scheme.set_inference_backend(Box::new(SlowBackend(delay)));
+        let agent_id = scheme.mock_register_agent("Agent 1".to_string()).unwrap();
+        scheme.npu_pool.load_model("test_model".to_string(), vec![1]).unwrap();
+        assert_eq!(scheme.agents[&agent_id].resource_usage.average_inference_time(), None);
+
+        let mut previous_total = std::time::Duration::ZERO;
+        for task_id in 1..=3 {
+            scheme.npu_pool.submit_task(inference_task(task_id, agent_id, "test_model", b"input")).unwrap();
+            scheme.process_inference_tasks();
+
+            let total = scheme.agents[&agent_id].resource_usage.total_inference_time;
+            assert!(total >= previous_total + delay);
+            previous_total = total;
+        }
+
+        let usage = &scheme.agents[&agent_id].resource_usage;
+        assert_eq!(usage.inference_count.get(), 3);
+        let average = usage.average_inference_time().unwrap();
+        assert!(average >= delay);
+        assert!(average <= usage.total_inference_time);
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_metrics_text() {
+        let mut scheme = AgentScheme::mock_new_with_npus(2);
+        let agent_id = scheme.mock_register_agent("Agent 1".to_string()).unwrap();
+        scheme.mock_register_agent("Agent 2".to_string()).unwrap();
+        scheme.npu_pool.load_model("test_model".to_string(), vec![1]).unwrap();
+        for task_id in 1..=3 {
+            scheme.npu_pool.submit_task(inference_task(task_id, agent_id, "test_model", b"input")).unwrap();
+        }
+        scheme.process_inference_tasks();
+        scheme.process_inference_tasks();
+
+        let metrics = scheme.metrics_text();
+        assert!(metrics.contains("# TYPE agentd_agents gauge"));
+        assert!(metrics.contains("agentd_agents{status=\"ready\"} 2"));
+        assert!(metrics.contains("agentd_agents{status=\"busy\"} 0"));
+        assert!(metrics.contains("agentd_inferences_total 2"));
+        assert!(metrics.contains("agentd_inference_queue_depth 1"));
+        assert!(metrics.contains("agentd_npu_devices 2"));
+        assert!(metrics.contains("agentd_npu_utilization 1"));
+        assert!(metrics.lines().all(|line| line.starts_with('#') || line.split(' ').count() == 2));
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_status_json_reports_device_health() {
+        let mut scheme = AgentScheme::mock_new_with_npus(2);
+        scheme.mock_register_agent("Agent 1".to_string()).unwrap();
+
+        let status = scheme.status_json();
+        let devices = status["devices"].as_array().unwrap();
+        assert_eq!(devices.len(), 2);
+        for (id, device) in devices.iter().enumerate() {
+            assert_eq!(device["id"], id);
+            let health = &device["health"];
+            assert_eq!(health["is_healthy"], true);
+            assert!(health["temperature_celsius"].as_f64().unwrap() > 0.0);
+            assert!(health["power_consumption_watts"].as_f64().unwrap() > 0.0);
+            assert!(device["utilization"].is_number());
+        }
+
+        let usage = &status["usage"];
+        assert_eq!(usage["total_devices"], 2);
+        assert_eq!(usage["active_devices"], 1);
+        assert_eq!(usage["compute_utilization"], 0.5);
+
+        let empty = AgentScheme::mock_new_with_npus(0).status_json();
+        assert!(empty["devices"].as_array().unwrap().is_empty());
+        assert_eq!(empty["usage"]["compute_utilization"], 0.0);
+        assert_eq!(empty["usage"]["power_consumption_watts"], 0.0);
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_npus_from_manifest() {
+        let manifest = NPUManifest::from_json(r#"{
+            "devices": [
+                { "name": "Edge NPU", "max_memory_mb": 2048, "supported_precision": ["FP16", "INT8"],
+                  "max_batch_size": 8, "ops_per_second": 11000000 },
+                { "name": "Big NPU", "max_memory_mb": 16384, "supported_precision": ["FP32"],
+                  "max_batch_size": 64, "ops_per_second": 45000000 }
+            ]
+        }"#).unwrap();
+        let scheme = AgentScheme::mock_new_with_manifest(&manifest);
+        assert_eq!(scheme.npu_pool.devices.len(), 2);
+
+        let edge = &scheme.npu_pool.devices[&0];
+        assert_eq!(edge.name, "Edge NPU");
+        assert_eq!(edge.capabilities.max_memory_mb, 2048);
+        assert_eq!(edge.capabilities.supported_precision, vec![Precision::FP16, Precision::INT8]);
+        assert_eq!(edge.capabilities.max_batch_size, 8);
+
+        let big = &scheme.npu_pool.devices[&1];
+        assert_eq!(big.name, "Big NPU");
+        assert_eq!(big.capabilities.ops_per_second, 45000000);
+        assert_eq!(big.status, NPUStatus::Idle);
+    }
+
+    #[test]
+    fn test_malformed_npu_manifest_rejected() {
+        assert!(NPUManifest::from_json("{ devices: ").unwrap_err().contains("Invalid NPU manifest"));
+        assert!(NPUManifest::from_json(r#"{ "devices": [] }"#).is_err());
+        assert!(NPUManifest::from_json(r#"{ "devices": [{ "name": "x" }] }"#).is_err());
+        assert!(NPUManifest::load("/nonexistent/npus.json").unwrap_err().contains("Cannot read"));
+    }
+
+    #[test]
+    #[cfg(feature = "ai")]
+    fn test_onnx_backend_runs_model() {
+        use crate::inference::OnnxBackend;
+
+        let model = include_bytes!("../../../agent_sdk/fixtures/multi_output.onnx");
+        let model_id = "multi".to_string();
+        let input: Vec<u8> = [0.5f32; 4].iter().flat_map(|value| value.to_le_bytes()).collect();
+        let mut backend = OnnxBackend::new().unwrap();
+
+        let output = backend.run(&model_id, model, &input).unwrap();
+        assert_eq!(output.len(), 16);
+        // The session is reused until the model is loaded again
+        assert_eq!(backend.run(&model_id, &[], &input).unwrap(), output);
+        backend.model_loaded(&model_id);
+        assert!(backend.run(&model_id, &[], &input).is_err());
+        assert!(backend.run(&model_id, model, &input[..3]).is_err());
+    }
 }
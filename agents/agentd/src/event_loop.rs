@@ -0,0 +1,142 @@
+//! Main loop policy for the agentd scheme socket
+
+use log::warn;
+use std::fmt::Display;
+
+/// EOFs in a row, with no successful socket I/O between them, after which
+/// the socket is considered gone rather than a client having disconnected
+pub const DEFAULT_MAX_CONSECUTIVE_EOFS: u32 = 16;
+
+/// What the main loop should do after a socket operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopAction {
+    /// Keep serving requests
+    Continue,
+    /// Stop the daemon cleanly
+    Exit,
+}
+
+/// Classifies socket results so a client disconnect does not stop the daemon
+///
+/// A single EOF is treated as benign: the loop keeps waiting for readiness
+/// and new clients can still open the scheme. Only errors, or a long run of
+/// EOFs with no successful I/O in between, end the loop.
+#[derive(Debug)]
+pub struct EofPolicy {
+    consecutive_eofs: u32,
+    max_consecutive_eofs: u32,
+}
+
+impl Default for EofPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONSECUTIVE_EOFS)
+    }
+}
+
+impl EofPolicy {
+    pub fn new(max_consecutive_eofs: u32) -> Self {
+        Self {
+            consecutive_eofs: 0,
+            max_consecutive_eofs,
+        }
+    }
+
+    /// Decide how to proceed after `operation` returned `result`, where
+    /// `Ok(false)` means EOF. Errors are fatal and returned as `Err`.
+    pub fn on_io<E: Display>(&mut self, operation: &str, result: Result<bool, E>) -> Result<LoopAction, String> {
+        match result {
+            Ok(true) => {
+                self.consecutive_eofs = 0;
+                Ok(LoopAction::Continue)
+            },
+            Ok(false) => {
+                self.consecutive_eofs += 1;
+                if self.consecutive_eofs > self.max_consecutive_eofs {
+                    warn!("Agent socket {}: {} EOFs in a row, shutting down", operation, self.consecutive_eofs);
+                    Ok(LoopAction::Exit)
+                } else {
+                    warn!("Agent socket {}: EOF, waiting for new clients", operation);
+                    Ok(LoopAction::Continue)
+                }
+            },
+            Err(err) => Err(format!("{} error: {}", operation, err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eof_is_not_fatal() {
+        let mut policy = EofPolicy::new(2);
+        assert_eq!(policy.on_io::<String>("read_requests", Ok(false)), Ok(LoopAction::Continue));
+        assert_eq!(policy.on_io::<String>("read_requests", Ok(true)), Ok(LoopAction::Continue));
+
+        // Successful I/O resets the run, so only an unbroken run exits
+        assert_eq!(policy.on_io::<String>("read_requests", Ok(false)), Ok(LoopAction::Continue));
+        assert_eq!(policy.on_io::<String>("write_responses", Ok(false)), Ok(LoopAction::Continue));
+        assert_eq!(policy.on_io::<String>("read_requests", Ok(false)), Ok(LoopAction::Exit));
+
+        assert!(policy.on_io("read_requests", Err("bad fd")).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_loop_serves_new_client_after_eof() {
+        use crate::agent::AgentScheme;
+        use crate::types::*;
+
+        let mut scheme = AgentScheme::mock_new();
+        let mut policy = EofPolicy::default();
+        let peer = scheme.register_agent_as("Peer".to_string(), AgentCapabilities::default(), &Caller::new(200, 1000)).unwrap();
+
+        // Each round mirrors one pass of the main loop: read, serve, write
+        let mut served = Vec::new();
+        let rounds: [(Result<bool, String>, Option<&str>); 4] = [
+            (Ok(true), Some("First client")),
+            // The first client goes away and the socket reports EOF
+            (Ok(false), None),
+            (Ok(false), None),
+            (Ok(true), Some("Second client")),
+        ];
+        for (pid, (read, client)) in (100..).zip(rounds) {
+            assert_eq!(policy.on_io("read_requests", read), Ok(LoopAction::Continue));
+            if let Some(name) = client {
+                let id = scheme.register_agent_as(name.to_string(), AgentCapabilities::default(), &Caller::new(pid, 1000)).unwrap();
+                scheme.mock_send_message(id, peer, name.as_bytes()).unwrap();
+                served.push(id);
+            }
+            assert_eq!(policy.on_io::<String>("write_responses", Ok(true)), Ok(LoopAction::Continue));
+        }
+
+        assert_eq!(served.len(), 2);
+        assert_eq!(scheme.mock_receive_message(peer).unwrap(), b"First client".to_vec());
+        assert_eq!(scheme.mock_receive_message(peer).unwrap(), b"Second client".to_vec());
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_client_reconnects_after_disconnect() {
+        use crate::agent::AgentScheme;
+        use crate::types::*;
+
+        let mut scheme = AgentScheme::mock_new();
+        let caller = Caller::new(100, 1000);
+        let peer = scheme.register_agent_as("Peer".to_string(), AgentCapabilities::default(), &Caller::new(200, 1000)).unwrap();
+        let first = scheme.register_agent_as("Client".to_string(), AgentCapabilities::default(), &caller).unwrap();
+        scheme.mock_send_message(first, peer, b"before").unwrap();
+
+        // The client unregisters and goes away
+        scheme.unregister_agent(first).unwrap();
+        assert!(scheme.mock_send_message(peer, first, b"lost").is_err());
+
+        // The same process reconnects and talks to the agent that stayed
+        let again = scheme.register_agent_as("Client".to_string(), AgentCapabilities::default(), &caller).unwrap();
+        assert_ne!(again, first);
+        scheme.mock_send_message(again, peer, b"after").unwrap();
+        assert_eq!(scheme.mock_receive_message(peer).unwrap(), b"before".to_vec());
+        assert_eq!(scheme.mock_receive_message(peer).unwrap(), b"after".to_vec());
+    }
+}
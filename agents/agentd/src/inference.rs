@@ -0,0 +1,104 @@
+//! Inference backends used by the agent scheme to execute queued tasks
+
+use crate::types::ModelId;
+
+#[cfg(any(feature = "redox", feature = "ai"))]
+use {
+    ort::{Environment, Session, SessionBuilder, Value},
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// Executes an inference task against a loaded model
+pub trait InferenceBackend: Send {
+    /// Short name used in logs
+    fn name(&self) -> &str;
+
+    /// Run `input` through the model and return the raw output bytes
+    fn run(&mut self, model_id: &ModelId, model_data: &[u8], input: &[u8]) -> Result<Vec<u8>, String>;
+
+    /// Forget anything cached for `model_id`, whose data was just replaced
+    fn model_loaded(&mut self, _model_id: &ModelId) {}
+}
+
+/// Backend that returns its input unchanged, like the SDK's mock NPU device
+#[cfg(any(feature = "mock", test))]
+#[derive(Debug, Default)]
+pub struct EchoBackend;
+
+#[cfg(any(feature = "mock", test))]
+impl InferenceBackend for EchoBackend {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn run(&mut self, _model_id: &ModelId, _model_data: &[u8], input: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(input.to_vec())
+    }
+}
+
+/// Backend running tasks through ONNX Runtime on the accelerators it was
+/// built with. Inputs and outputs are little-endian f32 tensors of shape
+/// `[1, len]`; each model's session is built on its first task.
+#[cfg(any(feature = "redox", feature = "ai"))]
+pub struct OnnxBackend {
+    environment: Arc<Environment>,
+    sessions: HashMap<ModelId, Session>,
+}
+
+#[cfg(any(feature = "redox", feature = "ai"))]
+impl OnnxBackend {
+    pub fn new() -> Result<Self, String> {
+        let environment = Environment::builder()
+            .with_name("agentd")
+            .build()
+            .map_err(|e| format!("Failed to create ONNX Runtime environment: {}", e))?
+            .into_arc();
+        Ok(Self {
+            environment,
+            sessions: HashMap::new(),
+        })
+    }
+}
+
+#[cfg(any(feature = "redox", feature = "ai"))]
+impl InferenceBackend for OnnxBackend {
+    fn name(&self) -> &str {
+        "onnx"
+    }
+
+    fn run(&mut self, model_id: &ModelId, model_data: &[u8], input: &[u8]) -> Result<Vec<u8>, String> {
+        if input.len() % 4 != 0 {
+            return Err(format!("Input of {} bytes is not a whole number of f32 values", input.len()));
+        }
+        if !self.sessions.contains_key(model_id) {
+            let session = SessionBuilder::new(&self.environment)
+                .and_then(|builder| builder.with_model_from_memory(model_data))
+                .map_err(|e| format!("Failed to load model {}: {}", model_id, e))?;
+            self.sessions.insert(model_id.clone(), session);
+        }
+        let session = &self.sessions[model_id];
+
+        let values: Vec<f32> = input.chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        let array = ndarray::Array::from_shape_vec((1, values.len()), values)
+            .map_err(|e| format!("Invalid input shape: {}", e))?
+            .into_dyn();
+        let array = ndarray::CowArray::from(array);
+        let value = Value::from_array(session.allocator(), &array)
+            .map_err(|e| format!("Failed to create input tensor: {}", e))?;
+
+        let outputs = session.run(vec![value])
+            .map_err(|e| format!("Inference on model {} failed: {}", model_id, e))?;
+        let output = outputs.first()
+            .ok_or_else(|| format!("Model {} produced no output", model_id))?
+            .try_extract::<f32>()
+            .map_err(|e| format!("Failed to read output of model {}: {}", model_id, e))?;
+        let bytes = output.view().iter().flat_map(|value| value.to_le_bytes()).collect();
+        Ok(bytes)
+    }
+
+    fn model_loaded(&mut self, model_id: &ModelId) {
+        self.sessions.remove(model_id);
+    }
+}
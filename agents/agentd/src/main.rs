@@ -7,9 +7,6 @@
 
 use log::{info, error};
 
-#[cfg(feature = "redox")]
-use log::warn;
-
 #[cfg(feature = "redox")]
 use {
     std::sync::Mutex,
@@ -18,9 +15,15 @@ use {
 };
 
 mod agent;
+#[cfg(any(feature = "redox", test))]
+mod event_loop;
+mod inference;
 mod types;
 
 pub use agent::AgentScheme;
+#[cfg(feature = "mock")]
+pub use inference::EchoBackend;
+pub use inference::InferenceBackend;
 pub use types::*;
 
 fn main() {
@@ -65,7 +68,7 @@ fn run_daemon(daemon: redox_daemon::Daemon) -> anyhow::Result<()> {
     let agent_socket = Socket::nonblock("agent")
         .map_err(|e| anyhow::anyhow!("failed to create agent scheme: {}", e))?;
     
-    let agent_scheme = Mutex::new(AgentScheme::new(&agent_socket));
+    let agent_scheme = Mutex::new(AgentScheme::new(&agent_socket).map_err(|e| anyhow::anyhow!(e))?);
     let mut agent_handler = ReadinessBased::new(&agent_socket, 16);
 
     info!("Agent scheme initialized on socket 'agent'");
@@ -91,26 +94,18 @@ fn run_daemon(daemon: redox_daemon::Daemon) -> anyhow::Result<()> {
     info!("NebulaOS Agent Daemon is ready!");
 
     // Main event loop
-    let mut agent_eof = false;
-    while !agent_eof {
-        let Some(event_res) = event_queue.next() else {
-            break;
-        };
+    let mut eof_policy = event_loop::EofPolicy::default();
+    while let Some(event_res) = event_queue.next() {
         let event = event_res.map_err(|e| anyhow::anyhow!("error in event queue: {}", e))?;
 
         match event.user_data {
             EventSource::AgentSocket => {
                 // Handle agent scheme events
-                if !agent_eof {
-                    // 1. Read requests
-                    match agent_handler.read_requests() {
-                        Ok(true) => {} // Success
-                        Ok(false) => {
-                            warn!("Agent socket EOF");
-                            agent_eof = true;
-                        }
-                        Err(err) => return Err(anyhow::anyhow!("read_requests error: {}", err)),
-                    }
+                // 1. Read requests
+                let action = eof_policy.on_io("read_requests", agent_handler.read_requests())
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                if action == event_loop::LoopAction::Exit {
+                    break;
                 }
 
                 // 2. Process requests
@@ -122,13 +117,10 @@ fn run_daemon(daemon: redox_daemon::Daemon) -> anyhow::Result<()> {
                     .map_err(|e| anyhow::anyhow!("poll_all_requests error: {}", e))?;
 
                 // 4. Write responses
-                match agent_handler.write_responses() {
-                    Ok(true) => {} // Success
-                    Ok(false) => {
-                        warn!("Agent socket write EOF");
-                        agent_eof = true;
-                    }
-                    Err(err) => return Err(anyhow::anyhow!("write_responses error: {}", err)),
+                let action = eof_policy.on_io("write_responses", agent_handler.write_responses())
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                if action == event_loop::LoopAction::Exit {
+                    break;
                 }
             }
         }
@@ -151,7 +143,8 @@ fn run_mock() -> anyhow::Result<()> {
     info!("  - Basic message passing test");
     info!("  - Inference task simulation");
 
-    // Simulate some agent operations
+    // Simulate some agent operations; these go through the same bincode
+    // command and response envelopes as the Redox scheme
     let agent1_id = agent_scheme.mock_register_agent("test_agent_1".to_string())
         .map_err(|e| anyhow::anyhow!(e))?;
     let agent2_id = agent_scheme.mock_register_agent("test_agent_2".to_string())
@@ -1,6 +1,8 @@
 //! Type definitions for the NebulaOS Agent System
 
 use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 
@@ -26,10 +28,36 @@ pub struct AgentContext {
     pub capabilities: AgentCapabilities,
     pub resource_usage: ResourceUsage,
     pub message_queue: VecDeque<AgentMessage>,
+    pub max_message_queue_size: usize,
+    pub queue_overflow: QueueOverflow,
+    pub dropped_messages: Counter,
     pub created_at: Instant,
     pub last_activity: Instant,
 }
 
+/// Default cap on messages waiting in a single agent's queue
+pub const DEFAULT_MAX_MESSAGE_QUEUE_SIZE: usize = 1000;
+
+/// What to do when a message arrives for an agent whose queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueOverflow {
+    /// Refuse the new message and report an error to the sender
+    #[default]
+    Reject,
+    /// Discard the oldest queued message to make room
+    DropOldest,
+}
+
+/// Whether the scheme lets several agents share a name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NamePolicy {
+    /// Names may repeat; lookups by name find the earliest registration
+    #[default]
+    Lenient,
+    /// Registering a name that is already taken fails
+    Strict,
+}
+
 impl AgentContext {
     pub fn new(id: AgentId, name: String) -> Self {
         let now = Instant::now();
@@ -41,6 +69,9 @@ impl AgentContext {
             capabilities: AgentCapabilities::default(),
             resource_usage: ResourceUsage::default(),
             message_queue: VecDeque::new(),
+            max_message_queue_size: DEFAULT_MAX_MESSAGE_QUEUE_SIZE,
+            queue_overflow: QueueOverflow::default(),
+            dropped_messages: Counter::default(),
             created_at: now,
             last_activity: now,
         }
@@ -49,6 +80,34 @@ impl AgentContext {
     pub fn update_activity(&mut self) {
         self.last_activity = Instant::now();
     }
+
+    /// Queue a message for this agent, applying its overflow policy when
+    /// the queue is at `max_message_queue_size`
+    pub fn enqueue_message(&mut self, message: AgentMessage) -> Result<(), String> {
+        if self.message_queue.len() >= self.max_message_queue_size {
+            match self.queue_overflow {
+                QueueOverflow::Reject => {
+                    return Err(format!("Message queue full for agent {}", self.id));
+                },
+                QueueOverflow::DropOldest => {
+                    self.message_queue.pop_front();
+                    self.dropped_messages.increment();
+                },
+            }
+        }
+        self.message_queue.push_back(message);
+        Ok(())
+    }
+
+    /// Time since the agent was created
+    pub fn uptime(&self) -> Duration {
+        self.created_at.elapsed()
+    }
+
+    /// Time since the agent's last recorded activity
+    pub fn idle_time(&self) -> Duration {
+        self.last_activity.elapsed()
+    }
 }
 
 /// Current status of an agent
@@ -69,6 +128,10 @@ pub struct AgentCapabilities {
     pub supported_models: Vec<String>,
     pub max_tensor_size: usize,
     pub preferred_npu: Option<NPUId>,
+    /// Agent reads and writes lz4-compressed payloads; others only ever
+    /// see and may only send uncompressed ones
+    #[serde(default)]
+    pub supports_compression: bool,
 }
 
 /// Resource usage tracking
@@ -76,10 +139,72 @@ pub struct AgentCapabilities {
 pub struct ResourceUsage {
     pub memory_mb: u64,
     pub npu_utilization: f32,
-    pub inference_count: u64,
+    pub inference_count: Counter,
     pub total_inference_time: Duration,
 }
 
+impl ResourceUsage {
+    /// Record one completed inference that took `latency`
+    pub fn record_inference(&mut self, latency: Duration) {
+        self.inference_count.increment();
+        self.total_inference_time += latency;
+    }
+
+    /// Mean time per completed inference, if any have run
+    pub fn average_inference_time(&self) -> Option<Duration> {
+        let count = self.inference_count.get();
+        if count == 0 {
+            return None;
+        }
+        Some(self.total_inference_time.div_f64(count as f64))
+    }
+}
+
+/// Lock-free counter for usage metrics
+///
+/// Cloning copies the current value. Use [`Counter::share`] for a handle
+/// that sees later increments.
+#[derive(Debug, Default)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Clone for Counter {
+    fn clone(&self) -> Self {
+        Self::new(self.get())
+    }
+}
+
+impl Counter {
+    pub fn new(value: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(value)))
+    }
+
+    /// Handle to the same value
+    pub fn share(&self) -> Counter {
+        Counter(self.0.clone())
+    }
+
+    /// Increment by one, returning the new value
+    pub fn increment(&self) -> u64 {
+        self.add(1)
+    }
+
+    /// Increment by `n`, returning the new value
+    pub fn add(&self, n: u64) -> u64 {
+        self.0.fetch_add(n, Ordering::Relaxed) + n
+    }
+
+    /// Current value
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Payloads larger than this are compressed before crossing the scheme
+pub const COMPRESSION_THRESHOLD: usize = 4096;
+
+/// Largest payload a message may carry once decompressed
+pub const MAX_PAYLOAD_SIZE: usize = 16 * 1024 * 1024;
+
 /// Messages passed between agents
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentMessage {
@@ -88,6 +213,99 @@ pub struct AgentMessage {
     pub message_type: MessageType,
     pub payload: Vec<u8>,
     pub timestamp: u64, // Unix timestamp
+    pub compressed: bool, // payload is lz4-compressed
+}
+
+impl AgentMessage {
+    /// Compress the payload if it is above `COMPRESSION_THRESHOLD` and
+    /// compression actually makes it smaller
+    pub fn compress_payload(&mut self) {
+        if self.compressed || self.payload.len() <= COMPRESSION_THRESHOLD {
+            return;
+        }
+
+        let compressed = lz4_flex::compress_prepend_size(&self.payload);
+        if compressed.len() < self.payload.len() {
+            self.payload = compressed;
+            self.compressed = true;
+        }
+    }
+
+    /// Restore the original payload of a compressed message
+    ///
+    /// The size prefix is sender-controlled, so it is checked against
+    /// `MAX_PAYLOAD_SIZE` before anything is allocated.
+    pub fn decompress_payload(&mut self) -> Result<(), String> {
+        if !self.compressed {
+            return Ok(());
+        }
+
+        let (prefix, block) = self.payload.split_first_chunk::<4>()
+            .ok_or_else(|| "Compressed payload is missing its size prefix".to_string())?;
+        let size = u32::from_le_bytes(*prefix) as usize;
+        if size > MAX_PAYLOAD_SIZE {
+            return Err(format!(
+                "Compressed payload claims {} bytes, more than the {} allowed",
+                size, MAX_PAYLOAD_SIZE
+            ));
+        }
+        self.payload = lz4_flex::decompress(block, size)
+            .map_err(|e| format!("Failed to decompress payload: {}", e))?;
+        self.compressed = false;
+        Ok(())
+    }
+
+    /// Serialize for the scheme, compressing large payloads if the reader
+    /// negotiated compression
+    pub fn to_wire(&self, compress: bool) -> Result<Vec<u8>, String> {
+        let mut message = self.clone();
+        if compress {
+            message.compress_payload();
+        }
+        bincode::serialize(&message).map_err(|e| format!("Failed to serialize message: {}", e))
+    }
+
+    /// Build the message delivering `result` to agent `to`. If the result
+    /// cannot be serialized, an `Error` message describing why is returned.
+    pub fn inference_result(to: AgentId, result: &InferenceResult) -> Self {
+        let (message_type, payload) = match bincode::serialize(result) {
+            Ok(payload) => (MessageType::InferenceResponse, payload),
+            Err(e) => (
+                MessageType::Error,
+                format!("Failed to serialize result of task {}: {}", result.task_id, e).into_bytes(),
+            ),
+        };
+
+        Self {
+            from: 0,
+            to,
+            message_type,
+            payload,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            compressed: false,
+        }
+    }
+
+    /// Decode the result carried by an `InferenceResponse` message
+    pub fn as_inference_result(&self) -> Result<InferenceResult, String> {
+        match self.message_type {
+            MessageType::InferenceResponse => bincode::deserialize(&self.payload)
+                .map_err(|e| format!("Failed to deserialize inference result: {}", e)),
+            MessageType::Error => Err(String::from_utf8_lossy(&self.payload).into_owned()),
+            ref other => Err(format!("Expected an inference response, got {:?}", other)),
+        }
+    }
+
+    /// Deserialize a message read from the scheme, decompressing its payload
+    pub fn from_wire(bytes: &[u8]) -> Result<Self, String> {
+        let mut message: AgentMessage = bincode::deserialize(bytes)
+            .map_err(|e| format!("Failed to deserialize message: {}", e))?;
+        message.decompress_payload()?;
+        Ok(message)
+    }
 }
 
 /// Types of messages agents can send
@@ -117,6 +335,29 @@ pub struct InferenceTask {
     pub submitted_at: u64, // Unix timestamp
 }
 
+impl InferenceTask {
+    /// Absolute deadline since the Unix epoch: submission time plus `max_latency`
+    pub fn deadline(&self) -> Option<Duration> {
+        self.max_latency
+            .map(|max_latency| Duration::from_secs(self.submitted_at) + max_latency)
+    }
+
+    pub fn is_expired(&self, now: Duration) -> bool {
+        self.deadline().is_some_and(|deadline| deadline <= now)
+    }
+}
+
+/// Order in which queued inference tasks are dispatched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchedulingMode {
+    /// Dispatch in submission order
+    #[default]
+    Fifo,
+    /// Dispatch the task with the nearest deadline first, dropping tasks
+    /// whose deadline passed while queued. Tasks without a deadline run last.
+    EarliestDeadlineFirst,
+}
+
 /// Priority levels for inference tasks
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum InferencePriority {
@@ -136,6 +377,34 @@ pub struct InferenceResult {
     pub error: Option<String>,
 }
 
+/// Identity of the process issuing scheme commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Caller {
+    pub pid: u32,
+    pub uid: u32,
+}
+
+impl Caller {
+    pub fn new(pid: u32, uid: u32) -> Self {
+        Self { pid, uid }
+    }
+
+    /// Privileged callers (root) may manage any agent
+    pub fn is_privileged(&self) -> bool {
+        self.uid == 0
+    }
+}
+
+#[cfg(feature = "redox")]
+impl From<&redox_scheme::CallerCtx> for Caller {
+    fn from(ctx: &redox_scheme::CallerCtx) -> Self {
+        Self {
+            pid: ctx.pid as u32,
+            uid: ctx.uid,
+        }
+    }
+}
+
 /// Commands that can be sent to the agent scheme
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AgentCommand {
@@ -185,6 +454,57 @@ pub enum AgentResponse {
     Error { message: String },
 }
 
+/// A command tagged with a client-chosen id, echoed in its reply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandEnvelope {
+    pub request_id: u64,
+    pub command: AgentCommand,
+}
+
+/// A response tagged with the id of the command that produced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseEnvelope {
+    pub request_id: u64,
+    pub response: AgentResponse,
+}
+
+/// Serialized replies waiting to be read from a control handle, in
+/// the order their commands were written
+#[derive(Debug, Default)]
+pub struct ResponseQueue {
+    pending: VecDeque<Vec<u8>>,
+}
+
+impl ResponseQueue {
+    pub fn push(&mut self, reply: &ResponseEnvelope) -> Result<(), String> {
+        let bytes = bincode::serialize(reply)
+            .map_err(|e| format!("Failed to serialize response: {}", e))?;
+        self.pending.push_back(bytes);
+        Ok(())
+    }
+
+    /// Copy the next reply into `buf`. Each read returns bytes from a single
+    /// reply; a reply larger than `buf` is continued on the next read.
+    pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let front = self.pending.front_mut()?;
+        let len = std::cmp::min(buf.len(), front.len());
+        buf[..len].copy_from_slice(&front[..len]);
+        front.drain(..len);
+        if front.is_empty() {
+            self.pending.pop_front();
+        }
+        Some(len)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
 /// NPU device capabilities and status
 #[derive(Debug, Clone)]
 pub struct NPUDevice {
@@ -214,8 +534,70 @@ pub enum NPUStatus {
     Error(String),
 }
 
+/// Simulated temperature of an idle device
+const IDLE_TEMPERATURE_CELSIUS: f32 = 35.0;
+/// Simulated temperature of a device loading or running a model
+const BUSY_TEMPERATURE_CELSIUS: f32 = 55.0;
+/// Simulated power draw of an idle device
+const IDLE_POWER_WATTS: f32 = 2.0;
+/// Simulated power draw of a device loading or running a model
+const BUSY_POWER_WATTS: f32 = 10.0;
+
+/// Health of one NPU device, serialized with the same fields as the SDK's
+/// `DeviceHealth`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NPUDeviceHealth {
+    pub is_healthy: bool,
+    pub temperature_celsius: f32,
+    pub power_consumption_watts: f32,
+    pub memory_errors: u32,
+    pub compute_errors: u32,
+    pub status_message: String,
+}
+
+/// Pool-wide NPU usage, serialized with the same fields as the SDK's
+/// `NpuUsageStats`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NPUUsageStats {
+    pub total_devices: usize,
+    /// Devices allocated to an agent or loading or running a model
+    pub active_devices: usize,
+    /// Fraction of devices that are active (0.0 to 1.0)
+    pub compute_utilization: f64,
+    /// Fraction of devices holding a model (0.0 to 1.0)
+    pub memory_utilization: f64,
+    /// Average power draw per device in watts
+    pub power_consumption_watts: f64,
+    pub queued_tasks: usize,
+}
+
+impl NPUDevice {
+    /// Whether the device is allocated or busy with a model
+    pub fn is_active(&self) -> bool {
+        self.allocated_to.is_some() || matches!(self.status, NPUStatus::Loading | NPUStatus::Running)
+    }
+
+    /// Health reading for the device. The scheme has no hardware sensors,
+    /// so temperature and power are simulated from the device's status.
+    pub fn health(&self) -> NPUDeviceHealth {
+        let busy = matches!(self.status, NPUStatus::Loading | NPUStatus::Running);
+        let (is_healthy, status_message) = match &self.status {
+            NPUStatus::Error(message) => (false, message.clone()),
+            status => (true, format!("{:?}", status)),
+        };
+        NPUDeviceHealth {
+            is_healthy,
+            temperature_celsius: if busy { BUSY_TEMPERATURE_CELSIUS } else { IDLE_TEMPERATURE_CELSIUS },
+            power_consumption_watts: if busy { BUSY_POWER_WATTS } else { IDLE_POWER_WATTS },
+            memory_errors: 0,
+            compute_errors: 0,
+            status_message,
+        }
+    }
+}
+
 /// Supported number precisions
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Precision {
     FP32,
     FP16,
@@ -223,12 +605,87 @@ pub enum Precision {
     INT4,
 }
 
+/// Environment variable naming an NPU manifest for the scheme
+pub const NPU_MANIFEST_ENV: &str = "AGENTD_NPU_MANIFEST";
+
+/// JSON description of the NPU devices the scheme should simulate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NPUManifest {
+    pub devices: Vec<NPUDeviceSpec>,
+}
+
+/// One simulated NPU device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NPUDeviceSpec {
+    pub name: String,
+    pub max_memory_mb: u64,
+    pub supported_precision: Vec<Precision>,
+    pub max_batch_size: usize,
+    pub ops_per_second: u64,
+}
+
+impl NPUManifest {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let manifest: Self = serde_json::from_str(json)
+            .map_err(|e| format!("Invalid NPU manifest: {}", e))?;
+        if manifest.devices.is_empty() {
+            return Err("NPU manifest lists no devices".to_string());
+        }
+        Ok(manifest)
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read NPU manifest {}: {}", path.display(), e))?;
+        Self::from_json(&json)
+    }
+
+    /// Build idle, unallocated devices numbered in manifest order
+    pub fn to_devices(&self) -> Vec<NPUDevice> {
+        self.devices.iter().enumerate().map(|(id, spec)| NPUDevice {
+            id,
+            name: spec.name.clone(),
+            capabilities: NPUCapabilities {
+                max_memory_mb: spec.max_memory_mb,
+                supported_precision: spec.supported_precision.clone(),
+                max_batch_size: spec.max_batch_size,
+                ops_per_second: spec.ops_per_second,
+            },
+            status: NPUStatus::Idle,
+            current_model: None,
+            allocated_to: None,
+        }).collect()
+    }
+}
+
+/// Default limit on queued inference tasks
+pub const DEFAULT_MAX_QUEUED_TASKS: usize = 1024;
+
 /// Pool of available NPU devices
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct NPUPool {
     pub devices: HashMap<NPUId, NPUDevice>,
     pub allocation_map: HashMap<AgentId, NPUId>,
     pub task_queue: VecDeque<InferenceTask>,
+    pub models: HashMap<ModelId, Vec<u8>>,
+    pub max_queued_tasks: usize,
+    pub scheduling_mode: SchedulingMode,
+}
+
+impl Default for NPUPool {
+    fn default() -> Self {
+        Self {
+            devices: HashMap::new(),
+            allocation_map: HashMap::new(),
+            task_queue: VecDeque::new(),
+            models: HashMap::new(),
+            max_queued_tasks: DEFAULT_MAX_QUEUED_TASKS,
+            scheduling_mode: SchedulingMode::default(),
+        }
+    }
 }
 
 impl NPUPool {
@@ -236,6 +693,59 @@ impl NPUPool {
         Self::default()
     }
 
+    /// Queue an inference task, refusing it once `max_queued_tasks` are waiting
+    pub fn submit_task(&mut self, task: InferenceTask) -> Result<(), String> {
+        if self.task_queue.len() >= self.max_queued_tasks {
+            return Err("inference queue full".to_string());
+        }
+        self.task_queue.push_back(task);
+        Ok(())
+    }
+
+    /// Remove the next task to dispatch according to the scheduling mode
+    pub fn next_task(&mut self) -> Option<InferenceTask> {
+        match self.scheduling_mode {
+            SchedulingMode::Fifo => self.task_queue.pop_front(),
+            SchedulingMode::EarliestDeadlineFirst => {
+                let (index, _) = self
+                    .task_queue
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(index, task)| (task.deadline().unwrap_or(Duration::MAX), *index))?;
+                self.task_queue.remove(index)
+            }
+        }
+    }
+
+    /// Remove queued tasks whose deadline has passed. Only deadline-aware
+    /// scheduling drops tasks; FIFO mode always returns an empty list.
+    pub fn take_expired_tasks(&mut self, now: Duration) -> Vec<InferenceTask> {
+        if self.scheduling_mode != SchedulingMode::EarliestDeadlineFirst {
+            return Vec::new();
+        }
+        let (expired, pending): (Vec<_>, VecDeque<_>) =
+            self.task_queue.drain(..).partition(|task| task.is_expired(now));
+        self.task_queue = pending;
+        expired
+    }
+
+    /// Summarize device usage; all zeros for an empty pool
+    pub fn usage_stats(&self) -> NPUUsageStats {
+        let total = self.devices.len();
+        let fraction = |count: usize| if total == 0 { 0.0 } else { count as f64 / total as f64 };
+        let active = self.devices.values().filter(|device| device.is_active()).count();
+        let holding_model = self.devices.values().filter(|device| device.current_model.is_some()).count();
+        let power: f64 = self.devices.values().map(|device| device.health().power_consumption_watts as f64).sum();
+        NPUUsageStats {
+            total_devices: total,
+            active_devices: active,
+            compute_utilization: fraction(active),
+            memory_utilization: fraction(holding_model),
+            power_consumption_watts: if total == 0 { 0.0 } else { power / total as f64 },
+            queued_tasks: self.task_queue.len(),
+        }
+    }
+
     pub fn add_device(&mut self, device: NPUDevice) {
         self.devices.insert(device.id, device);
     }
@@ -252,12 +762,29 @@ impl NPUPool {
         None
     }
 
-    pub fn deallocate_npu(&mut self, agent_id: AgentId) {
-        if let Some(npu_id) = self.allocation_map.remove(&agent_id) {
-            if let Some(device) = self.devices.get_mut(&npu_id) {
-                device.allocated_to = None;
-                device.status = NPUStatus::Idle;
-            }
+    /// Store model data so inference tasks can reference it by id.
+    /// Loading an id again replaces the previous data.
+    pub fn load_model(&mut self, model_id: ModelId, model_data: Vec<u8>) -> Result<(), String> {
+        if model_data.is_empty() {
+            return Err(format!("Model {} has no data", model_id));
+        }
+        self.models.insert(model_id, model_data);
+        Ok(())
+    }
+
+    pub fn is_model_loaded(&self, model_id: &str) -> bool {
+        self.models.contains_key(model_id)
+    }
+
+    /// Release the device held by `agent_id`, returning it to an idle,
+    /// unloaded state. Returns the freed device, if the agent held one.
+    pub fn deallocate_npu(&mut self, agent_id: AgentId) -> Option<NPUId> {
+        let npu_id = self.allocation_map.remove(&agent_id)?;
+        if let Some(device) = self.devices.get_mut(&npu_id) {
+            device.allocated_to = None;
+            device.current_model = None;
+            device.status = NPUStatus::Idle;
         }
+        Some(npu_id)
     }
 }
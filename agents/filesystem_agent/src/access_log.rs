@@ -0,0 +1,245 @@
+use log::{debug, warn};
+use std::io;
+use std::path::PathBuf;
+
+use crate::types::FileAccessEvent;
+
+/// Default size at which the access log starts a new segment
+pub const DEFAULT_SEGMENT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Storage behind an `AccessLog`, split into segments by rotation
+pub trait AccessLogSink: Send + Sync {
+    /// Append one line to the current segment
+    fn append(&mut self, line: &str) -> io::Result<()>;
+
+    /// Bytes written to the current segment
+    fn current_size(&self) -> u64;
+
+    /// Close the current segment and start an empty one
+    fn rotate(&mut self) -> io::Result<()>;
+
+    /// Contents of every retained segment, oldest first
+    fn read_segments(&self) -> io::Result<Vec<String>>;
+}
+
+/// Sink keeping every segment in memory, for tests and mock runs
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    segments: Vec<String>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AccessLogSink for MemorySink {
+    fn append(&mut self, line: &str) -> io::Result<()> {
+        if self.segments.is_empty() {
+            self.segments.push(String::new());
+        }
+        self.segments.last_mut().unwrap().push_str(line);
+        Ok(())
+    }
+
+    fn current_size(&self) -> u64 {
+        self.segments.last().map_or(0, |segment| segment.len() as u64)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.segments.push(String::new());
+        Ok(())
+    }
+
+    fn read_segments(&self) -> io::Result<Vec<String>> {
+        Ok(self.segments.clone())
+    }
+}
+
+/// Sink appending to a file, renaming full segments to `<path>.1`,
+/// `<path>.2`, ... with the highest number the oldest
+#[derive(Debug)]
+pub struct FileSink {
+    path: PathBuf,
+    max_rotated: usize,
+    current_size: u64,
+}
+
+impl FileSink {
+    /// Log to `path`, keeping at most `max_rotated` full segments
+    pub fn new(path: impl Into<PathBuf>, max_rotated: usize) -> io::Result<Self> {
+        let path = path.into();
+        let current_size = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+        Ok(Self { path, max_rotated, current_size })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+}
+
+impl AccessLogSink for FileSink {
+    fn append(&mut self, line: &str) -> io::Result<()> {
+        use std::io::Write;
+
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        self.current_size += line.len() as u64;
+        Ok(())
+    }
+
+    fn current_size(&self) -> u64 {
+        self.current_size
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_rotated == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.max_rotated).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.current_size = 0;
+        debug!("Rotated access log {}", self.path.display());
+        Ok(())
+    }
+
+    fn read_segments(&self) -> io::Result<Vec<String>> {
+        let paths = (1..=self.max_rotated)
+            .rev()
+            .map(|index| self.rotated_path(index))
+            .chain(std::iter::once(self.path.clone()));
+
+        let mut segments = Vec::new();
+        for path in paths {
+            match std::fs::read_to_string(&path) {
+                Ok(segment) => segments.push(segment),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(segments)
+    }
+}
+
+/// Append-only JSON lines log of raw access events, for auditing and
+/// retraining the prediction model
+pub struct AccessLog {
+    sink: Box<dyn AccessLogSink>,
+    max_segment_bytes: u64,
+}
+
+impl AccessLog {
+    /// Log to `sink`, rotating once a segment would exceed `max_segment_bytes`
+    pub fn new(sink: Box<dyn AccessLogSink>, max_segment_bytes: u64) -> Self {
+        Self { sink, max_segment_bytes }
+    }
+
+    /// Log kept in memory
+    pub fn in_memory(max_segment_bytes: u64) -> Self {
+        Self::new(Box::new(MemorySink::new()), max_segment_bytes)
+    }
+
+    /// Append an event, rotating first if it would overflow the segment
+    pub fn append(&mut self, event: &FileAccessEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+
+        let size = self.sink.current_size();
+        if size > 0 && size + line.len() as u64 > self.max_segment_bytes {
+            self.sink.rotate()?;
+        }
+        self.sink.append(&line)?;
+        Ok(())
+    }
+
+    /// Number of retained segments, including the current one
+    pub fn segment_count(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.sink.read_segments()?.len())
+    }
+
+    /// Every logged event still retained, oldest first. Lines that fail to
+    /// parse, such as one torn by a crash, are skipped.
+    pub fn replay(&self) -> Result<Vec<FileAccessEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut events = Vec::new();
+        for segment in self.sink.read_segments()? {
+            for line in segment.lines().filter(|line| !line.trim().is_empty()) {
+                match serde_json::from_str(line) {
+                    Ok(event) => events.push(event),
+                    Err(e) => warn!("Skipping unreadable access log line: {}", e),
+                }
+            }
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predictor::AccessPredictor;
+    use crate::types::PredictorConfig;
+
+    fn events() -> Vec<FileAccessEvent> {
+        ["/project/main.rs", "/project/lib.rs", "/project/utils.rs", "/project/main.rs"]
+            .iter()
+            .enumerate()
+            .map(|(i, path)| FileAccessEvent::new(path.to_string()).with_size(100 * (i as u64 + 1)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_rotated_log_replays_into_predictor() {
+        let events = events();
+        let line_len = serde_json::to_string(&events[0]).unwrap().len() as u64 + 1;
+
+        // Room for two events per segment
+        let mut log = AccessLog::in_memory(line_len * 2 + line_len / 2);
+        for event in &events {
+            log.append(event).unwrap();
+        }
+        assert_eq!(log.segment_count().unwrap(), 2);
+
+        let replayed = log.replay().unwrap();
+        let paths: Vec<_> = replayed.iter().map(|event| event.path.as_str()).collect();
+        assert_eq!(paths, ["/project/main.rs", "/project/lib.rs", "/project/utils.rs", "/project/main.rs"]);
+
+        let predictor = AccessPredictor::from_access_log(PredictorConfig::default(), &log).await.unwrap();
+        assert_eq!(predictor.file_size("/project/main.rs"), Some(400));
+        assert_eq!(predictor.file_size("/project/utils.rs"), Some(300));
+    }
+
+    #[test]
+    fn test_file_sink_drops_oldest_segments() {
+        let dir = std::env::temp_dir().join(format!("filesystem_agent_access_log_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("access.log");
+
+        // Every event gets its own segment and only two full ones are kept
+        let sink = FileSink::new(&path, 2).unwrap();
+        let mut log = AccessLog::new(Box::new(sink), 1);
+        for event in &events() {
+            log.append(event).unwrap();
+        }
+        assert_eq!(log.segment_count().unwrap(), 3);
+
+        let paths: Vec<_> = log.replay().unwrap().into_iter().map(|event| event.path).collect();
+        assert_eq!(paths, ["/project/lib.rs", "/project/utils.rs", "/project/main.rs"]);
+
+        // Reopening picks up the current segment's size
+        assert!(FileSink::new(&path, 2).unwrap().current_size() > 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,14 +1,45 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use log::{info, debug, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::predictor::AccessPredictor;
+use crate::types::{AccessType, FileAccessEvent, SchemeConfig};
+
 // Mock scheme implementation for development
 // In a real Redox implementation, this would use syscall::Scheme
 pub struct FilesystemScheme {
     next_id: usize,
     handles: HashMap<usize, FileHandle>,
     cache: Arc<RwLock<PrefetchCache>>,
+    config: SchemeConfig,
+    predictor: Option<Arc<RwLock<AccessPredictor>>>,
+    prefetch_queue: VecDeque<PrefetchRequest>,
+}
+
+// Budget charge for files whose size the predictor has never seen
+const UNKNOWN_FILE_SIZE: u64 = 64 * 1024;
+
+/// A file waiting in the prefetch queue
+#[derive(Debug, Clone)]
+struct PrefetchRequest {
+    path: String,
+    priority: f32,
+    size: Option<u64>,
+}
+
+impl PrefetchRequest {
+    /// Bytes charged against the prefetch budget
+    fn cost(&self) -> u64 {
+        self.size.unwrap_or(UNKNOWN_FILE_SIZE).max(1)
+    }
+    
+    /// Predicted probability per byte of cache spent
+    fn value_density(&self) -> f64 {
+        self.priority as f64 / self.cost() as f64
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +56,15 @@ pub struct PrefetchCache {
     current_size: usize,
 }
 
+/// What `persist_index` records about a cached file; the data is not kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheIndexEntry {
+    pub path: String,
+    pub priority: f32,
+    pub access_count: u32,
+    pub size: u64,
+}
+
 #[derive(Debug, Clone)]
 struct CacheEntry {
     data: Vec<u8>,
@@ -35,15 +75,28 @@ struct CacheEntry {
 
 impl FilesystemScheme {
     pub fn new() -> Self {
-        info!("Initializing filesystem scheme");
+        Self::with_config(SchemeConfig::default())
+    }
+    
+    pub fn with_config(config: SchemeConfig) -> Self {
+        info!("Initializing filesystem scheme (predictive prefetch: {})", config.predictive_prefetch);
         
         Self {
             next_id: 1,
             handles: HashMap::new(),
             cache: Arc::new(RwLock::new(PrefetchCache::new(100 * 1024 * 1024))), // 100MB cache
+            config,
+            predictor: None,
+            prefetch_queue: VecDeque::new(),
         }
     }
     
+    /// Predictor consulted on `open` when `predictive_prefetch` is enabled
+    pub fn with_predictor(mut self, predictor: Arc<RwLock<AccessPredictor>>) -> Self {
+        self.predictor = Some(predictor);
+        self
+    }
+    
     pub async fn open(&mut self, path: &str, flags: usize) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
         debug!("Opening file: {} with flags: {}", path, flags);
         
@@ -67,9 +120,125 @@ impl FilesystemScheme {
             }
         }
         
+        self.enqueue_predicted_prefetches(path).await;
+        
         Ok(handle_id)
     }
     
+    /// Queue uncached related files the predictor rates above the threshold.
+    /// Does nothing unless `predictive_prefetch` is enabled and a predictor is set.
+    pub async fn enqueue_predicted_prefetches(&mut self, path: &str) {
+        if !self.config.predictive_prefetch {
+            return;
+        }
+        let predictor = match &self.predictor {
+            Some(predictor) => Arc::clone(predictor),
+            None => return,
+        };
+        
+        let predictions = match predictor.read().await.predict_related_files(path).await {
+            Ok(predictions) => predictions,
+            Err(e) => {
+                warn!("Related file prediction for {} failed: {}", path, e);
+                return;
+            }
+        };
+        
+        for (file_path, probability) in predictions {
+            // Policy boosts count towards the threshold
+            let probability = match self.config.apply_prefetch_policy(&file_path, probability) {
+                Some(probability) => probability,
+                None => continue,
+            };
+            if probability > self.config.prefetch_threshold {
+                let size = predictor.read().await.file_size(&file_path);
+                self.queue_prefetch(&file_path, probability, size).await;
+            }
+        }
+    }
+    
+    /// Queue a file for prefetch unless a prefetch policy denies it or it is
+    /// already cached or queued. `size` is charged against the prefetch budget.
+    pub async fn enqueue_prefetch(&mut self, file_path: &str, priority: f32, size: Option<u64>) -> bool {
+        match self.config.apply_prefetch_policy(file_path, priority) {
+            Some(priority) => self.queue_prefetch(file_path, priority, size).await,
+            None => {
+                debug!("Prefetch of {} denied by policy", file_path);
+                false
+            }
+        }
+    }
+    
+    async fn queue_prefetch(&mut self, file_path: &str, priority: f32, size: Option<u64>) -> bool {
+        if self.cache.read().await.contains(file_path)
+            || self.prefetch_queue.iter().any(|queued| queued.path == file_path)
+        {
+            return false;
+        }
+        
+        debug!("Queueing prefetch of {} (priority: {:.2})", file_path, priority);
+        self.prefetch_queue.push_back(PrefetchRequest {
+            path: file_path.to_string(),
+            priority,
+            size,
+        });
+        true
+    }
+    
+    /// Drop any pending prefetch and cached copy of `path` so stale data is never served
+    pub async fn invalidate(&mut self, path: &str) {
+        let queued = self.prefetch_queue.len();
+        self.prefetch_queue.retain(|queued| queued.path != path);
+        if self.prefetch_queue.len() < queued {
+            info!("Cancelled pending prefetch of {}", path);
+        }
+        
+        if self.cache.write().await.remove(path) {
+            info!("Evicted cache entry for {}", path);
+        }
+    }
+    
+    /// React to an observed access; writes and deletes invalidate the path
+    pub async fn handle_access_event(&mut self, event: &FileAccessEvent) {
+        if matches!(event.access_type, AccessType::Write | AccessType::Delete) {
+            self.invalidate(&event.path).await;
+        }
+    }
+    
+    /// Paths waiting in the prefetch queue, oldest first
+    pub fn pending_prefetches(&self) -> Vec<String> {
+        self.prefetch_queue.iter().map(|queued| queued.path.clone()).collect()
+    }
+    
+    /// Prefetch the queued files that best use `prefetch_budget`, returning how
+    /// many were loaded. The rest of the queue is dropped.
+    pub async fn run_prefetch_queue(&mut self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let batch = self.plan_prefetch_batch();
+        for request in &batch {
+            self.prefetch_file(&request.path, request.priority).await?;
+        }
+        Ok(batch.len())
+    }
+    
+    /// Greedily pick queued files by probability per byte until the budget is spent,
+    /// so several likely small files beat one unlikely large one
+    fn plan_prefetch_batch(&mut self) -> Vec<PrefetchRequest> {
+        let mut candidates: Vec<PrefetchRequest> = self.prefetch_queue.drain(..).collect();
+        candidates.sort_by(|a, b| b.value_density().total_cmp(&a.value_density()));
+        
+        let mut remaining = self.config.prefetch_budget as u64;
+        let mut batch = Vec::new();
+        for request in candidates {
+            if request.cost() <= remaining {
+                remaining -= request.cost();
+                batch.push(request);
+            } else {
+                debug!("Skipping prefetch of {} ({} bytes): over budget", request.path, request.cost());
+            }
+        }
+        batch
+    }
+    
     pub async fn read(&mut self, handle_id: usize, buffer: &mut [u8]) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
         let handle = self.handles.get_mut(&handle_id)
             .ok_or("Invalid handle")?;
@@ -79,8 +248,8 @@ impl FilesystemScheme {
         
         // Try to read from cache first
         {
-            let cache = self.cache.read().await;
-            if let Some(entry) = cache.entries.get(&handle.path) {
+            let mut cache = self.cache.write().await;
+            if let Some(entry) = cache.entries.get_mut(&handle.path) {
                 let start = handle.offset.min(entry.data.len());
                 let end = (handle.offset + buffer.len()).min(entry.data.len());
                 
@@ -88,6 +257,7 @@ impl FilesystemScheme {
                     let bytes_to_copy = end - start;
                     buffer[..bytes_to_copy].copy_from_slice(&entry.data[start..end]);
                     handle.offset += bytes_to_copy;
+                    entry.access_count += 1;
                     
                     info!("Cache read: {} bytes from {}", bytes_to_copy, handle.path);
                     return Ok(bytes_to_copy);
@@ -127,7 +297,7 @@ impl FilesystemScheme {
         // Invalidate cache entry if it exists
         {
             let mut cache = self.cache.write().await;
-            if cache.entries.remove(&handle.path).is_some() {
+            if cache.remove(&handle.path) {
                 info!("Invalidated cache entry for modified file: {}", handle.path);
             }
         }
@@ -162,6 +332,30 @@ impl FilesystemScheme {
         Ok(())
     }
     
+    /// Save the cache's index to `path` so `restore_index` can warm a later run
+    pub async fn persist_cache_index(&self, path: &Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.cache.read().await.persist_index(path)
+    }
+    
+    /// Queue the files in a persisted cache index for prefetch, most accessed
+    /// first, returning how many were queued. A missing or unreadable index
+    /// queues nothing.
+    pub async fn restore_index(&mut self, path: &Path) -> usize {
+        let mut index = PrefetchCache::read_index(path);
+        index.sort_by(|a, b| {
+            b.access_count.cmp(&a.access_count).then(b.priority.total_cmp(&a.priority))
+        });
+        
+        let mut queued = 0;
+        for entry in index {
+            if self.enqueue_prefetch(&entry.path, entry.priority, Some(entry.size)).await {
+                queued += 1;
+            }
+        }
+        info!("Restored {} prefetches from cache index {}", queued, path.display());
+        queued
+    }
+    
     pub async fn get_cache_stats(&self) -> CacheStats {
         let cache = self.cache.read().await;
         CacheStats {
@@ -186,6 +380,16 @@ impl PrefetchCache {
         self.entries.contains_key(path)
     }
     
+    fn remove(&mut self, path: &str) -> bool {
+        match self.entries.remove(path) {
+            Some(entry) => {
+                self.current_size -= entry.data.len();
+                true
+            }
+            None => false,
+        }
+    }
+    
     async fn insert(&mut self, path: String, entry: CacheEntry) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let entry_size = entry.data.len();
         
@@ -205,6 +409,45 @@ impl PrefetchCache {
         Ok(())
     }
     
+    /// Cached paths with their priorities and access counts
+    pub fn index(&self) -> Vec<CacheIndexEntry> {
+        self.entries
+            .iter()
+            .map(|(path, entry)| CacheIndexEntry {
+                path: path.clone(),
+                priority: entry.priority,
+                access_count: entry.access_count,
+                size: entry.data.len() as u64,
+            })
+            .collect()
+    }
+    
+    /// Write the cache's index to `path` as JSON
+    pub fn persist_index(&self, path: &Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let index = self.index();
+        std::fs::write(path, serde_json::to_vec(&index)?)?;
+        debug!("Persisted cache index of {} entries to {}", index.len(), path.display());
+        Ok(())
+    }
+    
+    /// Read an index written by `persist_index`, treating a missing or
+    /// corrupt file as empty
+    pub fn read_index(path: &Path) -> Vec<CacheIndexEntry> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Cannot read cache index {}: {}", path.display(), e);
+                }
+                return Vec::new();
+            }
+        };
+        serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            warn!("Ignoring corrupt cache index {}: {}", path.display(), e);
+            Vec::new()
+        })
+    }
+    
     async fn evict_lru(&mut self) {
         // Find the entry with the oldest timestamp and lowest priority
         let mut oldest_key = None;
@@ -252,6 +495,7 @@ impl std::fmt::Display for CacheStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::PrefetchPolicy;
     
     #[tokio::test]
     async fn test_scheme_open_close() {
@@ -303,4 +547,139 @@ mod tests {
         assert_eq!(stats.entries, 1);
         assert!(stats.current_size > 0);
     }
+    
+    #[tokio::test]
+    async fn test_open_enqueues_predicted_prefetches() {
+        let mut predictor = AccessPredictor::new().await.unwrap();
+        for file in ["/project/main.rs", "/project/lib.rs", "/project/utils.rs"] {
+            let event = FileAccessEvent::new(file.to_string());
+            predictor.record_access(&event).await.unwrap();
+        }
+        let predictor = Arc::new(RwLock::new(predictor));
+        
+        // Pure cache behavior ignores the predictor
+        let mut scheme = FilesystemScheme::new().with_predictor(Arc::clone(&predictor));
+        scheme.open("/project/main.rs", 0).await.unwrap();
+        assert!(scheme.pending_prefetches().is_empty());
+        
+        let config = SchemeConfig::default().with_predictive_prefetch(true);
+        let mut scheme = FilesystemScheme::with_config(config).with_predictor(predictor);
+        scheme.open("/project/main.rs", 0).await.unwrap();
+        
+        let pending = scheme.pending_prefetches();
+        assert!(pending.contains(&"/project/lib.rs".to_string()));
+        assert!(pending.contains(&"/project/utils.rs".to_string()));
+        assert!(!pending.contains(&"/project/main.rs".to_string()));
+        
+        // Reopening does not queue the same files twice
+        scheme.open("/project/main.rs", 0).await.unwrap();
+        assert_eq!(scheme.pending_prefetches(), pending);
+        
+        assert_eq!(scheme.run_prefetch_queue().await.unwrap(), pending.len());
+        assert!(scheme.pending_prefetches().is_empty());
+        assert_eq!(scheme.get_cache_stats().await.entries, pending.len());
+    }
+    
+    #[tokio::test]
+    async fn test_delete_event_cancels_prefetch() {
+        let mut scheme = FilesystemScheme::new();
+        
+        assert!(scheme.enqueue_prefetch("/project/stale.rs", 0.9, None).await);
+        assert!(scheme.enqueue_prefetch("/project/fresh.rs", 0.9, None).await);
+        scheme.prefetch_file("/project/stale.rs", 0.9).await.unwrap();
+        
+        let event = FileAccessEvent::new("/project/stale.rs".to_string())
+            .with_access_type(AccessType::Delete);
+        scheme.handle_access_event(&event).await;
+        
+        assert_eq!(scheme.pending_prefetches(), vec!["/project/fresh.rs".to_string()]);
+        assert!(!scheme.cache.read().await.contains("/project/stale.rs"));
+        assert_eq!(scheme.get_cache_stats().await.current_size, 0);
+        
+        // Reads leave the queue alone
+        let event = FileAccessEvent::new("/project/fresh.rs".to_string());
+        scheme.handle_access_event(&event).await;
+        assert_eq!(scheme.pending_prefetches().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_deny_policy_blocks_prefetch() {
+        let mut predictor = AccessPredictor::new().await.unwrap();
+        for file in ["/project/target/debug/main.d", "/project/target/debug/lib.d"] {
+            let event = FileAccessEvent::new(file.to_string());
+            predictor.record_access(&event).await.unwrap();
+        }
+        let predictor = Arc::new(RwLock::new(predictor));
+        
+        let config = SchemeConfig::default()
+            .with_predictive_prefetch(true)
+            .with_prefetch_rule("/project/target/**", PrefetchPolicy::Deny);
+        let mut scheme = FilesystemScheme::with_config(config).with_predictor(Arc::clone(&predictor));
+        
+        // lib.d is predicted above the threshold but lives under target/
+        let related = predictor.read().await.predict_related_files("/project/target/debug/main.d").await.unwrap();
+        assert!(related.iter().any(|(path, p)| path == "/project/target/debug/lib.d" && *p > 0.5));
+        
+        scheme.open("/project/target/debug/main.d", 0).await.unwrap();
+        assert!(scheme.pending_prefetches().is_empty());
+        
+        assert!(!scheme.enqueue_prefetch("/project/target/release/app", 1.0, None).await);
+        assert!(scheme.enqueue_prefetch("/project/src/main.rs", 0.6, None).await);
+        assert_eq!(scheme.pending_prefetches(), vec!["/project/src/main.rs".to_string()]);
+    }
+    
+    #[tokio::test]
+    async fn test_restore_index_requeues_hot_files() {
+        let index_path = std::env::temp_dir()
+            .join(format!("filesystem_agent_cache_index_{}.json", std::process::id()));
+        
+        let mut scheme = FilesystemScheme::new();
+        scheme.prefetch_file("/project/hot.rs", 0.4).await.unwrap();
+        scheme.prefetch_file("/project/warm.rs", 0.9).await.unwrap();
+        let handle_id = scheme.open("/project/hot.rs", 0).await.unwrap();
+        let mut buffer = [0u8; 16];
+        for _ in 0..3 {
+            scheme.read(handle_id, &mut buffer).await.unwrap();
+        }
+        scheme.persist_cache_index(&index_path).await.unwrap();
+        
+        let mut restarted = FilesystemScheme::new();
+        assert_eq!(restarted.get_cache_stats().await.entries, 0);
+        assert_eq!(restarted.restore_index(&index_path).await, 2);
+        assert_eq!(
+            restarted.pending_prefetches(),
+            vec!["/project/hot.rs".to_string(), "/project/warm.rs".to_string()]
+        );
+        assert_eq!(restarted.run_prefetch_queue().await.unwrap(), 2);
+        assert!(restarted.cache.read().await.contains("/project/hot.rs"));
+        
+        // Missing and corrupt indexes restore nothing
+        std::fs::write(&index_path, b"not json").unwrap();
+        assert_eq!(FilesystemScheme::new().restore_index(&index_path).await, 0);
+        std::fs::remove_file(&index_path).unwrap();
+        assert_eq!(FilesystemScheme::new().restore_index(&index_path).await, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_small_likely_files_win_prefetch_budget() {
+        let config = SchemeConfig::default().with_prefetch_budget(1024 * 1024);
+        let mut scheme = FilesystemScheme::with_config(config);
+        
+        assert!(scheme.enqueue_prefetch("/data/disk.img", 0.55, Some(900 * 1024)).await);
+        for i in 0..5 {
+            let path = format!("/project/src/module{}.rs", i);
+            assert!(scheme.enqueue_prefetch(&path, 0.8, Some(100 * 1024)).await);
+        }
+        
+        // The image alone would fit, but not alongside the small files
+        assert_eq!(scheme.run_prefetch_queue().await.unwrap(), 5);
+        
+        let cache = scheme.cache.read().await;
+        assert!(!cache.contains("/data/disk.img"));
+        for i in 0..5 {
+            assert!(cache.contains(&format!("/project/src/module{}.rs", i)));
+        }
+        drop(cache);
+        assert!(scheme.pending_prefetches().is_empty());
+    }
 }
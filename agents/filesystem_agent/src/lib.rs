@@ -1,9 +1,14 @@
+pub mod access_log;
 pub mod filesystem;
 pub mod predictor;
 pub mod types;
 
+pub use access_log::{AccessLog, AccessLogSink, FileSink, MemorySink};
 pub use predictor::AccessPredictor;
-pub use types::{FileAccessEvent, AccessType, FileAccessPattern};
+pub use types::{
+    FileAccessEvent, AccessType, FileAccessPattern, HeuristicWeights, OnnxPolicy, OptimizationLevel, PredictionExplanation,
+    PredictionFactor, PredictorConfig, PredictorStats, PrefetchPolicy, PrefetchRule, SchemeConfig,
+};
 
 #[cfg(feature = "mock")]
-pub use filesystem::{FilesystemScheme, FileHandle, PrefetchCache};
+pub use filesystem::{CacheIndexEntry, FilesystemScheme, FileHandle, PrefetchCache};
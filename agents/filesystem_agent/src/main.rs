@@ -1,14 +1,15 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use tokio::sync::RwLock;
 use log::{info, error, debug, warn};
 
+mod access_log;
 mod filesystem;
 mod predictor;
 mod types;
 
 use filesystem::FilesystemScheme;
 use predictor::AccessPredictor;
-use types::FileAccessEvent;
+use types::{AccessType, FileAccessEvent, SchemeConfig};
 
 #[cfg(not(feature = "mock"))]
 use redox_daemon::Daemon;
@@ -17,19 +18,28 @@ const SCHEME_NAME: &str = "fs-agent";
 
 pub struct FilesystemAgent {
     scheme: Arc<RwLock<FilesystemScheme>>,
-    predictor: Arc<Mutex<AccessPredictor>>,
+    predictor: Arc<RwLock<AccessPredictor>>,
 }
 
 impl FilesystemAgent {
     pub async fn new() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::with_config(SchemeConfig::default().with_predictive_prefetch(true)).await
+    }
+    
+    /// Agent whose scheme prefetches predicted files when `predictive_prefetch` is set
+    pub async fn with_config(config: SchemeConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         info!("Initializing filesystem agent...");
         
-        let predictor = AccessPredictor::new().await?;
-        let scheme = FilesystemScheme::new();
+        let predictor = Arc::new(RwLock::new(AccessPredictor::new().await?));
+        let predictive_prefetch = config.predictive_prefetch;
+        let mut scheme = FilesystemScheme::with_config(config);
+        if predictive_prefetch {
+            scheme = scheme.with_predictor(Arc::clone(&predictor));
+        }
         
         Ok(Self {
             scheme: Arc::new(RwLock::new(scheme)),
-            predictor: Arc::new(Mutex::new(predictor)),
+            predictor,
         })
     }
     
@@ -57,6 +67,8 @@ impl FilesystemAgent {
             FileAccessEvent::new("/home/user/project/main.rs".to_string()),
             FileAccessEvent::new("/home/user/project/lib.rs".to_string()),
             FileAccessEvent::new("/home/user/downloads/file.pdf".to_string()),
+            FileAccessEvent::new("/home/user/project/main.rs".to_string())
+                .with_access_type(AccessType::Delete),
         ];
         
         for event in mock_events {
@@ -64,6 +76,9 @@ impl FilesystemAgent {
             tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
         }
         
+        let prefetched = self.scheme.write().await.run_prefetch_queue().await?;
+        info!("Prefetched {} queued files", prefetched);
+        
         // Demonstrate prediction
         let test_path = "/home/user/project/config.toml";
         match self.predict_access_probability(test_path).await {
@@ -106,47 +121,21 @@ impl FilesystemAgent {
     async fn handle_file_access(&self, event: FileAccessEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         debug!("Processing file access: {}", event.path);
         
-        // Record the access
-        {
-            let mut predictor = self.predictor.lock().unwrap();
-            predictor.record_access(&event).await?;
-        }
+        // Writes and deletes cancel queued prefetches and evict stale cache entries
+        self.scheme.write().await.handle_access_event(&event).await;
         
-        // Check if we should prefetch related files
-        let predictions = self.get_related_file_predictions(&event.path).await?;
+        // Record the access
+        self.predictor.write().await.record_access(&event).await?;
         
-        for (file_path, probability) in predictions {
-            if probability > 0.5 {
-                info!("Prefetching {} (probability: {:.2})", file_path, probability);
-                self.prefetch_file(&file_path).await?;
-            }
-        }
+        // The scheme queues related files the predictor rates highly, so a
+        // later write or delete can still cancel them
+        self.scheme.write().await.enqueue_predicted_prefetches(&event.path).await;
         
         Ok(())
     }
     
     async fn predict_access_probability(&self, path: &str) -> Result<f32, Box<dyn std::error::Error + Send + Sync>> {
-        let predictor = self.predictor.lock().unwrap();
-        predictor.predict_access(path).await
-    }
-    
-    async fn get_related_file_predictions(&self, accessed_path: &str) -> Result<Vec<(String, f32)>, Box<dyn std::error::Error + Send + Sync>> {
-        let predictor = self.predictor.lock().unwrap();
-        predictor.predict_related_files(accessed_path).await
-    }
-    
-    async fn prefetch_file(&self, path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // In a real implementation, this would:
-        // 1. Load file into memory/cache
-        // 2. Update cache metadata
-        // 3. Log prefetch activity
-        
-        debug!("Prefetching file: {}", path);
-        
-        // Mock implementation - just log the action
-        info!("File {} added to prefetch cache", path);
-        
-        Ok(())
+        self.predictor.read().await.predict_access(path).await
     }
 }
 
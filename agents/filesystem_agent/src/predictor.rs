@@ -1,23 +1,43 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use log::{info, debug, warn};
 use lru::LruCache;
 use std::num::NonZeroUsize;
-use chrono::{DateTime, Utc, Timelike, Datelike};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, FixedOffset, Utc, Timelike, Datelike};
 use ndarray::Array1;
 
 use ort::{Session, Value};
 
+use crate::access_log::AccessLog;
 use crate::types::{
-    FileAccessEvent, FileAccessPattern
+    FileAccessEvent, FileAccessPattern, OnnxPolicy, OptimizationLevel, PredictionExplanation,
+    PredictionFactor, PredictorConfig, PredictorStats
 };
 
+type EnvironmentResult = Result<Arc<ort::Environment>, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Most recent accesses kept for correlating files accessed close together
+const RECENT_ACCESS_CAPACITY: usize = 256;
+
+/// Seconds a cached heuristic prediction stays fresh
+const TEMPORAL_CACHE_TTL_SECS: i64 = 60;
+
+/// Directories and extensions listed in `PredictorStats`
+const STATS_TOP_ENTRIES: usize = 5;
+
 // AI-powered predictor using ONNX Runtime for real ML inference
 pub struct AccessPredictor {
     access_patterns: HashMap<String, FileAccessPattern>,
     directory_patterns: HashMap<String, Vec<String>>,  // directory -> frequently accessed files
     extension_patterns: HashMap<String, Vec<String>>,  // extension -> related files
-    temporal_cache: LruCache<String, f32>,             // file -> recent prediction score
+    temporal_cache: Mutex<LruCache<String, (f32, DateTime<Utc>)>>,  // file -> recent prediction score and when it was made
+    cache_hits: AtomicU64,                              // predictions served from temporal_cache
+    cache_misses: AtomicU64,
+    recent_accesses: VecDeque<(String, DateTime<Utc>)>,  // accesses within the correlation window, oldest first
     user_session_start: DateTime<Utc>,
+    config: PredictorConfig,
+    access_log: Option<AccessLog>,
     
     #[cfg(feature = "mock")]
     ml_session: Option<Session>,
@@ -25,25 +45,92 @@ pub struct AccessPredictor {
 
 impl AccessPredictor {
     pub async fn new() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        info!("Initializing AI-powered access predictor");
+        Self::with_config(PredictorConfig::default()).await
+    }
+    
+    /// Create a predictor, handling ONNX load failures per `config.onnx_policy`
+    pub async fn with_config(config: PredictorConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::with_environment(config, || {
+            Ok(ort::Environment::builder().with_name("fileSysPredictorEnv").build()?.into_arc())
+        }).await
+    }
+    
+    #[cfg_attr(not(feature = "mock"), allow(unused_variables))]
+    async fn with_environment(
+        config: PredictorConfig,
+        build_environment: impl FnOnce() -> EnvironmentResult,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        info!("Initializing AI-powered access predictor (UTC offset: {} min)", config.utc_offset_minutes);
+        config.heuristic_weights.validate()?;
         
         #[cfg(feature = "mock")]
-        let ml_session = Self::init_ml_model().await.ok();
+        let ml_session = match Self::init_ml_model(build_environment, config.optimization_level).await {
+            Ok(session) => Some(session),
+            Err(e) => match config.onnx_policy {
+                OnnxPolicy::Strict => return Err(e),
+                OnnxPolicy::Lenient => {
+                    warn!("Predicting with heuristics only: {}", e);
+                    None
+                }
+            },
+        };
         
         Ok(Self {
             access_patterns: HashMap::new(),
             directory_patterns: HashMap::new(),
             extension_patterns: HashMap::new(),
-            temporal_cache: LruCache::new(NonZeroUsize::new(1000).unwrap()),
+            temporal_cache: Mutex::new(LruCache::new(NonZeroUsize::new(1000).unwrap())),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            recent_accesses: VecDeque::new(),
             user_session_start: Utc::now(),
+            config,
+            access_log: None,
             
             #[cfg(feature = "mock")]
             ml_session,
         })
     }
     
+    /// Append every recorded access to `log`
+    pub fn with_access_log(mut self, log: AccessLog) -> Self {
+        self.access_log = Some(log);
+        self
+    }
+    
+    /// Build a predictor trained on the events retained in `log`, oldest first
+    pub async fn from_access_log(config: PredictorConfig, log: &AccessLog) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut predictor = Self::with_config(config).await?;
+        let events = log.replay()?;
+        for event in &events {
+            predictor.record_access(event).await?;
+        }
+        info!("Replayed {} logged accesses into predictor", events.len());
+        Ok(predictor)
+    }
+    
+    /// Access log fed by `record_access`, if any
+    pub fn access_log(&self) -> Option<&AccessLog> {
+        self.access_log.as_ref()
+    }
+    
+    /// Whether predictions come from the ONNX model rather than heuristics
+    pub fn has_ml_model(&self) -> bool {
+        #[cfg(feature = "mock")]
+        {
+            self.ml_session.is_some()
+        }
+        #[cfg(not(feature = "mock"))]
+        {
+            false
+        }
+    }
+    
     #[cfg(feature = "mock")]
-    async fn init_ml_model() -> Result<Session, Box<dyn std::error::Error + Send + Sync>> {
+    async fn init_ml_model(
+        build_environment: impl FnOnce() -> EnvironmentResult,
+        optimization_level: OptimizationLevel,
+    ) -> Result<Session, Box<dyn std::error::Error + Send + Sync>> {
         info!("Loading ONNX model for file access prediction");
         
         let model_path = "simple_file_access_predictor.onnx";
@@ -54,13 +141,11 @@ impl AccessPredictor {
             return Err("Model file not found".into());
         }
         
-        match ort::Environment::builder().with_name("fileSysPredictorEnv").build() {
+        match build_environment() {
             Ok(environment) => {
-                let environment = environment.into_arc();
-                
                 // Load the ONNX model with detailed error handling
                 match ort::SessionBuilder::new(&environment)
-                    .and_then(|builder| builder.with_optimization_level(ort::GraphOptimizationLevel::Level3))
+                    .and_then(|builder| builder.with_optimization_level(Self::graph_optimization_level(optimization_level)))
                     .and_then(|builder| builder.with_model_from_file(model_path)) {
                     Ok(session) => {
                         // Validate model inputs and outputs
@@ -95,15 +180,34 @@ impl AccessPredictor {
         }
     }
     
+    #[cfg(feature = "mock")]
+    fn graph_optimization_level(level: OptimizationLevel) -> ort::GraphOptimizationLevel {
+        match level {
+            OptimizationLevel::Disabled => ort::GraphOptimizationLevel::Disable,
+            OptimizationLevel::Level1 => ort::GraphOptimizationLevel::Level1,
+            OptimizationLevel::Level2 => ort::GraphOptimizationLevel::Level2,
+            OptimizationLevel::Level3 => ort::GraphOptimizationLevel::Level3,
+        }
+    }
+    
     pub async fn record_access(&mut self, event: &FileAccessEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         debug!("Recording file access: {}", event.path);
         
+        if let Some(log) = &mut self.access_log {
+            if let Err(e) = log.append(event) {
+                warn!("Failed to append {} to access log: {}", event.path, e);
+            }
+        }
+        
         // Update access pattern for this file
         let pattern = self.access_patterns
             .entry(event.path.clone())
             .or_insert_with(|| FileAccessPattern::new(event.path.clone()));
         
         pattern.record_access(event.timestamp);
+        if event.file_size.is_some() {
+            pattern.file_size = event.file_size;
+        }
         
         // Update directory patterns
         if let Some(parent) = std::path::Path::new(&event.path).parent() {
@@ -124,13 +228,89 @@ impl AccessPredictor {
         
         // Update correlations between files
         self.update_file_correlations(&event.path, event.timestamp).await?;
+        self.invalidate_cached_predictions(&event.path);
         
         Ok(())
     }
     
+    /// Drop cached predictions for `file_path` and the files correlated with it
+    fn invalidate_cached_predictions(&self, file_path: &str) {
+        let mut cache = self.temporal_cache.lock().unwrap();
+        cache.pop(file_path);
+        if let Some(pattern) = self.access_patterns.get(file_path) {
+            for related_file in pattern.related_files.keys() {
+                cache.pop(related_file);
+            }
+        }
+    }
+    
+    /// How much the predictor has learned so far
+    pub fn stats(&self) -> PredictorStats {
+        let hits = self.cache_hits.load(Ordering::Relaxed);
+        let lookups = hits + self.cache_misses.load(Ordering::Relaxed);
+        
+        PredictorStats {
+            tracked_files: self.access_patterns.len(),
+            total_accesses: self.access_patterns.values().map(|p| u64::from(p.access_count)).sum(),
+            correlation_edges: self.access_patterns.values().map(|p| p.related_files.len()).sum(),
+            cached_predictions: self.temporal_cache.lock().unwrap().len(),
+            cache_hit_rate: (lookups > 0).then(|| hits as f32 / lookups as f32),
+            top_directories: Self::busiest(&self.directory_patterns),
+            top_extensions: Self::busiest(&self.extension_patterns),
+        }
+    }
+    
+    /// Keys with the most recorded accesses, busiest first
+    fn busiest(patterns: &HashMap<String, Vec<String>>) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = patterns
+            .iter()
+            .map(|(key, accesses)| (key.clone(), accesses.len()))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(STATS_TOP_ENTRIES);
+        counts
+    }
+    
+    /// Last known size of `file_path`, if any recorded access carried one
+    pub fn file_size(&self, file_path: &str) -> Option<u64> {
+        self.access_patterns.get(file_path).and_then(|pattern| pattern.file_size)
+    }
+    
     pub async fn predict_access(&self, file_path: &str) -> Result<f32, Box<dyn std::error::Error + Send + Sync>> {
         debug!("Predicting access for: {}", file_path);
         
+        // Only files with a history are cached; record_access invalidates them
+        let known = self.access_patterns.contains_key(file_path);
+        if known {
+            if let Some(cached) = self.cached_prediction(file_path, Utc::now()) {
+                debug!("⚡ Cached prediction for {}: {:.4}", file_path, cached);
+                return Ok(cached);
+            }
+        }
+        
+        let prediction = self.compute_prediction(file_path).await?;
+        if known {
+            self.temporal_cache.lock().unwrap().put(file_path.to_string(), (prediction, Utc::now()));
+        }
+        Ok(prediction)
+    }
+    
+    /// Score cached for `file_path` if it is still fresh at `now`
+    fn cached_prediction(&self, file_path: &str, now: DateTime<Utc>) -> Option<f32> {
+        let mut cache = self.temporal_cache.lock().unwrap();
+        match cache.get(file_path) {
+            Some(&(cached, computed_at)) if (now - computed_at).num_seconds() < TEMPORAL_CACHE_TTL_SECS => {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                Some(cached)
+            }
+            _ => {
+                self.cache_misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+    
+    async fn compute_prediction(&self, file_path: &str) -> Result<f32, Box<dyn std::error::Error + Send + Sync>> {
         // Try ML model first if available
         #[cfg(feature = "mock")]
         if let Some(ref session) = self.ml_session {
@@ -267,27 +447,59 @@ impl AccessPredictor {
             }
         }
         
+        Ok(Self::rank_predictions(predictions))
+    }
+    
+    /// Drop non-finite scores, sort by probability and keep the top 10
+    fn rank_predictions(mut predictions: Vec<(String, f32)>) -> Vec<(String, f32)> {
+        predictions.retain(|(path, probability)| {
+            if probability.is_finite() {
+                true
+            } else {
+                warn!("⚠️ Dropping non-finite prediction score for {}", path);
+                false
+            }
+        });
+        
         // Remove duplicates and sort by probability
-        predictions.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        predictions.sort_by(|a, b| b.1.total_cmp(&a.1));
         predictions.dedup_by(|a, b| a.0 == b.0);
         predictions.truncate(10); // Top 10 predictions
         
-        Ok(predictions)
+        predictions
     }
     
     async fn predict_new_file_access(&self, file_path: &str) -> Result<f32, Box<dyn std::error::Error + Send + Sync>> {
-        let mut score = 0.0;
+        let factors = self.new_file_factors(file_path, Utc::now());
+        Ok(PredictionExplanation::from_factors(file_path.to_string(), factors).probability)
+    }
+    
+    /// Break the heuristic prediction for `file_path` down into weighted factors.
+    /// This explains the heuristic path only; ML model scores are not decomposed.
+    /// The factors are always recomputed, bypassing the temporal cache, so for up
+    /// to `TEMPORAL_CACHE_TTL_SECS` after a prediction the probability here may
+    /// differ from the score `predict_access` serves.
+    pub fn explain_prediction(&self, file_path: &str) -> PredictionExplanation {
+        let factors = match self.access_patterns.get(file_path) {
+            Some(pattern) => pattern.probability_factors(Utc::now()),
+            None => self.new_file_factors(file_path, Utc::now()),
+        };
         
+        PredictionExplanation::from_factors(file_path.to_string(), factors)
+    }
+    
+    fn new_file_factors(&self, file_path: &str, time: DateTime<Utc>) -> Vec<PredictionFactor> {
         // Factor 1: Directory activity
+        let mut directory_activity = 0.0;
         if let Some(parent) = std::path::Path::new(file_path).parent() {
             let dir_str = parent.to_string_lossy().to_string();
             if let Some(dir_files) = self.directory_patterns.get(&dir_str) {
-                let recent_activity = dir_files.len() as f32 / 100.0; // normalized
-                score += recent_activity * 0.3;
+                directory_activity = dir_files.len() as f32 / 100.0; // normalized
             }
         }
         
         // Factor 2: Extension popularity
+        let mut extension_popularity = 0.0;
         let file_ext = std::path::Path::new(file_path)
             .extension()
             .and_then(|ext| ext.to_str())
@@ -295,32 +507,48 @@ impl AccessPredictor {
             
         if let Some(ext) = file_ext {
             if let Some(ext_files) = self.extension_patterns.get(&ext) {
-                let ext_popularity = (ext_files.len() as f32 / 50.0).min(1.0);
-                score += ext_popularity * 0.2;
+                extension_popularity = (ext_files.len() as f32 / 50.0).min(1.0);
             }
         }
         
-        // Factor 3: Time of day pattern
-        let time_score = self.calculate_time_of_day_score();
-        score += time_score * 0.2;
-        
-        // Factor 4: Session context (files accessed in current session)
-        score += self.calculate_session_context_score() * 0.3;
-        
-        Ok(score.min(1.0))
+        let weights = &self.config.heuristic_weights;
+        vec![
+            PredictionFactor::new("directory_activity", directory_activity, weights.directory_activity),
+            PredictionFactor::new("extension_popularity", extension_popularity, weights.extension_popularity),
+            // Factor 3: Time of day pattern
+            PredictionFactor::new("time_of_day", self.time_of_day_score_at(time), weights.time_of_day),
+            // Factor 4: Session context (files accessed in current session)
+            PredictionFactor::new("session_context", self.calculate_session_context_score(), weights.session_context),
+        ]
     }
     
     async fn update_file_correlations(&mut self, accessed_file: &str, access_time: DateTime<Utc>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Look for files accessed within a time window (e.g., 5 minutes)
         let time_window = chrono::Duration::minutes(5);
         
+        // Only files still in the recent-access buffer can have been accessed
+        // within the window, so older patterns are never visited
+        while let Some((_, time)) = self.recent_accesses.front() {
+            if access_time - *time <= time_window {
+                break;
+            }
+            self.recent_accesses.pop_front();
+        }
+        let mut recent_files: Vec<&String> = self.recent_accesses
+            .iter()
+            .map(|(file, _)| file)
+            .filter(|file| *file != accessed_file)
+            .collect();
+        recent_files.sort();
+        recent_files.dedup();
+        
         // Collect correlations to update in a separate pass to avoid borrow conflicts
         let mut correlations_to_update = Vec::new();
         
-        for (other_file, pattern) in &self.access_patterns {
-            if other_file == accessed_file {
+        for other_file in recent_files {
+            let Some(pattern) = self.access_patterns.get(other_file) else {
                 continue;
-            }
+            };
             
             // Check if this file was accessed recently
             let time_diff = (access_time - pattern.last_access).abs();
@@ -354,6 +582,11 @@ impl AccessPredictor {
             }
         }
         
+        self.recent_accesses.push_back((accessed_file.to_string(), access_time));
+        if self.recent_accesses.len() > RECENT_ACCESS_CAPACITY {
+            self.recent_accesses.pop_front();
+        }
+        
         Ok(())
     }
     
@@ -399,8 +632,9 @@ impl AccessPredictor {
         }
     }
     
-    fn calculate_time_of_day_score(&self) -> f32 {
-        let current_hour = Utc::now().hour();
+    fn time_of_day_score_at(&self, time: DateTime<Utc>) -> f32 {
+        // Work hours are the user's, so bucket by local time
+        let current_hour = self.local_time(time).hour();
         
         // Typical work hours have higher scores
         match current_hour {
@@ -412,6 +646,10 @@ impl AccessPredictor {
         }
     }
     
+    fn local_time(&self, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        time.with_timezone(&self.config.utc_offset())
+    }
+    
     fn calculate_session_context_score(&self) -> f32 {
         let session_duration = (Utc::now() - self.user_session_start).num_minutes() as f32;
         
@@ -428,10 +666,11 @@ impl AccessPredictor {
         // Generate feature vector for ML model with consistent 8 features
         let mut features = Vec::new();
         
-        // Time-based features (3 features)
-        features.push(current_time.hour() as f32 / 24.0);  // Hour normalized [0,1]
-        features.push(current_time.minute() as f32 / 60.0);  // Minute normalized [0,1]
-        features.push((current_time.weekday().num_days_from_monday() as f32) / 7.0);  // Day of week [0,1]
+        // Time-based features (3 features), in the user's local time
+        let local_time = self.local_time(current_time);
+        features.push(local_time.hour() as f32 / 24.0);  // Hour normalized [0,1]
+        features.push(local_time.minute() as f32 / 60.0);  // Minute normalized [0,1]
+        features.push((local_time.weekday().num_days_from_monday() as f32) / 7.0);  // Day of week [0,1]
         
         // File path features (2 features)
         let depth = (file_path.matches('/').count() as f32 / 10.0).min(1.0);  // Path depth normalized
@@ -505,7 +744,37 @@ impl AccessPredictor {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::AccessType;
+    use crate::types::{AccessType, HeuristicWeights};
+    
+    #[tokio::test]
+    async fn test_record_access_feeds_access_log() {
+        let mut predictor = AccessPredictor::new().await.unwrap()
+            .with_access_log(AccessLog::in_memory(1024));
+        
+        for file in ["/project/main.rs", "/project/lib.rs"] {
+            predictor.record_access(&FileAccessEvent::new(file.to_string())).await.unwrap();
+        }
+        
+        let logged = predictor.access_log().unwrap().replay().unwrap();
+        assert_eq!(logged.len(), 2);
+        assert_eq!(logged[1].path, "/project/lib.rs");
+    }
+    
+    #[tokio::test]
+    async fn test_onnx_policy_when_environment_fails() {
+        let fail = || -> EnvironmentResult { Err("simulated ONNX Runtime failure".into()) };
+        
+        let strict = PredictorConfig::default().with_onnx_policy(OnnxPolicy::Strict);
+        assert!(AccessPredictor::with_environment(strict, fail).await.is_err());
+        
+        // Lenient predictors fall back to heuristics
+        let mut predictor = AccessPredictor::with_environment(PredictorConfig::default(), fail).await.unwrap();
+        assert!(!predictor.has_ml_model());
+        let event = FileAccessEvent::new("/test/file.rs".to_string());
+        predictor.record_access(&event).await.unwrap();
+        let prediction = predictor.predict_access("/test/file.rs").await.unwrap();
+        assert!((0.0..=1.0).contains(&prediction));
+    }
     
     #[tokio::test]
     async fn test_predictor_initialization() {
@@ -551,4 +820,192 @@ mod tests {
             .collect();
         assert!(!rust_files.is_empty());
     }
+    
+    #[test]
+    fn test_rank_predictions_drops_nan() {
+        let predictions = vec![
+            ("/project/a.rs".to_string(), 0.4),
+            ("/project/nan.rs".to_string(), f32::NAN),
+            ("/project/b.rs".to_string(), 0.9),
+            ("/project/inf.rs".to_string(), f32::INFINITY),
+        ];
+        
+        let ranked = AccessPredictor::rank_predictions(predictions);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].0, "/project/b.rs");
+        assert_eq!(ranked[1].0, "/project/a.rs");
+    }
+    
+    #[tokio::test]
+    async fn test_time_of_day_uses_local_offset() {
+        let utc_predictor = AccessPredictor::new().await.unwrap();
+        let tokyo_predictor = AccessPredictor::with_config(
+            PredictorConfig::default().with_utc_offset_hours(9)
+        ).await.unwrap();
+        
+        // 02:00 UTC is 11:00 in UTC+9
+        let time = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 3, 4, 2, 0, 0).unwrap();
+        
+        assert_eq!(utc_predictor.time_of_day_score_at(time), 0.2);    // night in UTC
+        assert_eq!(tokyo_predictor.time_of_day_score_at(time), 0.8);  // work hours locally
+        
+        let features = tokyo_predictor.generate_feature_vector("/project/main.rs", time);
+        assert!((features[0] - 11.0 / 24.0).abs() < 1e-6);
+    }
+    
+    #[tokio::test]
+    async fn test_explanation_contributions_sum_to_probability() {
+        let mut predictor = AccessPredictor::new().await.unwrap();
+        
+        let event = FileAccessEvent::new("/project/src/main.rs".to_string());
+        predictor.record_access(&event).await.unwrap();
+        
+        // Known file: recency / frequency / regularity
+        let known = predictor.explain_prediction("/project/src/main.rs");
+        assert_eq!(known.factors.len(), 3);
+        assert!((known.total_contribution() - known.probability).abs() < 1e-6);
+        // Explanations neither read nor fill the temporal cache
+        assert!(predictor.temporal_cache.lock().unwrap().is_empty());
+        assert_eq!(predictor.cache_misses.load(Ordering::Relaxed), 0);
+        
+        // Unseen file: directory / extension / time of day / session
+        let unseen = predictor.explain_prediction("/project/src/lib.rs");
+        assert_eq!(unseen.factors.len(), 4);
+        assert!(unseen.factor("directory_activity").unwrap().raw_value > 0.0);
+        assert!((unseen.total_contribution() - unseen.probability).abs() < 1e-6);
+        for factor in &unseen.factors {
+            assert!((factor.raw_value * factor.weight - factor.contribution).abs() < 1e-6);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_correlations_only_consider_recent_accesses() {
+        let start = Utc::now() - chrono::Duration::hours(3);
+        let accesses = [
+            ("/project/main.rs", 0),
+            ("/project/lib.rs", 1),
+            ("/project/utils.rs", 2),
+            ("/project/main.rs", 4),
+            ("/docs/notes.md", 60),
+            ("/docs/todo.md", 63),
+            ("/project/lib.rs", 65),
+            ("/project/main.rs", 120),
+        ];
+        let events: Vec<FileAccessEvent> = accesses
+            .iter()
+            .map(|(path, minutes)| {
+                let mut event = FileAccessEvent::new(path.to_string());
+                event.timestamp = start + chrono::Duration::minutes(*minutes);
+                event
+            })
+            .collect();
+        
+        let mut predictor = AccessPredictor::new().await.unwrap();
+        for event in &events {
+            predictor.record_access(event).await.unwrap();
+        }
+        
+        // What scanning every known pattern on each access produced
+        let window = chrono::Duration::minutes(5);
+        let mut last_access: HashMap<&str, DateTime<Utc>> = HashMap::new();
+        let mut expected: HashMap<(String, String), f32> = HashMap::new();
+        for event in &events {
+            last_access.insert(&event.path, event.timestamp);
+            for (other, time) in &last_access {
+                if *other != event.path && (event.timestamp - *time).abs() <= window {
+                    for key in [(other.to_string(), event.path.clone()), (event.path.clone(), other.to_string())] {
+                        let correlation = expected.entry(key).or_insert(0.0);
+                        *correlation = (*correlation + 0.1).min(1.0);
+                    }
+                }
+            }
+        }
+        let actual: HashMap<(String, String), f32> = predictor.access_patterns
+            .iter()
+            .flat_map(|(file, pattern)| {
+                pattern.related_files
+                    .iter()
+                    .map(move |(other, correlation)| ((file.clone(), other.clone()), *correlation))
+            })
+            .collect();
+        assert_eq!(actual, expected);
+        assert!(expected.contains_key(&("/docs/todo.md".to_string(), "/project/lib.rs".to_string())));
+        
+        // Only the latest access is still inside the window
+        let buffered: Vec<&str> = predictor.recent_accesses.iter().map(|(file, _)| file.as_str()).collect();
+        assert_eq!(buffered, ["/project/main.rs"]);
+    }
+    
+    #[tokio::test]
+    async fn test_stats_reflect_recorded_accesses() {
+        let mut predictor = AccessPredictor::new().await.unwrap();
+        assert_eq!(predictor.stats().tracked_files, 0);
+        assert_eq!(predictor.stats().cache_hit_rate, None);
+        
+        for file in ["/project/src/main.rs", "/project/src/lib.rs", "/project/src/main.rs", "/project/README.md"] {
+            predictor.record_access(&FileAccessEvent::new(file.to_string())).await.unwrap();
+        }
+        
+        let stats = predictor.stats();
+        assert_eq!(stats.tracked_files, 3);
+        assert_eq!(stats.total_accesses, 4);
+        assert!(stats.correlation_edges > 0);
+        assert_eq!(stats.top_directories[0], ("/project/src".to_string(), 3));
+        assert_eq!(stats.top_extensions, vec![("rs".to_string(), 3), ("md".to_string(), 1)]);
+        assert_eq!(stats.cached_predictions, 0);
+        assert_eq!(stats.cache_hit_rate, None);
+    }
+    
+    #[tokio::test]
+    async fn test_repeated_predictions_served_from_temporal_cache() {
+        let mut predictor = AccessPredictor::new().await.unwrap();
+        for file in ["/project/main.rs", "/project/lib.rs"] {
+            predictor.record_access(&FileAccessEvent::new(file.to_string())).await.unwrap();
+        }
+        
+        let first = predictor.predict_access("/project/main.rs").await.unwrap();
+        
+        // A cached score ignores changes to the pattern behind it
+        predictor.access_patterns.get_mut("/project/main.rs").unwrap().access_count += 100;
+        assert_eq!(predictor.predict_access("/project/main.rs").await.unwrap(), first);
+        assert_eq!(predictor.cache_hits.load(Ordering::Relaxed), 1);
+        assert_eq!(predictor.stats().cached_predictions, 1);
+        assert_eq!(predictor.stats().cache_hit_rate, Some(0.5));
+        
+        // Accessing a correlated file invalidates the entry
+        predictor.predict_access("/project/lib.rs").await.unwrap();
+        predictor.record_access(&FileAccessEvent::new("/project/lib.rs".to_string())).await.unwrap();
+        assert!(predictor.temporal_cache.lock().unwrap().peek("/project/main.rs").is_none());
+        assert!(predictor.temporal_cache.lock().unwrap().peek("/project/lib.rs").is_none());
+        predictor.predict_access("/project/main.rs").await.unwrap();
+        assert_eq!(predictor.cache_hits.load(Ordering::Relaxed), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_heuristic_weights_are_configurable() {
+        let time_heavy = HeuristicWeights {
+            directory_activity: 0.1,
+            extension_popularity: 0.1,
+            time_of_day: 0.7,
+            session_context: 0.1,
+        };
+        let default_predictor = AccessPredictor::new().await.unwrap();
+        let tuned_predictor = AccessPredictor::with_config(
+            PredictorConfig::default().with_heuristic_weights(time_heavy)
+        ).await.unwrap();
+        
+        // 10:00 UTC is a work hour
+        let peak = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 3, 4, 10, 0, 0).unwrap();
+        let score = |predictor: &AccessPredictor| {
+            let factors = predictor.new_file_factors("/project/new.rs", peak);
+            PredictionExplanation::from_factors("/project/new.rs".to_string(), factors).probability
+        };
+        assert!(score(&tuned_predictor) > score(&default_predictor));
+        
+        let unbalanced = HeuristicWeights { time_of_day: 0.5, ..HeuristicWeights::default() };
+        assert!(unbalanced.validate().is_err());
+        assert!(AccessPredictor::with_config(
+            PredictorConfig::default().with_heuristic_weights(unbalanced)
+        ).await.is_err());
+    }
 }
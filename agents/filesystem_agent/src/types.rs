@@ -1,7 +1,227 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, Utc};
 use std::collections::HashMap;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PredictorConfig {
+    pub utc_offset_minutes: i32,  // user's local time zone, used for time-of-day features
+    pub optimization_level: OptimizationLevel,
+    #[serde(default)]
+    pub onnx_policy: OnnxPolicy,
+    #[serde(default)]
+    pub heuristic_weights: HeuristicWeights,  // how new-file predictions combine their signals
+}
+
+impl Default for PredictorConfig {
+    fn default() -> Self {
+        Self {
+            utc_offset_minutes: 0,
+            optimization_level: OptimizationLevel::Level3,
+            onnx_policy: OnnxPolicy::default(),
+            heuristic_weights: HeuristicWeights::default(),
+        }
+    }
+}
+
+/// Weights of the signals behind a prediction for a file with no access history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct HeuristicWeights {
+    pub directory_activity: f32,
+    pub extension_popularity: f32,
+    pub time_of_day: f32,
+    pub session_context: f32,
+}
+
+impl Default for HeuristicWeights {
+    fn default() -> Self {
+        Self {
+            directory_activity: 0.3,
+            extension_popularity: 0.2,
+            time_of_day: 0.2,
+            session_context: 0.3,
+        }
+    }
+}
+
+impl HeuristicWeights {
+    /// Check every weight is non-negative and together they sum to 1.0
+    pub fn validate(&self) -> Result<(), String> {
+        let weights = [
+            self.directory_activity,
+            self.extension_popularity,
+            self.time_of_day,
+            self.session_context,
+        ];
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err(format!("heuristic weights must be non-negative: {:?}", self));
+        }
+        
+        let total: f32 = weights.iter().sum();
+        if (total - 1.0).abs() > 1e-3 {
+            return Err(format!("heuristic weights sum to {}, expected 1.0", total));
+        }
+        Ok(())
+    }
+}
+
+/// Behaviour of the filesystem scheme's cache layer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemeConfig {
+    pub predictive_prefetch: bool,  // consult the predictor on open and queue related files
+    pub prefetch_threshold: f32,    // minimum related-file probability worth prefetching
+    pub prefetch_rules: Vec<PrefetchRule>,  // first matching rule decides
+    pub prefetch_budget: usize,     // bytes a single prefetch queue run may load
+}
+
+impl Default for SchemeConfig {
+    fn default() -> Self {
+        Self {
+            predictive_prefetch: false,
+            prefetch_threshold: 0.5,
+            prefetch_rules: Vec::new(),
+            prefetch_budget: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl SchemeConfig {
+    pub fn with_predictive_prefetch(mut self, enabled: bool) -> Self {
+        self.predictive_prefetch = enabled;
+        self
+    }
+    
+    pub fn with_prefetch_threshold(mut self, threshold: f32) -> Self {
+        self.prefetch_threshold = threshold;
+        self
+    }
+    
+    pub fn with_prefetch_budget(mut self, bytes: usize) -> Self {
+        self.prefetch_budget = bytes;
+        self
+    }
+    
+    pub fn with_prefetch_rule(mut self, pattern: &str, policy: PrefetchPolicy) -> Self {
+        self.prefetch_rules.push(PrefetchRule::new(pattern, policy));
+        self
+    }
+    
+    /// Priority to prefetch `path` with under the configured rules, or `None` if denied
+    pub fn apply_prefetch_policy(&self, path: &str, priority: f32) -> Option<f32> {
+        let rule = self.prefetch_rules.iter().find(|rule| rule.matches(path));
+        match rule.map(|rule| rule.policy) {
+            Some(PrefetchPolicy::Deny) => None,
+            Some(PrefetchPolicy::Boost(boost)) => Some((priority + boost).min(1.0)),
+            Some(PrefetchPolicy::Allow) | None => Some(priority),
+        }
+    }
+}
+
+/// Whether files under a directory glob may be prefetched
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum PrefetchPolicy {
+    Allow,
+    Deny,
+    Boost(f32),  // added to the prefetch priority, capped at 1.0
+}
+
+/// Prefetch policy for paths matching a glob such as `/project/target/**`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefetchRule {
+    pub pattern: String,
+    pub policy: PrefetchPolicy,
+}
+
+impl PrefetchRule {
+    pub fn new(pattern: &str, policy: PrefetchPolicy) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            policy,
+        }
+    }
+    
+    /// `*` and `?` match within one path segment, `**` spans any number of segments
+    pub fn matches(&self, path: &str) -> bool {
+        let pattern: Vec<&str> = self.pattern.split('/').filter(|s| !s.is_empty()).collect();
+        let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        Self::match_segments(&pattern, &path)
+    }
+    
+    fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", rest)) => (0..=path.len()).any(|i| Self::match_segments(rest, &path[i..])),
+            Some((segment, rest)) => match path.split_first() {
+                Some((name, path_rest)) => {
+                    let segment: Vec<char> = segment.chars().collect();
+                    let name: Vec<char> = name.chars().collect();
+                    Self::match_segment(&segment, &name) && Self::match_segments(rest, path_rest)
+                }
+                None => false,
+            },
+        }
+    }
+    
+    fn match_segment(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|i| Self::match_segment(rest, &name[i..])),
+            Some(('?', rest)) => !name.is_empty() && Self::match_segment(rest, &name[1..]),
+            Some((c, rest)) => name.first() == Some(c) && Self::match_segment(rest, &name[1..]),
+        }
+    }
+}
+
+/// ONNX graph optimization level used when loading the prediction model
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum OptimizationLevel {
+    Disabled,
+    Level1,
+    Level2,
+    Level3,
+}
+
+/// What the predictor does when its ONNX environment or model cannot be loaded
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum OnnxPolicy {
+    /// Fail predictor construction
+    Strict,
+    /// Log the error and predict with heuristics only
+    #[default]
+    Lenient,
+}
+
+impl PredictorConfig {
+    pub fn with_utc_offset_hours(mut self, hours: i32) -> Self {
+        self.utc_offset_minutes = hours * 60;
+        self
+    }
+    
+    pub fn with_utc_offset_minutes(mut self, minutes: i32) -> Self {
+        self.utc_offset_minutes = minutes;
+        self
+    }
+    
+    pub fn with_optimization_level(mut self, level: OptimizationLevel) -> Self {
+        self.optimization_level = level;
+        self
+    }
+    
+    pub fn with_onnx_policy(mut self, policy: OnnxPolicy) -> Self {
+        self.onnx_policy = policy;
+        self
+    }
+    
+    pub fn with_heuristic_weights(mut self, weights: HeuristicWeights) -> Self {
+        self.heuristic_weights = weights;
+        self
+    }
+    
+    pub fn utc_offset(&self) -> FixedOffset {
+        FixedOffset::east_opt(self.utc_offset_minutes * 60)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileAccessEvent {
     pub path: String,
@@ -105,6 +325,7 @@ pub struct FileAccessPattern {
     pub access_times: Vec<DateTime<Utc>>,
     pub average_interval: Option<f32>,  // average seconds between accesses
     pub related_files: HashMap<String, f32>,  // path -> correlation score
+    pub file_size: Option<u64>,  // size seen on the most recent sized access
 }
 
 impl FileAccessPattern {
@@ -116,6 +337,7 @@ impl FileAccessPattern {
             access_times: Vec::new(),
             average_interval: None,
             related_files: HashMap::new(),
+            file_size: None,
         }
     }
     
@@ -141,8 +363,14 @@ impl FileAccessPattern {
     }
     
     pub fn calculate_access_probability(&self, current_time: DateTime<Utc>) -> f32 {
+        PredictionExplanation::from_factors(self.path.clone(), self.probability_factors(current_time))
+            .probability
+    }
+    
+    /// Weighted factors behind `calculate_access_probability`
+    pub fn probability_factors(&self, current_time: DateTime<Utc>) -> Vec<PredictionFactor> {
         if self.access_count == 0 {
-            return 0.0;
+            return Vec::new();
         }
         
         // Factor 1: Recency (more recent = higher probability)
@@ -162,9 +390,72 @@ impl FileAccessPattern {
             _ => 0.0,
         };
         
-        // Combine factors
-        let combined_score = recency_score * 0.4 + frequency_score * 0.3 + pattern_score * 0.3;
-        combined_score.min(1.0).max(0.0)
+        vec![
+            PredictionFactor::new("recency", recency_score, 0.4),
+            PredictionFactor::new("frequency", frequency_score, 0.3),
+            PredictionFactor::new("pattern_regularity", pattern_score, 0.3),
+        ]
+    }
+}
+
+/// Snapshot of how much an `AccessPredictor` has learned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PredictorStats {
+    pub tracked_files: usize,
+    pub total_accesses: u64,
+    pub correlation_edges: usize,  // directed related-file entries
+    pub cached_predictions: usize,
+    pub cache_hit_rate: Option<f32>,  // None until a prediction has been looked up
+    pub top_directories: Vec<(String, usize)>,  // most accessed first, with access counts
+    pub top_extensions: Vec<(String, usize)>,
+}
+
+/// A single weighted input to a heuristic prediction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PredictionFactor {
+    pub name: String,
+    pub raw_value: f32,
+    pub weight: f32,
+    pub contribution: f32,  // raw_value * weight
+}
+
+impl PredictionFactor {
+    pub fn new(name: &str, raw_value: f32, weight: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            raw_value,
+            weight,
+            contribution: raw_value * weight,
+        }
+    }
+}
+
+/// Breakdown of a heuristic prediction into its contributing factors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PredictionExplanation {
+    pub file_path: String,
+    pub probability: f32,
+    pub factors: Vec<PredictionFactor>,
+}
+
+impl PredictionExplanation {
+    /// Combine factors into a probability. Contributions sum to `probability`
+    /// unless the total falls outside [0, 1] and has to be clamped.
+    pub fn from_factors(file_path: String, factors: Vec<PredictionFactor>) -> Self {
+        let total: f32 = factors.iter().map(|f| f.contribution).sum();
+        Self {
+            file_path,
+            probability: total.min(1.0).max(0.0),
+            factors,
+        }
+    }
+    
+    pub fn total_contribution(&self) -> f32 {
+        self.factors.iter().map(|f| f.contribution).sum()
+    }
+    
+    pub fn factor(&self, name: &str) -> Option<&PredictionFactor> {
+        self.factors.iter().find(|f| f.name == name)
     }
 }
 
@@ -181,6 +472,35 @@ mod tests {
         assert_eq!(event.access_type, AccessType::Read);
     }
     
+    #[test]
+    fn test_prefetch_rule_globs() {
+        let target = PrefetchRule::new("/project/target/**", PrefetchPolicy::Deny);
+        assert!(target.matches("/project/target/debug/main.d"));
+        assert!(target.matches("/project/target"));
+        assert!(!target.matches("/project/src/main.rs"));
+        
+        let modules = PrefetchRule::new("**/node_modules/**", PrefetchPolicy::Deny);
+        assert!(modules.matches("/web/app/node_modules/react/index.js"));
+        assert!(!modules.matches("/web/app/src/index.js"));
+        
+        let sources = PrefetchRule::new("/project/src/*.rs", PrefetchPolicy::Allow);
+        assert!(sources.matches("/project/src/lib.rs"));
+        assert!(!sources.matches("/project/src/bin/tool.rs"));
+        assert!(!sources.matches("/project/src/notes.md"));
+    }
+    
+    #[test]
+    fn test_prefetch_policy_resolution() {
+        let config = SchemeConfig::default()
+            .with_prefetch_rule("/project/src/generated/**", PrefetchPolicy::Deny)
+            .with_prefetch_rule("/project/src/**", PrefetchPolicy::Boost(0.3));
+        
+        assert_eq!(config.apply_prefetch_policy("/project/src/generated/api.rs", 0.9), None);
+        assert!((config.apply_prefetch_policy("/project/src/main.rs", 0.4).unwrap() - 0.7).abs() < 1e-6);
+        assert_eq!(config.apply_prefetch_policy("/project/src/main.rs", 0.9), Some(1.0));
+        assert_eq!(config.apply_prefetch_policy("/home/user/notes.txt", 0.4), Some(0.4));
+    }
+    
     #[test]
     fn test_file_size_category() {
         assert!(matches!(FileSizeCategory::from_size(500_000), FileSizeCategory::Small));